@@ -8,6 +8,22 @@ export interface BackupInfo {
 	path: string
 	backup_type: 'manual' | 'auto' | 'pre-restore'
 	included_folders: string[]
+	pinned?: boolean
+}
+
+// Mirrors backup_restore::RestoreReport
+export interface RestoreReport {
+	pre_restore: BackupInfo
+	replaced_folders: string[]
+	skipped_folders: string[] // listed in the backup but not present in it
+	restored_files: number
+}
+
+export interface RotationPlan {
+	dry_run: boolean
+	keep: { id: string, name: string, reasons: string[] }[]
+	delete: { id: string, name: string }[]
+	freed_bytes: number
 }
 
 export interface BackupSettings {
@@ -16,6 +32,9 @@ export interface BackupSettings {
 	max_backups: number
 	custom_path: string | null
 	included_folders: string[]
+	exclude_patterns?: string[]
+	compression?: 'deflate' | 'zstd' | 'store'
+	target_has_secret?: boolean // the target's secret is never loaded, leave it empty to keep it
 }
 
 export interface BackupStats {
@@ -25,8 +44,6 @@ export interface BackupStats {
 	newest_backup: string | null
 }
 
-const autoBackupIntervals = new Map<string, ReturnType<typeof setInterval>>()
-
 export function useBackupStore() {
 	const backups = useState<Record<string, BackupInfo[]>>('backups', () => ({}))
 	const settings = useState<Record<string, BackupSettings>>('backupSettings', () => ({}))
@@ -84,7 +101,7 @@ export function useBackupStore() {
 			await loadBackups(serverId, customPath || serverSettings.custom_path || undefined)
 
 			// Rotate if needed
-			await rotateBackups(serverId, serverSettings.max_backups, customPath || serverSettings.custom_path || undefined)
+			await rotateBackups(serverId, customPath || serverSettings.custom_path || undefined)
 
 			return backup
 		} catch (e) {
@@ -101,14 +118,14 @@ export function useBackupStore() {
 		backupId: string,
 		includedFolders?: string[],
 		customPath?: string
-	): Promise<BackupInfo | null> {
+	): Promise<RestoreReport> {
 		loading.value[serverId] = true
 		try {
 			const serverSettings = settings.value[serverId] || defaultSettings
 			const folders = includedFolders || serverSettings.included_folders
 
-			// Returns the pre-restore backup that was created
-			const preRestoreBackup = await invoke<BackupInfo>('backup_restore_cmd', {
+			// Reports the pre-restore backup that was created and which folders were replaced
+			const report = await invoke<RestoreReport>('backup_restore_cmd', {
 				serverPath,
 				serverId,
 				backupId,
@@ -119,7 +136,7 @@ export function useBackupStore() {
 			// Refresh list
 			await loadBackups(serverId, customPath || serverSettings.custom_path || undefined)
 
-			return preRestoreBackup
+			return report
 		} catch (e) {
 			console.error('Failed to restore backup:', e)
 			throw e
@@ -155,15 +172,15 @@ export function useBackupStore() {
 
 	async function rotateBackups(
 		serverId: string,
-		maxBackups: number,
 		customPath?: string
 	): Promise<number> {
 		try {
-			const deleted = await invoke<number>('backup_rotate_cmd', {
+			const plan = await invoke<RotationPlan>('backup_rotate_cmd', {
 				serverId,
-				maxBackups,
-				customPath: customPath || null
+				customPath: customPath || null,
+				dryRun: false
 			})
+			const deleted = plan.delete.length
 
 			if (deleted > 0) {
 				await loadBackups(serverId, customPath)
@@ -200,12 +217,10 @@ export function useBackupStore() {
 		}
 	}
 
-	async function loadSettings(serverId: string, customPath?: string): Promise<BackupSettings> {
+	// Settings always live in the default backup folder, custom_path only moves the backups
+	async function loadSettings(serverId: string): Promise<BackupSettings> {
 		try {
-			const loaded = await invoke<BackupSettings>('backup_load_settings_cmd', {
-				serverId,
-				customPath: customPath || null
-			})
+			const loaded = await invoke<BackupSettings>('backup_load_settings_cmd', { serverId })
 			settings.value[serverId] = loaded
 			return loaded
 		} catch (e) {
@@ -215,68 +230,20 @@ export function useBackupStore() {
 		}
 	}
 
-	async function saveSettings(
-		serverId: string,
-		newSettings: BackupSettings,
-		customPath?: string
-	): Promise<void> {
+	async function saveSettings(serverId: string, newSettings: BackupSettings): Promise<void> {
 		try {
 			await invoke('backup_save_settings_cmd', {
 				serverId,
-				settings: newSettings,
-				customPath: customPath || null
+				settings: newSettings
 			})
+			// Automatic backups are run by the backend scheduler from these settings
 			settings.value[serverId] = newSettings
-
-			// Update auto-backup interval if settings changed
-			if (newSettings.enabled) {
-				startAutoBackup(serverId, '', newSettings)
-			} else {
-				stopAutoBackup(serverId)
-			}
 		} catch (e) {
 			console.error('Failed to save backup settings:', e)
 			throw e
 		}
 	}
 
-	function startAutoBackup(serverId: string, serverPath: string, serverSettings?: BackupSettings) {
-		// Stop existing interval if any
-		stopAutoBackup(serverId)
-
-		const s = serverSettings || settings.value[serverId]
-		if (!s || !s.enabled) return
-
-		const intervalMs = s.interval_minutes * 60 * 1000
-
-		const interval = setInterval(async () => {
-			// Check if server is running before creating backup
-			const processStore = useServerProcessStore()
-			const status = processStore.getStatus(serverId)
-
-			if (status === 'online') {
-				console.log(`Auto-backup triggered for server ${serverId}`)
-				try {
-					await createBackup(serverPath, serverId, 'auto', s.included_folders, s.custom_path || undefined)
-				} catch (e) {
-					console.error('Auto-backup failed:', e)
-				}
-			}
-		}, intervalMs)
-
-		autoBackupIntervals.set(serverId, interval)
-		console.log(`Auto-backup scheduled for ${serverId} every ${s.interval_minutes} minutes`)
-	}
-
-	function stopAutoBackup(serverId: string) {
-		const interval = autoBackupIntervals.get(serverId)
-		if (interval) {
-			clearInterval(interval)
-			autoBackupIntervals.delete(serverId)
-			console.log(`Auto-backup stopped for ${serverId}`)
-		}
-	}
-
 	function getBackups(serverId: string): BackupInfo[] {
 		return backups.value[serverId] || []
 	}
@@ -316,8 +283,6 @@ export function useBackupStore() {
 		getAvailableFolders,
 		loadSettings,
 		saveSettings,
-		startAutoBackup,
-		stopAutoBackup,
 		getBackups,
 		getSettings,
 		isLoading,
@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
 
 export interface JavaInstallation {
 	path: string
@@ -18,15 +19,70 @@ export interface JavaValidation {
 	error?: string
 }
 
-export interface AdoptiumRelease {
+export type JavaDistribution = 'temurin' | 'zulu' | 'corretto' | 'microsoft'
+
+export const JAVA_DISTRIBUTIONS: { value: JavaDistribution, label: string }[] = [
+	{ value: 'temurin', label: 'Eclipse Temurin' },
+	{ value: 'zulu', label: 'Azul Zulu' },
+	{ value: 'corretto', label: 'Amazon Corretto' },
+	{ value: 'microsoft', label: 'Microsoft OpenJDK' }
+]
+
+export interface JavaRelease {
 	version: string
 	major: number
+	distribution: JavaDistribution
 	download_url: string
 	filename: string
 	size: number
 	checksum: string | null
 }
 
+export interface JavaDownloadProgress {
+	major: number
+	job_id: string
+	bytes_done: number
+	bytes_total: number
+	attempt: number
+}
+
+export interface ManagedRuntime {
+	id: string
+	major: number
+	distribution: JavaDistribution
+	version: string | null
+	vendor: string | null
+	java_path: string
+	size_bytes: number
+	installed_at: string | null
+	used_by: string[]
+	superseded: boolean
+	latest_version: string | null
+	update_available: boolean
+}
+
+export interface RuntimeUpdate {
+	runtime: ManagedRuntime | null
+	repointed: string[]
+	kept: string[]
+	removed: string[]
+}
+
+export interface JavaRequirement {
+	min_major: number
+	max_major: number | null
+	recommended_major: number
+	mc_version: string | null
+	loader: string
+	source: string
+}
+
+export interface JavaResolution {
+	requirement: JavaRequirement
+	installation: JavaInstallation | null
+	download: JavaRelease | null
+}
+
 export const useJava = () => {
 	// Global state shared across all components
 	const installations = useState<JavaInstallation[]>('java-installations', () => [])
@@ -58,17 +114,53 @@ export const useJava = () => {
 		}
 	}
 
-	const fetchAdoptiumRelease = async (major: number): Promise<AdoptiumRelease | null> => {
+	// Without a distribution, the first one that builds this major for this machine is used
+	const fetchJavaRelease = async (major: number, distribution?: JavaDistribution): Promise<JavaRelease | null> => {
 		try {
-			return await invoke<AdoptiumRelease>('fetch_adoptium_release_cmd', { major })
+			return await invoke<JavaRelease>('fetch_java_release_cmd', { major, distribution })
 		} catch (e) {
-			console.error(`Failed to fetch Adoptium release for Java ${major}:`, e)
+			console.error(`Failed to fetch release for Java ${major}:`, e)
 			return null
 		}
 	}
 
-	const downloadJava = async (major: number, installDir: string): Promise<string> => {
-		return await invoke<string>('download_java_cmd', { major, installDir })
+	// Downloads are resumed and retried on flaky connections, and checked against the vendor's SHA-256
+	const downloadJava = async (
+		major: number,
+		installDir: string,
+		onProgress?: (progress: JavaDownloadProgress) => void,
+		distribution?: JavaDistribution
+	): Promise<string> => {
+		const unlisten = onProgress
+			? await listen<JavaDownloadProgress>('java-download-progress', (event) => {
+				if (event.payload.major === major) onProgress(event.payload)
+			})
+			: null
+		try {
+			return await invoke<string>('download_java_cmd', { major, installDir, distribution })
+		} finally {
+			unlisten?.()
+		}
+	}
+
+	// Required Java range of a server, its best installed match, or the release to download
+	const resolveJava = async (serverPath: string): Promise<JavaResolution> => {
+		return await invoke<JavaResolution>('java_resolve_cmd', { serverPath })
+	}
+
+	// Runtimes downloaded by VoidLink, with the servers and settings using them
+	const listManagedRuntimes = async (checkUpdates = false): Promise<ManagedRuntime[]> => {
+		return await invoke<ManagedRuntime[]>('java_runtime_list_cmd', { checkUpdates })
+	}
+
+	// Install the latest release of a major version; stopped servers and settings move over to it
+	const updateManagedRuntime = async (major: number, distribution?: JavaDistribution): Promise<RuntimeUpdate> => {
+		return await invoke<RuntimeUpdate>('java_runtime_update_cmd', { major, distribution })
+	}
+
+	// Fails while a server or setting still uses the runtime
+	const removeManagedRuntime = async (runtimeId: string): Promise<void> => {
+		await invoke('java_runtime_remove_cmd', { runtimeId })
 	}
 
 	/**
@@ -111,8 +203,12 @@ export const useJava = () => {
 		error,
 		scanJava,
 		validateJavaPath,
-		fetchAdoptiumRelease,
+		fetchJavaRelease,
 		downloadJava,
+		resolveJava,
+		listManagedRuntimes,
+		updateManagedRuntime,
+		removeManagedRuntime,
 		getJavaForVersion
 	}
 }
@@ -0,0 +1,78 @@
+import { invoke } from '@tauri-apps/api/core'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
+
+export interface JobProgress {
+	done: number
+	total: number
+	message: string
+	eta_secs: number | null
+}
+
+export interface JobInfo {
+	id: string
+	kind: string
+	label: string
+	started_at: string
+	progress: JobProgress | null
+	cancelling: boolean
+}
+
+export interface JobFinished {
+	job: JobInfo | null
+	job_id: string
+	status: 'completed' | 'failed' | 'cancelled'
+	error: string | null
+}
+
+let unlisteners: UnlistenFn[] = []
+
+export const useJobs = () => {
+	// Global state shared across all components
+	const jobs = useState<JobInfo[]>('jobs-running', () => [])
+
+	const upsert = (job: JobInfo) => {
+		const index = jobs.value.findIndex(j => j.id === job.id)
+		if (index === -1) jobs.value.push(job)
+		else jobs.value[index] = job
+	}
+
+	const refreshJobs = async () => {
+		try {
+			jobs.value = await invoke<JobInfo[]>('job_list')
+		} catch (e) {
+			console.error('Failed to list jobs:', e)
+		}
+	}
+
+	const cancelJob = async (jobId: string) => {
+		await invoke('job_cancel', { jobId })
+		const job = jobs.value.find(j => j.id === jobId)
+		if (job) job.cancelling = true
+	}
+
+	const startListening = async (onFinished?: (event: JobFinished) => void) => {
+		if (unlisteners.length > 0) return
+		await refreshJobs()
+		unlisteners = await Promise.all([
+			listen<{ job: JobInfo }>('job-started', (event) => upsert(event.payload.job)),
+			listen<{ job: JobInfo }>('job-progress', (event) => upsert(event.payload.job)),
+			listen<JobFinished>('job-finished', (event) => {
+				jobs.value = jobs.value.filter(j => j.id !== event.payload.job_id)
+				onFinished?.(event.payload)
+			})
+		])
+	}
+
+	const stopListening = () => {
+		unlisteners.forEach(unlisten => unlisten())
+		unlisteners = []
+	}
+
+	return {
+		jobs,
+		refreshJobs,
+		cancelJob,
+		startListening,
+		stopListening
+	}
+}
@@ -1,28 +1,101 @@
-import type { Child } from '@tauri-apps/plugin-shell'
 import { invoke } from '@tauri-apps/api/core'
-import { writeTextFile, readTextFile, exists, BaseDirectory } from '@tauri-apps/plugin-fs'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
 
 export interface ProcessInfo {
 	memory_bytes: number
 	cpu_usage: number
 }
 
+// One sample of the backend's metrics sampler (every 2 seconds)
+export interface MetricSample {
+	timestamp: number
+	cpu_usage: number
+	memory_bytes: number
+	threads: number | null
+	open_files: number | null
+	disk_read_bytes_per_sec: number
+	disk_write_bytes_per_sec: number
+}
+
+export type MetricResolution = 'raw' | 'minute' | 'quarter'
+
+// Stored point; aggregated ones (minute, quarter hour) start at `timestamp`
+export interface MetricPoint {
+	timestamp: number
+	cpu_avg: number
+	cpu_max: number
+	memory_avg: number
+	memory_max: number
+	tps: number | null
+	players: number | null
+	samples: number
+}
+
+export interface MetricSeries {
+	server_id: string
+	resolution: MetricResolution
+	points: MetricPoint[]
+}
+
+// In-game numbers read over RCON by the backend, see server_perf.rs
+export interface ServerPerformance {
+	timestamp: number
+	tps: number | null
+	mspt: number | null
+	entities: number | null
+	source: 'spark' | 'paper' | 'forge' | 'neoforge' | 'vanilla' | 'debug'
+}
+
+// Stage of the backend's stop ladder (stop command, then SIGTERM, then SIGKILL), see server::server_stop
+export type StopStage = 'already_stopped' | 'stop_command' | 'terminate' | 'kill' | 'failed'
+
+export const STOP_STAGE_LABELS: Record<StopStage, string> = {
+	already_stopped: 'Already stopped',
+	stop_command: 'Stop command',
+	terminate: 'SIGTERM',
+	kill: 'SIGKILL',
+	failed: 'Failed'
+}
+
+// A server as seen by the backend supervisor, see server::server_get_status
+export interface SupervisorStatus {
+	server_id: string
+	is_running: boolean
+	is_online: boolean
+	stop_requested: boolean
+	pid: number | null
+	server_path: string | null
+	started_at: string | null
+	exit_code: number | null
+	restart_pending: boolean
+	consecutive_crashes: number
+}
+
+export interface ServerStopResult {
+	server_id: string
+	stopped: boolean
+	stage: StopStage
+	elapsed_ms: number
+	message: string
+}
+
 export interface ServerProcess {
 	status: 'offline' | 'starting' | 'online' | 'stopping'
-	process: Child | null
-	pid: number | null  // Store PID separately for recovery
+	supervised: boolean // Started through the backend, console input goes to its stdin
+	pid: number | null
+	stopStage: StopStage | null
 	consoleLines: string[]
 	memoryBytes: number
 	cpuUsage: number
 	tps: number
+	mspt: number | null
+	entities: number | null
+	perfSource: ServerPerformance['source'] | null
 	history: { timestamp: number; cpu: number; memory: number }[]
 }
 
-interface PersistedPIDs {
-	[serverId: string]: number
-}
-
-const PIDS_FILE = 'VoidLink/running-servers.json'
+let metricsUnlisteners: UnlistenFn[] = []
+let serverListeners: Promise<UnlistenFn[]> | null = null
 
 // Global state for server processes using Nuxt's useState
 export function useServerProcessStore() {
@@ -32,72 +105,61 @@ export function useServerProcessStore() {
 		if (!servers.value[serverId]) {
 			servers.value[serverId] = {
 				status: 'offline',
-				process: null,
+				supervised: false,
 				pid: null,
+				stopStage: null,
 				consoleLines: [],
 				memoryBytes: 0,
 				cpuUsage: 0,
 				tps: 0,
+				mspt: null,
+				entities: null,
+				perfSource: null,
 				history: []
 			}
 		}
 		return servers.value[serverId]
 	}
 
-	// Save running PIDs to file
-	async function persistPIDs() {
-		const pids: PersistedPIDs = {}
-		for (const [serverId, server] of Object.entries(servers.value)) {
-			const pid = server.process?.pid || server.pid
-			if (pid && server.status !== 'offline') {
-				pids[serverId] = pid
-			}
-		}
-		console.log('Persisting PIDs:', pids)
+	function applySupervisorStatus(status: SupervisorStatus) {
+		const server = getServer(status.server_id)
+		server.supervised = status.is_running
+		server.pid = status.pid
+		server.status = !status.is_running
+			? 'offline'
+			: status.stop_requested ? 'stopping' : status.is_online ? 'online' : 'starting'
+	}
+
+	// Pick up a server the backend is still running, e.g. after the webview was reloaded
+	async function reattach(serverId: string) {
+		await startServerListeners()
+		// Already attached, the listeners keep it up to date
+		if (getServer(serverId).supervised) return
 		try {
-			await writeTextFile(PIDS_FILE, JSON.stringify(pids), { baseDir: BaseDirectory.Document })
-			console.log('PIDs persisted successfully')
+			const status = await invoke<SupervisorStatus>('server_get_status', { serverId })
+			if (!status.is_running) return
+			applySupervisorStatus(status)
+			getServer(serverId).consoleLines = await invoke<string[]>('server_get_console', { serverId })
 		} catch (e) {
-			console.error('Failed to persist PIDs:', e)
+			console.error('Failed to reattach to server:', serverId, e)
 		}
 	}
 
-	// Recover running servers after reload
+	// Reattach to every server the backend supervises
 	async function recoverRunningServers() {
+		await startServerListeners()
 		try {
-			if (!await exists(PIDS_FILE, { baseDir: BaseDirectory.Document })) {
-				return
-			}
-
-			const content = await readTextFile(PIDS_FILE, { baseDir: BaseDirectory.Document })
-			const pids: PersistedPIDs = JSON.parse(content)
-
-			for (const [serverId, pid] of Object.entries(pids)) {
-				// Check if process is still running
-				const info = await invoke<ProcessInfo | null>('get_process_info', { pid })
-				if (info) {
-					console.log('Recovered running server:', serverId, 'PID:', pid)
-					const server = getServer(serverId)
-					server.status = 'online'
-					server.pid = pid
-					server.memoryBytes = info.memory_bytes
-					server.cpuUsage = info.cpu_usage
-				}
+			const running = await invoke<SupervisorStatus[]>('server_list_running')
+			for (const status of running) {
+				console.log('Recovered running server:', status.server_id, 'PID:', status.pid)
+				applySupervisorStatus(status)
+				getServer(status.server_id).consoleLines = await invoke<string[]>('server_get_console', { serverId: status.server_id })
 			}
 		} catch (e) {
 			console.error('Failed to recover running servers:', e)
 		}
 	}
 
-	// Clear persisted PIDs file
-	async function clearPersistedPIDs() {
-		try {
-			await writeTextFile(PIDS_FILE, '{}', { baseDir: BaseDirectory.Document })
-		} catch (e) {
-			// Ignore
-		}
-	}
-
 	function setStatus(serverId: string, status: ServerProcess['status']) {
 		const server = getServer(serverId)
 		server.status = status
@@ -106,20 +168,19 @@ export function useServerProcessStore() {
 			server.memoryBytes = 0
 			server.cpuUsage = 0
 			server.tps = 0
+			server.mspt = null
+			server.entities = null
+			server.perfSource = null
 			server.history = []
 			server.pid = null
+			server.supervised = false
+			server.stopStage = null
 		}
-
-		// Persist PIDs whenever status changes
-		persistPIDs()
 	}
 
-	function setProcess(serverId: string, process: Child | null) {
-		const server = getServer(serverId)
-		server.process = process
-		server.pid = process?.pid || null
-		// Persist whenever process is set
-		persistPIDs()
+	// Write a line to the server's stdin through the supervisor
+	async function sendInput(serverId: string, command: string) {
+		await invoke('server_send_input', { serverId, command })
 	}
 
 	function setTps(serverId: string, tps: number) {
@@ -143,13 +204,8 @@ export function useServerProcessStore() {
 		return getServer(serverId).status
 	}
 
-	function getProcess(serverId: string): Child | null {
-		return getServer(serverId).process
-	}
-
 	function getPid(serverId: string): number | null {
-		const server = getServer(serverId)
-		return server.process?.pid || server.pid
+		return getServer(serverId).pid
 	}
 
 	function getConsoleLines(serverId: string): string[] {
@@ -170,7 +226,7 @@ export function useServerProcessStore() {
 
 	async function refreshProcessInfo(serverId: string) {
 		const server = getServer(serverId)
-		const pid = server.process?.pid || server.pid
+		const pid = server.pid
 
 		if (!pid) {
 			server.memoryBytes = 0
@@ -179,27 +235,15 @@ export function useServerProcessStore() {
 		}
 
 		try {
-			const info = await invoke<ProcessInfo | null>('get_process_info', { pid })
+			// Also makes sure the backend samples this PID; history arrives through "server-metrics"
+			const info = await invoke<ProcessInfo | null>('get_process_info', { pid, serverId })
 			if (info) {
 				server.memoryBytes = info.memory_bytes
 				server.cpuUsage = info.cpu_usage
-
-				server.history.push({
-					timestamp: Date.now(),
-					cpu: info.cpu_usage,
-					memory: info.memory_bytes
-				})
-
-				// Keep last 60 points (approx 2 minutes at 2s interval)
-				if (server.history.length > 60) {
-					server.history.shift()
-				}
 			} else {
 				// Process no longer running
 				if (server.status === 'online') {
-					server.status = 'offline'
-					server.pid = null
-					persistPIDs()
+					setStatus(serverId, 'offline')
 				}
 			}
 		} catch (e) {
@@ -207,33 +251,121 @@ export function useServerProcessStore() {
 		}
 	}
 
-	// Kill all running servers - call on app exit
-	async function killAllServers() {
-		for (const [serverId, server] of Object.entries(servers.value)) {
-			const pid = server.process?.pid || server.pid
-			if (pid && server.status !== 'offline') {
-				try {
-					console.log('Killing server process:', serverId, 'PID:', pid)
-					// Use Rust command to kill entire process tree (important for Java)
-					await invoke('kill_process', { pid })
-				} catch (e) {
-					console.error('Failed to kill server:', serverId, e)
-				}
-			}
+	function pushSample(server: ServerProcess, sample: MetricSample) {
+		server.history.push({
+			timestamp: sample.timestamp,
+			cpu: sample.cpu_usage,
+			memory: sample.memory_bytes
+		})
+		// Keep last 60 points (approx 2 minutes at 2s interval)
+		if (server.history.length > 60) {
+			server.history.shift()
 		}
-		// Clear persisted PIDs after killing
-		await clearPersistedPIDs()
+	}
+
+	// Rebuild the graph from the backend's history, e.g. after navigating back to a server
+	async function loadHistory(serverId: string, minutes = 2) {
+		try {
+			const samples = await invoke<MetricSample[]>('metrics_history_cmd', { serverId, minutes })
+			const server = getServer(serverId)
+			server.history = []
+			samples.forEach(sample => pushSample(server, sample))
+		} catch (e) {
+			console.error('Failed to load metrics history:', e)
+		}
+	}
+
+	// Long-term metrics from the backend's store, e.g. the last 7 days; the finest stored resolution is picked when not given
+	async function queryMetrics(serverId: string, from: number, to?: number, resolution?: MetricResolution) {
+		return await invoke<MetricSeries>('metrics_query_cmd', { serverId, from, to, resolution })
+	}
+
+	// Live samples pushed by the backend for every tracked server
+	async function startMetricsListener() {
+		if (metricsUnlisteners.length > 0) return
+		metricsUnlisteners = await Promise.all([
+			listen<{ server_id: string, sample: MetricSample }>('server-metrics', (event) => {
+				const server = getServer(event.payload.server_id)
+				if (server.status === 'offline') return
+				server.memoryBytes = event.payload.sample.memory_bytes
+				server.cpuUsage = event.payload.sample.cpu_usage
+				pushSample(server, event.payload.sample)
+			}),
+			listen<{ server_id: string, performance: ServerPerformance }>('server-performance', (event) => {
+				const server = getServer(event.payload.server_id)
+				if (server.status === 'offline') return
+				const { tps, mspt, entities, source } = event.payload.performance
+				if (tps !== null) server.tps = tps
+				server.mspt = mspt
+				server.entities = entities
+				server.perfSource = source
+			})
+		])
+	}
+
+	// Have the backend poll TPS/MSPT over RCON while the server runs
+	async function watchPerformance(serverId: string, serverPath: string, rcon: { host: string, port: number, password: string } | null) {
+		try {
+			if (rcon) await invoke('server_perf_register', { serverId, serverPath, rcon })
+			else await invoke('server_perf_unregister', { serverId })
+		} catch (e) {
+			console.error('Failed to update performance polling:', e)
+		}
+	}
+
+	// Console, status and restarts of supervised servers, pushed by the backend
+	function startServerListeners() {
+		if (serverListeners) return serverListeners
+		serverListeners = Promise.all([
+			listen<{ server_id: string, pid: number }>('server-started', (event) => {
+				const server = getServer(event.payload.server_id)
+				server.supervised = true
+				server.pid = event.payload.pid
+				server.status = 'starting'
+				server.stopStage = null
+			}),
+			listen<{ server_id: string, type: string, message: string }>('server-output', (event) => {
+				// Filter RCON spam
+				if (event.payload.message.includes('Thread RCON Client /127.0.0.1')) return
+				addConsoleLine(event.payload.server_id, event.payload.message)
+			}),
+			listen<{ server_id: string }>('server-online', (event) => {
+				getServer(event.payload.server_id).status = 'online'
+			}),
+			listen<{ server_id: string, stage: StopStage }>('server-stopping', (event) => {
+				const server = getServer(event.payload.server_id)
+				server.status = 'stopping'
+				server.stopStage = event.payload.stage
+				if (event.payload.stage !== 'stop_command') {
+					addConsoleLine(event.payload.server_id, `[VoidLink] Server is not stopping, escalating to ${STOP_STAGE_LABELS[event.payload.stage]}`)
+				}
+			}),
+			listen<{ server_id: string, code: number | null }>('server-stopped', (event) => {
+				addConsoleLine(event.payload.server_id, `Server stopped with code ${event.payload.code}`)
+				setStatus(event.payload.server_id, 'offline')
+			}),
+			listen<{ server_id: string, code: number | null, crash_report: { name: string } | null }>('server-crashed', (event) => {
+				const report = event.payload.crash_report ? `, see ${event.payload.crash_report.name}` : ''
+				addConsoleLine(event.payload.server_id, `[VoidLink] Server crashed with code ${event.payload.code}${report}`)
+			}),
+			listen<{ server_id: string, attempt: number, delay_secs: number }>('server-restart-scheduled', (event) => {
+				addConsoleLine(event.payload.server_id, `[VoidLink] Restarting in ${event.payload.delay_secs}s (attempt ${event.payload.attempt})`)
+			}),
+			listen<{ server_id: string, reason: string }>('server-restart-gave-up', (event) => {
+				addConsoleLine(event.payload.server_id, `[VoidLink] Not restarting: ${event.payload.reason}`)
+			})
+		])
+		return serverListeners
 	}
 
 	return {
 		servers,
 		getServer,
 		setStatus,
-		setProcess,
+		sendInput,
 		addConsoleLine,
 		clearConsole,
 		getStatus,
-		getProcess,
 		getPid,
 		getConsoleLines,
 		getMemoryBytes,
@@ -241,8 +373,12 @@ export function useServerProcessStore() {
 		getTps,
 		setTps,
 		refreshProcessInfo,
-		killAllServers,
-		recoverRunningServers,
-		persistPIDs
+		loadHistory,
+		queryMetrics,
+		startMetricsListener,
+		watchPerformance,
+		startServerListeners,
+		reattach,
+		recoverRunningServers
 	}
 }
@@ -1,6 +1,7 @@
 import { defineStore } from 'pinia'
 import { readDir, readTextFile, writeTextFile, BaseDirectory, exists } from '@tauri-apps/plugin-fs'
 import { join } from '@tauri-apps/api/path'
+import { invoke } from '@tauri-apps/api/core'
 
 export interface ServerMeta {
 	id: string
@@ -19,6 +20,78 @@ export interface ServerMeta {
 		path: string
 		flags: string
 	}
+	launchProfile?: Partial<LaunchProfile>
+	restartPolicy?: Partial<RestartPolicy>
+}
+
+// Mirrors restart::RestartPolicy
+export interface RestartPolicy {
+	enabled: boolean
+	max_restarts_per_window: number
+	window_minutes: number
+	initial_backoff_secs: number
+	max_backoff_secs: number
+	give_up_after: number // consecutive crashes without finishing startup
+}
+
+export const DEFAULT_RESTART_POLICY: RestartPolicy = {
+	enabled: false,
+	max_restarts_per_window: 5,
+	window_minutes: 60,
+	initial_backoff_secs: 10,
+	max_backoff_secs: 300,
+	give_up_after: 3
+}
+
+export type GarbageCollector = 'default' | 'g1' | 'zgc' | 'generational-zgc' | 'shenandoah' | 'parallel' | 'serial'
+export type FlagPreset = 'none' | 'aikar' | 'zgc' | 'low-memory'
+
+export const FLAG_PRESETS: { value: FlagPreset, label: string }[] = [
+	{ value: 'none', label: 'None' },
+	{ value: 'aikar', label: "Aikar's flags (G1)" },
+	{ value: 'zgc', label: 'Generational ZGC (Java 21+)' },
+	{ value: 'low-memory', label: 'Low memory' }
+]
+
+export interface LaunchProfile {
+	java_path: string | null
+	max_memory_mb: number
+	min_memory_mb: number | null
+	gc: GarbageCollector
+	preset: FlagPreset
+	extra_args: string[]
+	env: Record<string, string>
+}
+
+export interface LaunchPlan {
+	java_path: string
+	java_major: number
+	jvm_args: string[]
+	env: Record<string, string>
+	warnings: string[]
+}
+
+export interface BundleManifest {
+	format_version: number
+	exported_at: string
+	app_version: string
+	server_name: string
+	mc_version: string
+	loader: string
+	java_major: number
+	jar_file: string
+	file_count: number
+	total_size_bytes: number
+	files: { path: string, size: number, sha256: string }[]
+}
+
+export interface ImportedServer {
+	id: string
+	folder: string
+	name: string
+	path: string
+	java_path: string
+	java_downloaded: boolean
 }
 
 export const useServersStore = defineStore('servers', () => {
@@ -154,6 +227,36 @@ export const useServersStore = defineStore('servers', () => {
 		return processStore.getCpuUsage(serverId)
 	}
 
+	// Export a server into a portable bundle (runs as a job, see useJobs)
+	async function exportServer(serverPath: string, destination: string, excludePatterns?: string[]) {
+		return await invoke<BundleManifest>('server_export_cmd', { serverPath, destination, excludePatterns })
+	}
+
+	async function inspectBundle(bundlePath: string) {
+		return await invoke<BundleManifest>('server_bundle_inspect_cmd', { bundlePath })
+	}
+
+	// Recreate a server from a bundle, downloading the Java it needs if missing
+	async function importServer(bundlePath: string, name?: string) {
+		const imported = await invoke<ImportedServer>('server_import_cmd', { bundlePath, name })
+		await refreshServers()
+		return imported
+	}
+
+	// Saved profile, with the memory, Java path and flags from javaSettings applied
+	async function getLaunchProfile(serverPath: string) {
+		return await invoke<LaunchProfile>('launch_profile_get_cmd', { serverPath })
+	}
+
+	async function saveLaunchProfile(serverPath: string, profile: LaunchProfile) {
+		await invoke('launch_profile_save_cmd', { serverPath, profile })
+	}
+
+	// Checks the profile against the Java it runs on and the machine's memory; throws when it cannot start
+	async function buildLaunchPlan(serverPath: string, profile?: LaunchProfile) {
+		return await invoke<LaunchPlan>('launch_profile_build_cmd', { serverPath, profile })
+	}
+
 	function formatBytes(bytes: number, decimals = 1): string {
 		if (bytes === 0) return '0 B'
 		const k = 1024
@@ -175,7 +278,13 @@ export const useServersStore = defineStore('servers', () => {
 		getServerMemory,
 		getServerCpu,
 		formatBytes,
-		updateServerOrder
+		updateServerOrder,
+		exportServer,
+		inspectBundle,
+		importServer,
+		getLaunchProfile,
+		saveLaunchProfile,
+		buildLaunchPlan
 	}
 })
 
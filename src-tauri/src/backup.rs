@@ -1,11 +1,45 @@
 use serde::{Deserialize, Serialize};
-use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::fs;
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
-use zip::write::FileOptions;
-use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
 use chrono::Local;
+use tauri::Emitter;
+use crate::backup_archive::{self, ArchiveProgress, BackupCompression, ProgressTracker};
+use crate::backup_crypto::{self, BackupKey};
+use crate::backup_filter::{self, BackupEntry, BackupFilter};
+use crate::backup_restore::{self, RestoreReport};
+use crate::backup_retention::{self, RetentionPolicy, RotationPlan};
+use crate::backup_snapshot;
+use crate::backup_target::{self, BackupTargetConfig};
+use crate::backup_verify;
+use crate::jobs::{self, Job, JobProgress};
+use crate::rcon::{self, RconCredentials};
+use crate::server;
+
+// How long to wait for "Saved the game" after `save-all flush`
+const SAVE_FLUSH_TIMEOUT_SECS: u64 = 120;
+
+// How a backup is stored on disk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupFormat {
+    #[default]
+    Zip, // self-contained zip archive
+    Snapshot, // manifest referencing the deduplicated chunk store
+}
+
+// Whether the world could have been written to while it was archived
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackupConsistency {
+    HotConsistent, // server running, saving disabled and flushed via RCON
+    HotUnsafe, // server running without save-off, region files may be torn
+    Cold, // server was stopped
+    #[default]
+    Unknown, // created before consistency was tracked
+}
 
 // Backup metadata structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,10 +47,26 @@ pub struct BackupInfo {
     pub id: String,
     pub name: String,
     pub created_at: String,
-    pub size_bytes: u64,
+    pub size_bytes: u64, // for snapshots: new data added to the chunk store
     pub path: String,
     pub backup_type: String, // "manual" or "auto"
     pub included_folders: Vec<String>,
+    #[serde(default)]
+    pub format: BackupFormat,
+    #[serde(default)]
+    pub logical_size_bytes: u64, // uncompressed size of the backed up files
+    #[serde(default)]
+    pub consistency: BackupConsistency,
+    #[serde(default)]
+    pub sha256: Option<String>, // archive (or snapshot manifest) checksum
+    #[serde(default)]
+    pub remote_key: Option<String>, // set once uploaded to the configured backup target
+    #[serde(default)]
+    pub encrypted: bool, // archive and entry manifest are encrypted with the server's backup key
+    #[serde(default)]
+    pub key: Option<backup_crypto::KeyRecord>, // which key, None for backups made before it was recorded
+    #[serde(default)]
+    pub pinned: bool, // never deleted by rotation
 }
 
 // Backup settings per server
@@ -27,6 +77,39 @@ pub struct BackupSettings {
     pub max_backups: u32,
     pub custom_path: Option<String>,
     pub included_folders: Vec<String>,
+    #[serde(default)]
+    pub format: BackupFormat,
+    #[serde(default)]
+    pub target: Option<BackupTargetConfig>, // remote copy of every zip backup
+    #[serde(default = "default_keep_local_copy")]
+    pub keep_local_copy: bool, // keep the archive locally after uploading it
+    #[serde(default)]
+    pub encrypt: bool, // encrypt zip backups with the server's backup key
+    #[serde(default)]
+    pub retention: Option<RetentionPolicy>, // None keeps the newest `max_backups` auto backups
+    #[serde(default = "backup_filter::default_exclude_patterns")]
+    pub exclude_patterns: Vec<String>, // gitignore syntax, relative to the server directory
+    #[serde(default)]
+    pub compression: BackupCompression, // zip backups only
+}
+
+// Settings as sent to the UI, without the target's password or secret key. Saving them back
+// with the secret left empty keeps the stored one.
+#[derive(Debug, Clone, Serialize)]
+pub struct RedactedBackupSettings {
+    #[serde(flatten)]
+    pub settings: BackupSettings,
+    pub target_has_secret: bool,
+}
+
+fn default_keep_local_copy() -> bool {
+    true
+}
+
+impl BackupSettings {
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        self.retention.clone().unwrap_or_else(|| RetentionPolicy::keep_last(self.max_backups))
+    }
 }
 
 impl Default for BackupSettings {
@@ -41,6 +124,13 @@ impl Default for BackupSettings {
                 "world_nether".to_string(),
                 "world_the_end".to_string(),
             ],
+            format: BackupFormat::Zip,
+            target: None,
+            keep_local_copy: true,
+            encrypt: false,
+            retention: None,
+            exclude_patterns: backup_filter::default_exclude_patterns(),
+            compression: BackupCompression::Deflate,
         }
     }
 }
@@ -50,37 +140,22 @@ impl Default for BackupSettings {
 pub struct BackupStats {
     pub total_backups: u32,
     pub total_size_bytes: u64,
+    #[serde(default)]
+    pub remote_size_bytes: Option<u64>, // bytes stored on the backup target, None without one or while it is unreachable
     pub oldest_backup: Option<String>,
     pub newest_backup: Option<String>,
 }
 
-// Default exclusion patterns (files/folders to skip)
-const DEFAULT_EXCLUSIONS: &[&str] = &[
-    ".log",
-    ".log.gz",
-    "crash-reports",
-    ".mixin.out",
-    "logs",
-    ".cache",
-    "libraries",
-];
-
-fn should_exclude(path: &Path, exclusions: &[&str]) -> bool {
-    let path_str = path.to_string_lossy().to_lowercase();
-    let file_name = path.file_name()
-        .map(|n| n.to_string_lossy().to_lowercase())
-        .unwrap_or_default();
-    
-    for exclusion in exclusions {
-        let excl_lower = exclusion.to_lowercase();
-        if file_name.ends_with(&excl_lower) || file_name == excl_lower || path_str.contains(&excl_lower) {
-            return true;
-        }
+// VoidLink's folder in Documents, a scratch folder while testing
+pub(crate) fn voidlink_dir() -> Result<PathBuf, String> {
+    if cfg!(test) {
+        return Ok(std::env::temp_dir().join(format!("voidlink-test-{}", std::process::id())));
     }
-    false
+    let docs = dirs::document_dir().ok_or("Could not find Documents directory")?;
+    Ok(docs.join("VoidLink"))
 }
 
-fn get_backup_dir(server_id: &str, custom_path: Option<&str>) -> Result<PathBuf, String> {
+pub(crate) fn get_backup_dir(server_id: &str, custom_path: Option<&str>) -> Result<PathBuf, String> {
     match custom_path {
         Some(path) => {
             let custom = PathBuf::from(path);
@@ -89,9 +164,7 @@ fn get_backup_dir(server_id: &str, custom_path: Option<&str>) -> Result<PathBuf,
             Ok(backup_dir)
         }
         None => {
-            let docs = dirs::document_dir()
-                .ok_or("Could not find Documents directory")?;
-            let backup_dir = docs.join("VoidLink").join("Backups").join(server_id);
+            let backup_dir = voidlink_dir()?.join("Backups").join(server_id);
             fs::create_dir_all(&backup_dir).map_err(|e| e.to_string())?;
             Ok(backup_dir)
         }
@@ -102,32 +175,126 @@ fn get_metadata_path(backup_dir: &Path) -> PathBuf {
     backup_dir.join("backups.json")
 }
 
-fn load_backup_metadata(backup_dir: &Path) -> Vec<BackupInfo> {
+fn read_metadata_file(path: &Path) -> Result<Vec<BackupInfo>, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+pub(crate) fn load_backup_metadata(backup_dir: &Path) -> Result<Vec<BackupInfo>, String> {
     let meta_path = get_metadata_path(backup_dir);
     if !meta_path.exists() {
-        return vec![];
+        return Ok(vec![]);
     }
     
-    match fs::read_to_string(&meta_path) {
-        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
-        Err(_) => vec![],
+    match read_metadata_file(&meta_path) {
+        Ok(backups) => Ok(backups),
+        Err(e) => {
+            // Fall back to the copy kept by the previous save rather than pretending there are no backups
+            log::error!("Corrupt backup metadata {:?}: {}", meta_path, e);
+            read_metadata_file(&meta_path.with_extension("json.bak"))
+                .map_err(|_| format!("Backup metadata is corrupt ({}): {}", meta_path.display(), e))
+        }
     }
 }
 
 fn save_backup_metadata(backup_dir: &Path, backups: &[BackupInfo]) -> Result<(), String> {
     let meta_path = get_metadata_path(backup_dir);
     let content = serde_json::to_string_pretty(backups).map_err(|e| e.to_string())?;
-    fs::write(meta_path, content).map_err(|e| e.to_string())
+    
+    // Keep the last good copy and replace the file atomically
+    if meta_path.exists() {
+        let _ = fs::copy(&meta_path, meta_path.with_extension("json.bak"));
+    }
+    let tmp_path = meta_path.with_extension("json.tmp");
+    fs::write(&tmp_path, content).map_err(|e| e.to_string())?;
+    fs::rename(&tmp_path, &meta_path).map_err(|e| e.to_string())
 }
 
-/// Create a backup of the specified server
+/// Create a backup of the specified server as a cancellable job, emitting `backup-progress` while archiving
 #[tauri::command]
-pub fn backup_create_cmd(
+#[allow(clippy::too_many_arguments)]
+pub async fn backup_create_cmd(
+    app: tauri::AppHandle,
+    server_path: String,
+    server_id: String,
+    backup_type: String,
+    included_folders: Vec<String>,
+    custom_path: Option<String>,
+    format: Option<BackupFormat>,
+    rcon: Option<RconCredentials>,
+) -> Result<BackupInfo, String> {
+    let handle = tauri::async_runtime::spawn_blocking(move || {
+        create_backup_job(&app, server_path, server_id, backup_type, included_folders, custom_path, format, rcon)
+    });
+
+    handle.await.map_err(|e| e.to_string())?
+}
+
+/// Blocking part of `backup_create_cmd`, for callers already off the main thread
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_backup_job(
+    app: &tauri::AppHandle,
+    server_path: String,
+    server_id: String,
+    backup_type: String,
+    included_folders: Vec<String>,
+    custom_path: Option<String>,
+    format: Option<BackupFormat>,
+    rcon: Option<RconCredentials>,
+) -> Result<BackupInfo, String> {
+    let label = server_id.clone();
+    jobs::run(app, "backup", &label, |job| create_backup(
+        app, job, server_path, server_id, backup_type, included_folders, custom_path, format, rcon,
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_backup(
+    app: &tauri::AppHandle,
+    job: &Job,
     server_path: String,
     server_id: String,
     backup_type: String,
     included_folders: Vec<String>,
     custom_path: Option<String>,
+    format: Option<BackupFormat>,
+    rcon: Option<RconCredentials>,
+) -> Result<BackupInfo, String> {
+    let progress_server_id = server_id.clone();
+    let report = |progress: ArchiveProgress| {
+        let _ = app.emit("backup-progress", serde_json::json!({
+            "server_id": progress_server_id,
+            "job_id": job.id(),
+            "stage": "archiving",
+            "bytes_done": progress.bytes_done,
+            "bytes_total": progress.bytes_total,
+            "current_file": progress.current_file,
+            "eta_secs": progress.eta_secs
+        }));
+        job.progress(JobProgress {
+            done: progress.bytes_done,
+            total: progress.bytes_total,
+            message: progress.current_file,
+            eta_secs: progress.eta_secs,
+        });
+    };
+    archive_server(
+        server_path, server_id, backup_type, included_folders, custom_path, format, rcon, job.cancel_flag(), report,
+    )
+}
+
+// The work of `create_backup`, reporting archiving progress to `report`
+#[allow(clippy::too_many_arguments)]
+fn archive_server(
+    server_path: String,
+    server_id: String,
+    backup_type: String,
+    included_folders: Vec<String>,
+    custom_path: Option<String>,
+    format: Option<BackupFormat>,
+    rcon: Option<RconCredentials>,
+    cancelled: Arc<AtomicBool>,
+    report: impl Fn(ArchiveProgress) + Send + Sync,
 ) -> Result<BackupInfo, String> {
     let server_dir = PathBuf::from(&server_path);
     if !server_dir.exists() {
@@ -135,96 +302,324 @@ pub fn backup_create_cmd(
     }
 
     let backup_dir = get_backup_dir(&server_id, custom_path.as_deref())?;
-    
-    // Generate backup filename with timestamp
-    let now = Local::now();
-    let timestamp = now.format("%Y-%m-%d_%H-%M-%S").to_string();
-    let backup_name = format!("backup_{}.zip", timestamp);
-    let backup_path = backup_dir.join(&backup_name);
-    
-    // Create ZIP file with maximum compression
-    let file = File::create(&backup_path).map_err(|e| e.to_string())?;
-    let mut zip = ZipWriter::new(file);
-    
-    let options = FileOptions::default()
-        .compression_method(CompressionMethod::Deflated);
-    
-    let mut files_added = 0u64;
-    
-    for folder in &included_folders {
-        let folder_path = server_dir.join(folder);
-        if !folder_path.exists() {
-            log::warn!("Folder does not exist, skipping: {:?}", folder_path);
-            continue;
+    let settings = load_settings(&server_id)?;
+    let format = format.unwrap_or(settings.format);
+
+    // Fail before touching the server if the patterns are invalid or the backup could not be encrypted
+    let filter = BackupFilter::new(&server_dir, &settings.exclude_patterns)?;
+    let key = if settings.encrypt {
+        if format == BackupFormat::Snapshot {
+            return Err("Encryption is only supported for zip backups".to_string());
         }
-        
-        for entry in WalkDir::new(&folder_path) {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(e) => {
-                    log::warn!("Error walking directory: {}", e);
-                    continue;
-                }
-            };
-            
-            let path = entry.path();
-            
-            // Skip excluded files/folders
-            if should_exclude(path, DEFAULT_EXCLUSIONS) {
-                continue;
-            }
-            
-            // Calculate relative path from server directory
-            let relative_path = match path.strip_prefix(&server_dir) {
-                Ok(p) => p,
-                Err(_) => continue,
-            };
-            
-            let path_str = relative_path.to_string_lossy().replace('\\', "/");
-            
-            if path.is_file() {
-                zip.start_file(&path_str, options)
-                    .map_err(|e| format!("Failed to start file in zip: {}", e))?;
-                
-                let mut file = File::open(path).map_err(|e| e.to_string())?;
-                let mut buffer = Vec::new();
-                file.read_to_end(&mut buffer).map_err(|e| e.to_string())?;
-                zip.write_all(&buffer).map_err(|e| e.to_string())?;
-                files_added += 1;
-            } else if path.is_dir() && !path_str.is_empty() {
-                zip.add_directory(&format!("{}/", path_str), options)
-                    .map_err(|e| format!("Failed to add directory: {}", e))?;
-            }
+        Some(backup_crypto::load_key(&server_id)?
+            .ok_or("Backup encryption is enabled but no key is set for this server")?)
+    } else {
+        None
+    };
+
+    // Freeze world saving while archiving so region files are not torn
+    let (consistency, saving_disabled) = match &rcon {
+        Some(credentials) => begin_hot_backup(&server_id, &server_dir, credentials),
+        // Also catches servers started outside VoidLink
+        None if server::is_server_dir_running(&server_id, &server_dir) => (BackupConsistency::HotUnsafe, false),
+        None => (BackupConsistency::Cold, false),
+    };
+
+    let entries = backup_filter::collect_entries(&server_dir, &included_folders, &filter, |_| {});
+    let progress = ProgressTracker::new(entries.iter().map(|e| e.size).sum(), cancelled, report);
+    let result = check_free_space(&backup_dir, &entries, format, key.is_some())
+        .and_then(|()| write_backup(&backup_dir, &entries, format, settings.compression, key.as_ref(), &progress));
+
+    // Always re-enable saving, even if archiving failed
+    if saving_disabled {
+        if let Some(credentials) = &rcon {
+            end_hot_backup(&server_id, credentials);
         }
     }
+
+    let written = result?;
     
-    zip.finish().map_err(|e| e.to_string())?;
-    
-    // Get file size
-    let metadata = fs::metadata(&backup_path).map_err(|e| e.to_string())?;
-    let size_bytes = metadata.len();
-    
-    log::info!("Created backup with {} files, size: {} bytes", files_added, size_bytes);
+    log::info!(
+        "Created {:?} backup {} ({} bytes, {} bytes of files)",
+        consistency, written.name, written.size_bytes, written.logical_size_bytes
+    );
     
     // Create backup info
-    let backup_info = BackupInfo {
+    let mut backup_info = BackupInfo {
         id: uuid_simple(),
-        name: backup_name,
-        created_at: now.to_rfc3339(),
-        size_bytes,
-        path: backup_path.to_string_lossy().to_string(),
+        name: written.name,
+        created_at: written.created_at.to_rfc3339(),
+        size_bytes: written.size_bytes,
+        path: written.path.to_string_lossy().to_string(),
         backup_type,
         included_folders,
+        format,
+        logical_size_bytes: written.logical_size_bytes,
+        consistency,
+        sha256: Some(written.sha256),
+        remote_key: None,
+        encrypted: key.is_some(),
+        key: key.as_ref().map(BackupKey::record),
+        pinned: false,
     };
     
-    // Update metadata
-    let mut backups = load_backup_metadata(&backup_dir);
+    // Copy to the remote target, keeping the local archive if the upload fails
+    if let Some(target) = &settings.target {
+        upload_backup(&settings, target, &mut backup_info);
+    }
+    
+    // Update metadata, without losing a concurrent rotation or pin change
+    let store_lock = backup_snapshot::store_lock(&backup_dir);
+    let _store = store_lock.lock().map_err(|e| e.to_string())?;
+    let mut backups = load_backup_metadata(&backup_dir)?;
     backups.push(backup_info.clone());
     save_backup_metadata(&backup_dir, &backups)?;
     
     Ok(backup_info)
 }
 
+/// Run `save-off` and `save-all flush` over RCON, returning the resulting consistency
+/// and whether saving was disabled (and therefore needs `save-on` afterwards)
+pub(crate) fn begin_hot_backup(server_id: &str, server_dir: &Path, credentials: &RconCredentials) -> (BackupConsistency, bool) {
+    if let Err(e) = rcon::send_command_blocking(credentials, "save-off") {
+        log::warn!("save-off failed for {}, backing up without it: {}", server_id, e);
+        let consistency = if server::is_server_dir_running(server_id, server_dir) {
+            BackupConsistency::HotUnsafe
+        } else {
+            BackupConsistency::Cold
+        };
+        return (consistency, false);
+    }
+
+    let console_position = server::console_position(server_id);
+    let response = match rcon::send_command_blocking(credentials, "save-all flush") {
+        Ok(response) => response,
+        Err(e) => {
+            log::warn!("save-all flush failed for {}: {}", server_id, e);
+            return (BackupConsistency::HotUnsafe, true);
+        }
+    };
+
+    // Vanilla answers once the flush completes; some forks only log it to the console
+    let flushed = response.to_lowercase().contains("saved")
+        || console_position.is_some_and(|position| server::wait_for_console_line(
+            server_id,
+            position,
+            "Saved the game",
+            Duration::from_secs(SAVE_FLUSH_TIMEOUT_SECS),
+        ));
+
+    if flushed {
+        (BackupConsistency::HotConsistent, true)
+    } else {
+        log::warn!("No flush confirmation from {}, marking backup as hot-unsafe", server_id);
+        (BackupConsistency::HotUnsafe, true)
+    }
+}
+
+// Free space on the disk holding `path`, None if it cannot be determined
+fn available_space(path: &Path) -> Option<u64> {
+    let path = path.canonicalize().ok()?;
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    disks.list().iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+fn ensure_free_space(dir: &Path, required: u64) -> Result<(), String> {
+    match available_space(dir) {
+        Some(available) if available < required => Err(format!(
+            "Not enough disk space for the backup in {}: {:.1} GB needed, {:.1} GB free",
+            dir.display(),
+            required as f64 / 1_073_741_824.0,
+            available as f64 / 1_073_741_824.0
+        )),
+        Some(_) => Ok(()),
+        None => {
+            log::warn!("Could not determine free disk space for {}", dir.display());
+            Ok(())
+        }
+    }
+}
+
+// The per-core part archives and the final zip exist side by side until the parts are merged,
+// so a zip briefly needs up to twice the size of its files. Encrypted zips are assembled in the
+// temp directory and only the ciphertext is written to the backup folder.
+fn check_free_space(backup_dir: &Path, entries: &[BackupEntry], format: BackupFormat, encrypted: bool) -> Result<(), String> {
+    if format != BackupFormat::Zip {
+        return Ok(());
+    }
+    let total: u64 = entries.iter().map(|e| e.size).sum();
+    if encrypted {
+        ensure_free_space(&std::env::temp_dir(), total.saturating_mul(2))?;
+        ensure_free_space(backup_dir, total)
+    } else {
+        ensure_free_space(backup_dir, total.saturating_mul(2))
+    }
+}
+
+/// Turn saving back on after `begin_hot_backup` disabled it
+pub(crate) fn end_hot_backup(server_id: &str, credentials: &RconCredentials) {
+    if let Err(e) = rcon::send_command_blocking(credentials, "save-on") {
+        log::error!("Failed to re-enable saving for {}: {}", server_id, e);
+    }
+}
+
+struct WrittenBackup {
+    created_at: chrono::DateTime<Local>,
+    name: String,
+    path: PathBuf,
+    size_bytes: u64,
+    logical_size_bytes: u64,
+    sha256: String,
+}
+
+// Archive the collected entries in the requested format
+fn write_backup(
+    backup_dir: &Path,
+    entries: &[BackupEntry],
+    format: BackupFormat,
+    compression: BackupCompression,
+    key: Option<&BackupKey>,
+    progress: &ProgressTracker,
+) -> Result<WrittenBackup, String> {
+    // Generate backup filename with timestamp
+    let now = Local::now();
+    let timestamp = now.format("%Y-%m-%d_%H-%M-%S").to_string();
+
+    let (backup_name, backup_path, size_bytes, logical_size_bytes) = match format {
+        BackupFormat::Zip => {
+            let backup_name = match key {
+                Some(_) => format!("backup_{}.zip.enc", timestamp),
+                None => format!("backup_{}.zip", timestamp),
+            };
+            let backup_path = backup_dir.join(&backup_name);
+            let (logical_size_bytes, manifest) = match key {
+                Some(key) => {
+                    // Zip into a local temp file so the backup folder only ever sees ciphertext
+                    let plain = backup_crypto::PlainArchive::temporary(&backup_name);
+                    let written = backup_archive::write_zip(plain.path(), entries, compression, progress)?;
+                    progress.check_cancelled()?;
+                    if let Err(e) = backup_crypto::encrypt_file(key, plain.path(), &backup_path) {
+                        let _ = fs::remove_file(&backup_path);
+                        return Err(e);
+                    }
+                    written
+                }
+                None => backup_archive::write_zip(&backup_path, entries, compression, progress)?,
+            };
+            backup_verify::save_entry_manifest(&backup_path, &manifest, key)?;
+            let size_bytes = fs::metadata(&backup_path).map_err(|e| e.to_string())?.len();
+            (backup_name, backup_path, size_bytes, logical_size_bytes)
+        }
+        BackupFormat::Snapshot => {
+            let backup_name = format!("snapshot_{}", timestamp);
+            let store_lock = backup_snapshot::store_lock(backup_dir);
+            let _store = store_lock.lock().map_err(|e| e.to_string())?;
+            let summary = backup_snapshot::create_snapshot(backup_dir, &backup_name, &now.to_rfc3339(), entries, progress)?;
+            log::info!(
+                "Snapshot stored {} new chunks ({} bytes) for {} files",
+                summary.new_chunks, summary.stored_bytes, summary.files
+            );
+            (backup_name, summary.manifest_path, summary.stored_bytes, summary.logical_size_bytes)
+        }
+    };
+
+    let sha256 = backup_verify::sha256_file(&backup_path)?;
+
+    Ok(WrittenBackup {
+        created_at: now,
+        name: backup_name,
+        path: backup_path,
+        size_bytes,
+        logical_size_bytes,
+        sha256,
+    })
+}
+
+// Upload a zip backup and its entry manifest, recording the key on success
+fn upload_backup(settings: &BackupSettings, target: &BackupTargetConfig, backup: &mut BackupInfo) {
+    if backup.format == BackupFormat::Snapshot {
+        log::warn!("Snapshot backups are kept locally only, not uploading {}", backup.name);
+        return;
+    }
+
+    let backup_path = PathBuf::from(&backup.path);
+    let manifest_path = backup_verify::entry_manifest_path(&backup_path);
+    let manifest_key = format!("{}.manifest.json", backup.name);
+    let result = backup_target::open_target(target).and_then(|target| {
+        target.upload(&backup_path, &backup.name)?;
+        if manifest_path.exists() {
+            target.upload(&manifest_path, &manifest_key)?;
+        }
+        log::info!("Uploaded backup {} to {}", backup.name, target.describe());
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => {
+            backup.remote_key = Some(backup.name.clone());
+            if !settings.keep_local_copy {
+                let _ = fs::remove_file(&backup_path);
+                let _ = fs::remove_file(&manifest_path);
+            }
+        }
+        Err(e) => log::error!("Failed to upload backup {}, keeping it locally only: {}", backup.name, e),
+    }
+}
+
+/// Make sure a backup is available on disk, downloading it from the backup target if needed
+pub(crate) fn ensure_local_copy(server_id: &str, backup: &BackupInfo) -> Result<(), String> {
+    let backup_path = PathBuf::from(&backup.path);
+    let Some(key) = &backup.remote_key else { return Ok(()) };
+    if backup_path.exists() {
+        return Ok(());
+    }
+
+    let target = load_settings(server_id)?.target.ok_or("Backup is stored remotely but no backup target is configured")?;
+    let target = backup_target::open_target(&target)?;
+    log::info!("Downloading backup {} from {}", backup.name, target.describe());
+    target.download(key, &backup_path)?;
+    // Older uploads may not have an entry manifest
+    let manifest_path = backup_verify::entry_manifest_path(&backup_path);
+    if let Err(e) = target.download(&format!("{}.manifest.json", key), &manifest_path) {
+        log::warn!("No entry manifest for remote backup {}: {}", backup.name, e);
+    }
+    Ok(())
+}
+
+// Remove the data of a backup from disk and the backup target (snapshot chunks are collected separately)
+fn remove_backup_data(server_id: &str, backup: &BackupInfo) -> Result<(), String> {
+    if let Some(key) = &backup.remote_key {
+        if let Some(target) = load_settings(server_id)?.target {
+            let target = backup_target::open_target(&target)?;
+            target.delete(key)?;
+            target.delete(&format!("{}.manifest.json", key))?;
+        }
+    }
+
+    let backup_path = PathBuf::from(&backup.path);
+    if backup_path.exists() {
+        fs::remove_file(&backup_path).map_err(|e| e.to_string())?;
+    }
+    let manifest_path = backup_verify::entry_manifest_path(&backup_path);
+    if manifest_path.exists() {
+        fs::remove_file(&manifest_path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// List the objects stored on a server's backup target
+#[tauri::command]
+pub async fn backup_remote_list_cmd(server_id: String) -> Result<Vec<backup_target::RemoteObject>, String> {
+    let handle = tauri::async_runtime::spawn_blocking(move || {
+        let target = load_settings(&server_id)?.target.ok_or("No backup target configured")?;
+        backup_target::open_target(&target)?.list()
+    });
+    handle.await.map_err(|e| e.to_string())?
+}
+
 /// List all backups for a server
 #[tauri::command]
 pub fn backup_list_cmd(
@@ -232,150 +627,287 @@ pub fn backup_list_cmd(
     custom_path: Option<String>,
 ) -> Result<Vec<BackupInfo>, String> {
     let backup_dir = get_backup_dir(&server_id, custom_path.as_deref())?;
-    let backups = load_backup_metadata(&backup_dir);
-    Ok(backups)
+    load_backup_metadata(&backup_dir)
 }
 
-/// Restore a backup (creates pre-restore backup automatically)
-#[tauri::command]
-pub fn backup_restore_cmd(
-    server_path: String,
-    server_id: String,
-    backup_id: String,
-    included_folders: Vec<String>,
-    custom_path: Option<String>,
-) -> Result<BackupInfo, String> {
-    let server_dir = PathBuf::from(&server_path);
-    let backup_dir = get_backup_dir(&server_id, custom_path.as_deref())?;
-    
-    // Find the backup to restore
-    let backups = load_backup_metadata(&backup_dir);
-    let backup = backups.iter()
+/// A backup ready to be read: fetched from the backup target if needed and, if encrypted,
+/// decrypted once into a temp copy that every step of the operation reads
+pub struct OpenedBackup {
+    pub backup_dir: PathBuf,
+    pub backup: BackupInfo,
+    pub key: Option<BackupKey>,
+    pub plain: backup_crypto::PlainArchive,
+}
+
+pub(crate) fn open_backup(server_id: &str, backup_id: &str, custom_path: Option<&str>) -> Result<OpenedBackup, String> {
+    let backup_dir = get_backup_dir(server_id, custom_path)?;
+    let backup = load_backup_metadata(&backup_dir)?
+        .into_iter()
         .find(|b| b.id == backup_id)
         .ok_or("Backup not found")?;
     
-    let backup_path = PathBuf::from(&backup.path);
-    if !backup_path.exists() {
+    ensure_local_copy(server_id, &backup)?;
+    if !Path::new(&backup.path).exists() {
         return Err("Backup file does not exist".to_string());
     }
     
-    // Create pre-restore backup automatically
+    let key = backup_crypto::key_for(server_id, &backup)?;
+    let plain = backup_crypto::open_archive(&backup, key.as_ref())?;
+    Ok(OpenedBackup { backup_dir, backup, key, plain })
+}
+
+/// Verify an opened backup for restoring and take a pre-restore backup of `protect_folders`.
+/// Returns the pre-restore backup.
+pub(crate) fn prepare_restore(
+    app: &tauri::AppHandle,
+    server_path: &str,
+    server_id: &str,
+    opened: &OpenedBackup,
+    protect_folders: Vec<String>,
+    custom_path: Option<String>,
+    force: Option<bool>,
+) -> Result<BackupInfo, String> {
+    // Swapping folders under a running server corrupts the world it is still writing
+    if server::is_server_dir_running(server_id, Path::new(server_path)) {
+        return Err("Stop the server before restoring a backup".to_string());
+    }
+    let backup = &opened.backup;
+    
+    // Refuse to overwrite the world with a damaged backup
+    let verification = backup_verify::verify_backup(&opened.backup_dir, backup, opened.key.as_ref(), &opened.plain);
+    if !verification.is_valid {
+        let reason = verification.error.clone().unwrap_or_else(|| format!(
+            "{} missing and {} corrupt entries{}",
+            verification.missing.len(),
+            verification.corrupt.len(),
+            if verification.checksum_matches == Some(false) { ", checksum mismatch" } else { "" }
+        ));
+        if !force.unwrap_or(false) {
+            return Err(format!("Backup failed verification: {}", reason));
+        }
+        log::warn!("Restoring unverified backup {} (forced): {}", backup.name, reason);
+    }
+    
+    // Create pre-restore backup automatically (as a zip if it has to be encrypted)
     log::info!("Creating pre-restore backup...");
-    let pre_restore = backup_create_cmd(
-        server_path.clone(),
-        server_id.clone(),
+    let pre_restore_format = if load_settings(server_id)?.encrypt { BackupFormat::Zip } else { backup.format };
+    let pre_restore = create_backup_job(
+        app,
+        server_path.to_string(),
+        server_id.to_string(),
         "pre-restore".to_string(),
-        included_folders.clone(),
-        custom_path.clone(),
+        protect_folders,
+        custom_path,
+        Some(pre_restore_format),
+        None,
     )?;
     
-    // Extract the backup
-    let file = File::open(&backup_path).map_err(|e| e.to_string())?;
-    let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+    Ok(pre_restore)
+}
+
+/// Restore a backup (creates pre-restore backup automatically).
+/// Backups that fail verification are refused unless `force` is set. The backup is staged and
+/// swapped in folder by folder, rolling back to the originals on any error.
+#[tauri::command]
+pub async fn backup_restore_cmd(
+    app: tauri::AppHandle,
+    server_path: String,
+    server_id: String,
+    backup_id: String,
+    included_folders: Vec<String>,
+    custom_path: Option<String>,
+    force: Option<bool>,
+) -> Result<RestoreReport, String> {
+    let handle = tauri::async_runtime::spawn_blocking(move || {
+        jobs::run(&app, "restore", &server_id, |job| {
+            restore_backup(&app, job, &server_path, &server_id, &backup_id, included_folders, custom_path, force)
+        })
+    });
+
+    handle.await.map_err(|e| e.to_string())?
+}
+
+#[allow(clippy::too_many_arguments)]
+fn restore_backup(
+    app: &tauri::AppHandle,
+    job: &Job,
+    server_path: &str,
+    server_id: &str,
+    backup_id: &str,
+    included_folders: Vec<String>,
+    custom_path: Option<String>,
+    force: Option<bool>,
+) -> Result<RestoreReport, String> {
+    let server_dir = PathBuf::from(server_path);
+    let opened = open_backup(server_id, backup_id, custom_path.as_deref())?;
+    let pre_restore = prepare_restore(app, server_path, server_id, &opened, included_folders, custom_path, force)?;
+    job.check_cancelled()?;
+    let backup = &opened.backup;
     
-    // Remove existing folders that will be restored
-    for folder in &backup.included_folders {
-        let folder_path = server_dir.join(folder);
-        if folder_path.exists() {
-            fs::remove_dir_all(&folder_path).map_err(|e| e.to_string())?;
-        }
-    }
+    // Extract into a staging directory first so a failed or cancelled restore never touches the live folders
+    backup_restore::cleanup_stale_workspaces(&server_dir);
+    let mut workspace = backup_restore::RestoreWorkspace::create(&server_dir)?;
+    let restored_files = backup_restore::extract_backup(&opened, &workspace.staging(), &|_| true, job)?;
     
-    // Extract all files
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
-        let out_path = server_dir.join(file.mangled_name());
-        
-        if file.name().ends_with('/') {
-            fs::create_dir_all(&out_path).map_err(|e| e.to_string())?;
-        } else {
-            if let Some(parent) = out_path.parent() {
-                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-            }
-            let mut outfile = File::create(&out_path).map_err(|e| e.to_string())?;
-            std::io::copy(&mut file, &mut outfile).map_err(|e| e.to_string())?;
-        }
+    let (replaced_folders, skipped_folders) =
+        backup_restore::staged_folders(&workspace.staging(), &backup.included_folders);
+    if replaced_folders.is_empty() {
+        return Err("Backup does not contain any of its included folders".to_string());
+    }
+    for folder in &skipped_folders {
+        log::warn!("Folder {} is not in the backup, leaving it untouched", folder);
     }
     
-    log::info!("Backup restored successfully");
+    // Last chance to cancel, the swap itself always runs to completion or rolls back
+    job.check_cancelled()?;
+    backup_restore::swap_in(&server_dir, &mut workspace, &replaced_folders)?;
     
-    Ok(pre_restore)
+    log::info!("Backup restored successfully, replaced: {}", replaced_folders.join(", "));
+    
+    Ok(RestoreReport {
+        pre_restore,
+        replaced_folders,
+        skipped_folders,
+        restored_files,
+    })
 }
 
 /// Delete a backup
 #[tauri::command]
-pub fn backup_delete_cmd(
+pub async fn backup_delete_cmd(
     server_id: String,
     backup_id: String,
     custom_path: Option<String>,
 ) -> Result<(), String> {
-    let backup_dir = get_backup_dir(&server_id, custom_path.as_deref())?;
-    let mut backups = load_backup_metadata(&backup_dir);
+    // Removing the remote copy talks to the backup target
+    let handle = tauri::async_runtime::spawn_blocking(move || delete_backup(&server_id, &backup_id, custom_path.as_deref()));
+    handle.await.map_err(|e| e.to_string())?
+}
+
+fn delete_backup(server_id: &str, backup_id: &str, custom_path: Option<&str>) -> Result<(), String> {
+    let backup_dir = get_backup_dir(server_id, custom_path)?;
+    let store_lock = backup_snapshot::store_lock(&backup_dir);
+    let _store = store_lock.lock().map_err(|e| e.to_string())?;
+    let mut backups = load_backup_metadata(&backup_dir)?;
     
     // Find and remove the backup
     let backup_idx = backups.iter()
         .position(|b| b.id == backup_id)
         .ok_or("Backup not found")?;
+    if backups[backup_idx].pinned {
+        return Err("Backup is pinned, unpin it before deleting".to_string());
+    }
     
     let backup = backups.remove(backup_idx);
     
     // Delete the file
-    let backup_path = PathBuf::from(&backup.path);
-    if backup_path.exists() {
-        fs::remove_file(&backup_path).map_err(|e| e.to_string())?;
-    }
+    remove_backup_data(server_id, &backup)?;
     
     // Update metadata
     save_backup_metadata(&backup_dir, &backups)?;
     
+    // Drop chunks only this snapshot was using
+    if backup.format == BackupFormat::Snapshot {
+        backup_snapshot::garbage_collect(&backup_dir)?;
+    }
+    
     log::info!("Deleted backup: {}", backup.name);
     
     Ok(())
 }
 
-/// Rotate backups (delete oldest if over limit)
+/// Apply the server's retention policy. With `dry_run` nothing is deleted and the plan is only returned.
 #[tauri::command]
-pub fn backup_rotate_cmd(
+pub async fn backup_rotate_cmd(
     server_id: String,
-    max_backups: u32,
     custom_path: Option<String>,
-) -> Result<u32, String> {
-    let backup_dir = get_backup_dir(&server_id, custom_path.as_deref())?;
-    let mut backups = load_backup_metadata(&backup_dir);
-    
-    // Sort by created_at (oldest first)
-    backups.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    dry_run: Option<bool>,
+) -> Result<RotationPlan, String> {
+    let handle = tauri::async_runtime::spawn_blocking(move || {
+        rotate_backups(&server_id, custom_path.as_deref(), dry_run.unwrap_or(false))
+    });
+    handle.await.map_err(|e| e.to_string())?
+}
+
+pub(crate) fn rotate_backups(server_id: &str, custom_path: Option<&str>, dry_run: bool) -> Result<RotationPlan, String> {
+    let backup_dir = get_backup_dir(server_id, custom_path)?;
+    // Dry runs only read, they do not wait for a snapshot that is being written
+    let store_lock = backup_snapshot::store_lock(&backup_dir);
+    let _store = if dry_run { None } else { Some(store_lock.lock().map_err(|e| e.to_string())?) };
+    let backups = load_backup_metadata(&backup_dir)?;
+    let policy = load_settings(server_id)?.retention_policy();
     
-    let mut deleted_count = 0u32;
+    let mut plan = backup_retention::plan_rotation(&backups, &policy);
+    if dry_run || plan.delete.is_empty() {
+        return Ok(plan);
+    }
+    plan.dry_run = false;
     
-    // Delete oldest backups if over limit
-    while backups.len() > max_backups as usize {
-        if let Some(oldest) = backups.first() {
-            let backup_path = PathBuf::from(&oldest.path);
-            if backup_path.exists() {
-                let _ = fs::remove_file(&backup_path);
-            }
-            backups.remove(0);
-            deleted_count += 1;
+    let mut remaining = Vec::with_capacity(plan.keep.len());
+    let mut deleted_snapshot = false;
+    for backup in backups {
+        if !plan.delete.iter().any(|d| d.id == backup.id) {
+            remaining.push(backup);
+            continue;
+        }
+        // Keep the metadata of backups whose data could not be removed so they are retried next time
+        if let Err(e) = remove_backup_data(server_id, &backup) {
+            log::warn!("Failed to remove rotated backup {}: {}", backup.name, e);
+            plan.delete.retain(|d| d.id != backup.id);
+            plan.freed_bytes = plan.freed_bytes.saturating_sub(backup.size_bytes);
+            remaining.push(backup);
+            continue;
         }
+        deleted_snapshot |= backup.format == BackupFormat::Snapshot;
     }
     
-    if deleted_count > 0 {
-        save_backup_metadata(&backup_dir, &backups)?;
-        log::info!("Rotated {} old backups", deleted_count);
+    save_backup_metadata(&backup_dir, &remaining)?;
+    log::info!("Rotated {} old backups", plan.delete.len());
+    
+    if deleted_snapshot {
+        backup_snapshot::garbage_collect(&backup_dir)?;
     }
     
-    Ok(deleted_count)
+    Ok(plan)
+}
+
+/// Pin or unpin a backup; pinned backups are never deleted by rotation
+#[tauri::command]
+pub fn backup_set_pinned_cmd(
+    server_id: String,
+    backup_id: String,
+    pinned: bool,
+    custom_path: Option<String>,
+) -> Result<BackupInfo, String> {
+    let backup_dir = get_backup_dir(&server_id, custom_path.as_deref())?;
+    let store_lock = backup_snapshot::store_lock(&backup_dir);
+    let _store = store_lock.lock().map_err(|e| e.to_string())?;
+    let mut backups = load_backup_metadata(&backup_dir)?;
+    
+    let backup = backups.iter_mut()
+        .find(|b| b.id == backup_id)
+        .ok_or("Backup not found")?;
+    backup.pinned = pinned;
+    let updated = backup.clone();
+    
+    save_backup_metadata(&backup_dir, &backups)?;
+    Ok(updated)
 }
 
 /// Get backup statistics
 #[tauri::command]
-pub fn backup_stats_cmd(
+pub async fn backup_stats_cmd(
     server_id: String,
     custom_path: Option<String>,
 ) -> Result<BackupStats, String> {
-    let backup_dir = get_backup_dir(&server_id, custom_path.as_deref())?;
-    let backups = load_backup_metadata(&backup_dir);
+    // The remote size is listed from the backup target
+    let handle = tauri::async_runtime::spawn_blocking(move || backup_stats(&server_id, custom_path.as_deref()));
+    handle.await.map_err(|e| e.to_string())?
+}
+
+fn backup_stats(server_id: &str, custom_path: Option<&str>) -> Result<BackupStats, String> {
+    let backup_dir = get_backup_dir(server_id, custom_path)?;
+    let backups = load_backup_metadata(&backup_dir)?;
     
     let total_backups = backups.len() as u32;
     let total_size_bytes: u64 = backups.iter().map(|b| b.size_bytes).sum();
@@ -388,9 +920,27 @@ pub fn backup_stats_cmd(
         .max_by(|a, b| a.created_at.cmp(&b.created_at))
         .map(|b| b.created_at.clone());
     
+    // Size of the backups actually present on the backup target, unknown while it is unreachable
+    let remote_size_bytes = match load_settings(server_id)?.target {
+        Some(target) => match backup_target::open_target(&target).and_then(|target| target.list()) {
+            Ok(objects) => Some(objects.iter()
+                .filter(|o| backups.iter().any(|b| {
+                    b.remote_key.as_ref().is_some_and(|key| o.key == *key || o.key == format!("{}.manifest.json", key))
+                }))
+                .map(|o| o.size_bytes)
+                .sum()),
+            Err(e) => {
+                log::warn!("Could not list backup target of {}: {}", server_id, e);
+                None
+            }
+        },
+        None => None,
+    };
+    
     Ok(BackupStats {
         total_backups,
         total_size_bytes,
+        remote_size_bytes,
         oldest_backup,
         newest_backup,
     })
@@ -440,16 +990,30 @@ pub fn backup_get_folders_cmd(server_path: String) -> Result<Vec<String>, String
 
 /// Load backup settings for a server
 #[tauri::command]
-pub fn backup_load_settings_cmd(
-    server_id: String,
-    custom_path: Option<String>,
-) -> Result<BackupSettings, String> {
-    let backup_dir = get_backup_dir(&server_id, custom_path.as_deref())?;
-    let settings_path = backup_dir.join("settings.json");
-    
+pub fn backup_load_settings_cmd(server_id: String) -> Result<RedactedBackupSettings, String> {
+    let mut settings = load_settings(&server_id)?;
+    let target_has_secret = settings.target.as_mut().is_some_and(|target| target.redact());
+    Ok(RedactedBackupSettings { settings, target_has_secret })
+}
+
+// Settings are kept in the default backup folder even when `custom_path` moves the backups
+// elsewhere, so they are found without knowing the custom path first
+fn settings_path(server_id: &str) -> Result<PathBuf, String> {
+    Ok(get_backup_dir(server_id, None)?.join("settings.json"))
+}
+
+pub(crate) fn load_settings(server_id: &str) -> Result<BackupSettings, String> {
+    read_settings(&settings_path(server_id)?)
+}
+
+fn read_settings(settings_path: &Path) -> Result<BackupSettings, String> {
     if settings_path.exists() {
-        let content = fs::read_to_string(&settings_path).map_err(|e| e.to_string())?;
-        serde_json::from_str(&content).map_err(|e| e.to_string())
+        let content = fs::read_to_string(settings_path).map_err(|e| e.to_string())?;
+        let mut settings: BackupSettings = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+        if let Some(target) = &mut settings.target {
+            target.load_secret()?;
+        }
+        Ok(settings)
     } else {
         Ok(BackupSettings::default())
     }
@@ -457,13 +1021,21 @@ pub fn backup_load_settings_cmd(
 
 /// Save backup settings for a server
 #[tauri::command]
-pub fn backup_save_settings_cmd(
-    server_id: String,
-    settings: BackupSettings,
-    custom_path: Option<String>,
-) -> Result<(), String> {
-    let backup_dir = get_backup_dir(&server_id, custom_path.as_deref())?;
-    let settings_path = backup_dir.join("settings.json");
+pub fn backup_save_settings_cmd(server_id: String, settings: BackupSettings) -> Result<(), String> {
+    write_settings(&settings_path(&server_id)?, settings)
+}
+
+fn write_settings(settings_path: &Path, mut settings: BackupSettings) -> Result<(), String> {
+    // Reject settings that would only fail once the next backup runs
+    BackupFilter::new(settings_path.parent().unwrap_or(Path::new(".")), &settings.exclude_patterns)?;
+    if settings.format == BackupFormat::Snapshot && settings.target.is_some() {
+        return Err("Snapshot backups cannot be uploaded to a backup target, use zip backups or remove the target".to_string());
+    }
+    
+    // Keep passwords and secret keys out of settings.json
+    if let Some(target) = &mut settings.target {
+        target.store_secret()?;
+    }
     
     let content = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
     fs::write(settings_path, content).map_err(|e| e.to_string())
@@ -477,3 +1049,115 @@ fn uuid_simple() -> String {
         .unwrap();
     format!("{:x}{:x}", duration.as_secs(), duration.subsec_nanos())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn custom_path_backups_use_the_saved_settings() {
+        let root = std::env::temp_dir().join(format!("voidlink-backup-custom-{}", std::process::id()));
+        let server_dir = root.join("server");
+        let custom_dir = root.join("custom");
+        fs::create_dir_all(server_dir.join("world")).unwrap();
+        fs::write(server_dir.join("world").join("level.dat"), b"level data").unwrap();
+        let server_id = "custom-path-server".to_string();
+        let custom_path = custom_dir.to_string_lossy().to_string();
+
+        let settings = BackupSettings {
+            custom_path: Some(custom_path.clone()),
+            encrypt: true,
+            included_folders: vec!["world".to_string()],
+            ..BackupSettings::default()
+        };
+        backup_save_settings_cmd(server_id.clone(), settings).unwrap();
+        backup_crypto::backup_key_set_cmd(server_id.clone(), Some("hunter2".to_string()), None, None).unwrap();
+
+        let backup = archive_server(
+            server_dir.to_string_lossy().to_string(),
+            server_id.clone(),
+            "manual".to_string(),
+            vec!["world".to_string()],
+            Some(custom_path),
+            None,
+            None,
+            Arc::new(AtomicBool::new(false)),
+            |_| {},
+        )
+        .unwrap();
+
+        assert!(backup.encrypted);
+        let archive = PathBuf::from(&backup.path);
+        assert!(archive.starts_with(custom_dir.join(&server_id)));
+        let mut header = [0u8; 64];
+        let read = fs::File::open(&archive).and_then(|mut f| f.read(&mut header)).unwrap();
+        assert!(backup_crypto::is_encrypted(&header[..read]));
+        // The settings were not looked up under the custom path
+        assert!(!custom_dir.join(&server_id).join("settings.json").exists());
+
+        let _ = fs::remove_dir_all(&root);
+        let _ = fs::remove_dir_all(get_backup_dir(&server_id, None).unwrap());
+        let _ = fs::remove_file(backup_crypto::keys_dir().join(format!("{}.json", server_id)));
+    }
+
+    #[test]
+    fn key_replacement_is_checked_against_remote_only_backups() {
+        let server_id = "remote-only-key-server".to_string();
+        let passphrase = |p: &str| backup_crypto::backup_key_set_cmd(server_id.clone(), Some(p.to_string()), None, None);
+        passphrase("first").unwrap();
+        let key = backup_crypto::load_key(&server_id).unwrap().unwrap();
+
+        // Encrypted backup that was uploaded without keeping a local copy
+        let backup_dir = get_backup_dir(&server_id, None).unwrap();
+        let remote_only: BackupInfo = serde_json::from_value(serde_json::json!({
+            "id": "remote",
+            "name": "backup_remote.zip.enc",
+            "created_at": "2024-01-01T12:00:00+00:00",
+            "size_bytes": 100,
+            "path": backup_dir.join("backup_remote.zip.enc").to_string_lossy(),
+            "backup_type": "auto",
+            "included_folders": ["world"],
+            "remote_key": "backup_remote.zip.enc",
+            "encrypted": true,
+            "key": key.record(),
+        }))
+        .unwrap();
+        save_backup_metadata(&backup_dir, &[remote_only]).unwrap();
+
+        assert!(passphrase("second").is_err());
+        // The same passphrase yields the same key with the recorded salt
+        assert_eq!(passphrase("first").unwrap().fingerprint, key.record().fingerprint);
+
+        let _ = fs::remove_dir_all(&backup_dir);
+        let _ = fs::remove_file(backup_crypto::keys_dir().join(format!("{}.json", server_id)));
+    }
+
+    #[test]
+    fn target_secrets_are_not_sent_to_the_ui() {
+        let server_id = "redacted-secret-server".to_string();
+        let with_password = |password: &str| BackupSettings {
+            target: Some(BackupTargetConfig::Webdav {
+                url: "https://dav.example.com/backups".to_string(),
+                username: Some("alice".to_string()),
+                password: Some(password.to_string()),
+            }),
+            ..BackupSettings::default()
+        };
+        let password = |settings: BackupSettings| match settings.target {
+            Some(BackupTargetConfig::Webdav { password, .. }) => password,
+            _ => panic!("target was not kept"),
+        };
+
+        backup_save_settings_cmd(server_id.clone(), with_password("s3cret")).unwrap();
+        let shown = backup_load_settings_cmd(server_id.clone()).unwrap();
+        assert!(shown.target_has_secret);
+        assert_eq!(password(shown.settings), None);
+
+        // Saving the redacted settings back keeps the stored password
+        backup_save_settings_cmd(server_id.clone(), with_password("")).unwrap();
+        assert_eq!(password(load_settings(&server_id).unwrap()), Some("s3cret".to_string()));
+
+        let _ = fs::remove_dir_all(get_backup_dir(&server_id, None).unwrap());
+    }
+}
@@ -0,0 +1,273 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::backup_filter::BackupEntry;
+use crate::backup_verify::{to_hex, EntryManifest, ManifestEntry};
+use crate::jobs;
+
+// Zip backups are written by one worker per core, each compressing its share of the files
+// into a part archive next to the final one (<archive>.part<N>). The compressed entries are
+// then copied into the final archive without being recompressed.
+
+// Minimum time between two progress reports
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+// Entries larger than this need zip64 headers, which must be requested before streaming them
+const ZIP64_THRESHOLD: u64 = u32::MAX as u64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupCompression {
+    #[default]
+    Deflate,
+    Zstd, // faster and smaller, needs a zstd-capable unzip tool to open by hand
+    Store, // no compression, for already compressed data or slow machines
+}
+
+impl BackupCompression {
+    fn method(self) -> CompressionMethod {
+        match self {
+            BackupCompression::Deflate => CompressionMethod::Deflated,
+            BackupCompression::Zstd => CompressionMethod::Zstd,
+            BackupCompression::Store => CompressionMethod::Stored,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveProgress {
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub current_file: String,
+    pub eta_secs: Option<u64>, // None until enough has been written to estimate
+}
+
+/// Shared progress of an archive being written, reported at most every `PROGRESS_INTERVAL`
+pub struct ProgressTracker<'a> {
+    bytes_total: u64,
+    bytes_done: AtomicU64,
+    current_file: Mutex<String>,
+    started: Instant,
+    last_report: Mutex<Instant>,
+    report: Box<dyn Fn(ArchiveProgress) + Send + Sync + 'a>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<'a> ProgressTracker<'a> {
+    pub fn new(
+        bytes_total: u64,
+        cancelled: Arc<AtomicBool>,
+        report: impl Fn(ArchiveProgress) + Send + Sync + 'a,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            bytes_total,
+            bytes_done: AtomicU64::new(0),
+            current_file: Mutex::new(String::new()),
+            started: now,
+            last_report: Mutex::new(now),
+            report: Box::new(report),
+            cancelled,
+        }
+    }
+
+    /// Err(CANCELLED) once the owning job was cancelled
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            Err(jobs::CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn start_file(&self, path: &str) {
+        if let Ok(mut current) = self.current_file.lock() {
+            current.clear();
+            current.push_str(path);
+        }
+        self.maybe_report();
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.bytes_done.fetch_add(bytes, Ordering::Relaxed);
+        self.maybe_report();
+    }
+
+    fn maybe_report(&self) {
+        let Ok(mut last) = self.last_report.try_lock() else { return };
+        if last.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+        *last = Instant::now();
+        drop(last);
+        self.report_now();
+    }
+
+    /// Report regardless of when the last report was sent, e.g. once archiving finished
+    pub fn report_now(&self) {
+        let bytes_done = self.bytes_done.load(Ordering::Relaxed);
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let eta_secs = (bytes_done > 0 && elapsed >= 1.0).then(|| {
+            let rate = bytes_done as f64 / elapsed;
+            (self.bytes_total.saturating_sub(bytes_done) as f64 / rate) as u64
+        });
+        (self.report)(ArchiveProgress {
+            bytes_done,
+            bytes_total: self.bytes_total,
+            current_file: self.current_file.lock().map(|c| c.clone()).unwrap_or_default(),
+            eta_secs,
+        });
+    }
+}
+
+// Hashes and counts everything read through it
+struct TrackingReader<'p, 'a, R> {
+    inner: R,
+    hasher: Sha256,
+    size: u64,
+    progress: &'p ProgressTracker<'a>,
+}
+
+impl<R: Read> Read for TrackingReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Not `Interrupted`, which io::copy would simply retry
+        self.progress.check_cancelled().map_err(io::Error::other)?;
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        self.size += read as u64;
+        self.progress.add_bytes(read as u64);
+        Ok(read)
+    }
+}
+
+fn part_path(archive_path: &Path, index: usize) -> PathBuf {
+    let mut name = archive_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".part{}", index));
+    archive_path.with_file_name(name)
+}
+
+// Compress the files handed out by `next` into one part archive
+fn write_part(
+    part_path: &Path,
+    files: &[&BackupEntry],
+    next: &AtomicUsize,
+    compression: BackupCompression,
+    progress: &ProgressTracker,
+) -> Result<Vec<ManifestEntry>, String> {
+    let file = File::create(part_path).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+    let mut manifest = Vec::new();
+
+    loop {
+        let index = next.fetch_add(1, Ordering::Relaxed);
+        let Some(entry) = files.get(index) else { break };
+        progress.start_file(&entry.relative_path);
+
+        let options = FileOptions::default()
+            .compression_method(compression.method())
+            .large_file(entry.size > ZIP64_THRESHOLD);
+        zip.start_file(&entry.relative_path, options)
+            .map_err(|e| format!("Failed to start file in zip: {}", e))?;
+
+        let source = File::open(&entry.path)
+            .map_err(|e| format!("Failed to read {}: {}", entry.relative_path, e))?;
+        let mut reader = TrackingReader { inner: source, hasher: Sha256::new(), size: 0, progress };
+        io::copy(&mut reader, &mut zip)
+            .map_err(|e| format!("Failed to compress {}: {}", entry.relative_path, e))?;
+
+        manifest.push(ManifestEntry {
+            path: entry.relative_path.clone(),
+            size: reader.size,
+            sha256: to_hex(&reader.hasher.finalize()),
+        });
+    }
+
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(manifest)
+}
+
+fn write_parallel(
+    archive_path: &Path,
+    entries: &[BackupEntry],
+    compression: BackupCompression,
+    progress: &ProgressTracker,
+    parts: &[PathBuf],
+) -> Result<(u64, EntryManifest), String> {
+    // Largest files first so no worker is left compressing one big file at the end
+    let mut files: Vec<&BackupEntry> = entries.iter().filter(|e| !e.is_dir).collect();
+    files.sort_by_key(|e| std::cmp::Reverse(e.size));
+    let next = AtomicUsize::new(0);
+
+    let results: Vec<Result<Vec<ManifestEntry>, String>> = std::thread::scope(|scope| {
+        let workers: Vec<_> = parts.iter()
+            .map(|part| {
+                let (files, next) = (&files, &next);
+                scope.spawn(move || write_part(part, files, next, compression, progress))
+            })
+            .collect();
+        workers.into_iter()
+            .map(|worker| worker.join().unwrap_or_else(|_| Err("Compression worker panicked".to_string())))
+            .collect()
+    });
+
+    let mut manifest = EntryManifest::default();
+    for result in results {
+        manifest.entries.extend(result?);
+    }
+
+    // Assemble the final archive: directories first, then the compressed entries of every part
+    let file = File::create(archive_path).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+    for entry in entries.iter().filter(|e| e.is_dir) {
+        zip.add_directory(format!("{}/", entry.relative_path), FileOptions::default())
+            .map_err(|e| format!("Failed to add directory: {}", e))?;
+    }
+    for part in parts {
+        let mut archive = ZipArchive::new(File::open(part).map_err(|e| e.to_string())?)
+            .map_err(|e| e.to_string())?;
+        for i in 0..archive.len() {
+            let entry = archive.by_index(i).map_err(|e| e.to_string())?;
+            zip.raw_copy_file(entry).map_err(|e| format!("Failed to assemble archive: {}", e))?;
+        }
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+
+    let total_size = manifest.entries.iter().map(|e| e.size).sum();
+    Ok((total_size, manifest))
+}
+
+/// Write entries into a zip using all cores, returning the total size of the files added and their
+/// entry manifest. Files are streamed from disk, so memory use does not depend on file sizes.
+pub fn write_zip(
+    archive_path: &Path,
+    entries: &[BackupEntry],
+    compression: BackupCompression,
+    progress: &ProgressTracker,
+) -> Result<(u64, EntryManifest), String> {
+    let file_count = entries.iter().filter(|e| !e.is_dir).count();
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(file_count)
+        .max(1);
+    let parts: Vec<PathBuf> = (0..workers).map(|i| part_path(archive_path, i)).collect();
+
+    let result = write_parallel(archive_path, entries, compression, progress, &parts);
+    for part in &parts {
+        let _ = fs::remove_file(part);
+    }
+    if result.is_err() {
+        let _ = fs::remove_file(archive_path);
+        // Report the cancellation rather than whichever read it interrupted
+        progress.check_cancelled()?;
+    }
+    progress.report_now();
+    result
+}
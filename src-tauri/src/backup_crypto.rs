@@ -0,0 +1,568 @@
+use argon2::Argon2;
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::backup::{self, BackupInfo};
+use crate::backup_verify::to_hex;
+
+// Encrypted archives are a fixed header followed by ChaCha20-Poly1305 STREAM segments:
+//   "VLENC\0" | version | key source | key fingerprint (8) | salt (16) | nonce prefix (7)
+//   SEGMENT_SIZE bytes of plaintext + 16 byte tag per segment, the last one shorter (possibly empty)
+// The header is authenticated as associated data of every segment. Keys live in
+// <Documents>/VoidLink/keys/<server_id>.json so they never end up next to the backups.
+const MAGIC: &[u8; 6] = b"VLENC\0";
+const FORMAT_VERSION: u8 = 1;
+const FINGERPRINT_SIZE: usize = 8;
+const SALT_SIZE: usize = 16;
+const NONCE_PREFIX_SIZE: usize = 7; // 12 byte nonce minus the 5 bytes STREAM uses for the counter
+const HEADER_SIZE: usize = MAGIC.len() + 2 + FINGERPRINT_SIZE + SALT_SIZE + NONCE_PREFIX_SIZE;
+const SEGMENT_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+const KEY_SIZE: usize = 32;
+
+// Distinguishes decrypted temp files of concurrent operations
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// Where a server's backup key came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeySource {
+    Passphrase, // Argon2id over the passphrase, salt stored in every archive header
+    KeyFile, // SHA-256 of the key file contents
+}
+
+impl KeySource {
+    fn to_byte(self) -> u8 {
+        match self {
+            KeySource::Passphrase => 1,
+            KeySource::KeyFile => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(KeySource::Passphrase),
+            2 => Some(KeySource::KeyFile),
+            _ => None,
+        }
+    }
+}
+
+pub struct BackupKey {
+    bytes: [u8; KEY_SIZE],
+    source: KeySource,
+    salt: [u8; SALT_SIZE], // zero for key files
+}
+
+impl BackupKey {
+    fn fingerprint(&self) -> [u8; FINGERPRINT_SIZE] {
+        let digest = Sha256::new()
+            .chain_update(b"voidlink-backup-key")
+            .chain_update(self.bytes)
+            .finalize();
+        let mut fingerprint = [0u8; FINGERPRINT_SIZE];
+        fingerprint.copy_from_slice(&digest[..FINGERPRINT_SIZE]);
+        fingerprint
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(&self.bytes))
+    }
+
+    fn status(&self) -> KeyStatus {
+        KeyStatus {
+            source: self.source,
+            fingerprint: to_hex(&self.fingerprint()),
+        }
+    }
+
+    pub fn record(&self) -> KeyRecord {
+        KeyRecord {
+            source: self.source,
+            fingerprint: to_hex(&self.fingerprint()),
+            salt: to_hex(&self.salt),
+        }
+    }
+}
+
+// Key as persisted in the key store
+#[derive(Serialize, Deserialize)]
+struct StoredKey {
+    key: String,
+    source: KeySource,
+    salt: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyStatus {
+    pub source: KeySource,
+    pub fingerprint: String, // identifies the key without revealing it
+}
+
+// The key a backup was encrypted with, kept in the backup metadata so a new key can be checked
+// against it even when the archive itself only exists on the backup target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyRecord {
+    pub source: KeySource,
+    pub fingerprint: String,
+    pub salt: String,
+}
+
+struct Header {
+    source: KeySource,
+    fingerprint: [u8; FINGERPRINT_SIZE],
+    salt: [u8; SALT_SIZE],
+    nonce: [u8; NONCE_PREFIX_SIZE],
+}
+
+impl Header {
+    fn record(&self) -> KeyRecord {
+        KeyRecord {
+            source: self.source,
+            fingerprint: to_hex(&self.fingerprint),
+            salt: to_hex(&self.salt),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.push(self.source.to_byte());
+        bytes.extend_from_slice(&self.fingerprint);
+        bytes.extend_from_slice(&self.salt);
+        bytes.extend_from_slice(&self.nonce);
+        bytes
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_SIZE || !is_encrypted(bytes) {
+            return Err("Not an encrypted backup".to_string());
+        }
+        if bytes[MAGIC.len()] != FORMAT_VERSION {
+            return Err(format!("Unsupported encryption format version {}", bytes[MAGIC.len()]));
+        }
+        let source = KeySource::from_byte(bytes[MAGIC.len() + 1]).ok_or("Unknown key source in encrypted backup")?;
+
+        let mut offset = MAGIC.len() + 2;
+        let mut fingerprint = [0u8; FINGERPRINT_SIZE];
+        fingerprint.copy_from_slice(&bytes[offset..offset + FINGERPRINT_SIZE]);
+        offset += FINGERPRINT_SIZE;
+        let mut salt = [0u8; SALT_SIZE];
+        salt.copy_from_slice(&bytes[offset..offset + SALT_SIZE]);
+        offset += SALT_SIZE;
+        let mut nonce = [0u8; NONCE_PREFIX_SIZE];
+        nonce.copy_from_slice(&bytes[offset..offset + NONCE_PREFIX_SIZE]);
+
+        Ok(Self { source, fingerprint, salt, nonce })
+    }
+}
+
+/// Whether data starts with the encrypted archive magic
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// Fill the buffer unless the reader ends first, returning the number of bytes read
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn encrypt_stream<R: Read, W: Write>(key: &BackupKey, reader: &mut R, writer: &mut W) -> Result<(), String> {
+    let mut nonce = [0u8; NONCE_PREFIX_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+    let header = Header {
+        source: key.source,
+        fingerprint: key.fingerprint(),
+        salt: key.salt,
+        nonce,
+    }.to_bytes();
+    writer.write_all(&header).map_err(|e| e.to_string())?;
+
+    let mut encryptor = EncryptorBE32::from_aead(key.cipher(), GenericArray::from_slice(&nonce));
+    let mut buffer = vec![0u8; SEGMENT_SIZE];
+    loop {
+        let read = read_full(reader, &mut buffer).map_err(|e| e.to_string())?;
+        let payload = Payload { msg: &buffer[..read], aad: &header };
+        // A short segment marks the end, so inputs that fill the last segment get an empty one after it
+        if read < SEGMENT_SIZE {
+            let sealed = encryptor.encrypt_last(payload).map_err(|_| "Encryption failed")?;
+            writer.write_all(&sealed).map_err(|e| e.to_string())?;
+            return writer.flush().map_err(|e| e.to_string());
+        }
+        let sealed = encryptor.encrypt_next(payload).map_err(|_| "Encryption failed")?;
+        writer.write_all(&sealed).map_err(|e| e.to_string())?;
+    }
+}
+
+fn decrypt_stream<R: Read, W: Write>(key: &BackupKey, reader: &mut R, writer: &mut W) -> Result<u64, String> {
+    let mut header_bytes = [0u8; HEADER_SIZE];
+    let read = read_full(reader, &mut header_bytes).map_err(|e| e.to_string())?;
+    let header = Header::parse(&header_bytes[..read])?;
+    if header.fingerprint != key.fingerprint() {
+        return Err("Backup was encrypted with a different key".to_string());
+    }
+
+    let mut decryptor = DecryptorBE32::from_aead(key.cipher(), GenericArray::from_slice(&header.nonce));
+    let mut buffer = vec![0u8; SEGMENT_SIZE + TAG_SIZE];
+    let mut total = 0u64;
+    loop {
+        let read = read_full(reader, &mut buffer).map_err(|e| e.to_string())?;
+        let payload = Payload { msg: &buffer[..read], aad: &header_bytes };
+        // Truncated, reordered or modified segments all fail authentication here
+        if read < buffer.len() {
+            let plain = decryptor.decrypt_last(payload)
+                .map_err(|_| "Encrypted backup is corrupt or was tampered with")?;
+            writer.write_all(&plain).map_err(|e| e.to_string())?;
+            writer.flush().map_err(|e| e.to_string())?;
+            return Ok(total + plain.len() as u64);
+        }
+        let plain = decryptor.decrypt_next(payload)
+            .map_err(|_| "Encrypted backup is corrupt or was tampered with")?;
+        writer.write_all(&plain).map_err(|e| e.to_string())?;
+        total += plain.len() as u64;
+    }
+}
+
+pub fn encrypt_file(key: &BackupKey, src: &Path, dest: &Path) -> Result<(), String> {
+    let mut reader = File::open(src).map_err(|e| e.to_string())?;
+    let mut writer = io::BufWriter::new(File::create(dest).map_err(|e| e.to_string())?);
+    encrypt_stream(key, &mut reader, &mut writer)
+}
+
+pub fn encrypt_bytes(key: &BackupKey, data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(HEADER_SIZE + data.len() + TAG_SIZE);
+    encrypt_stream(key, &mut &data[..], &mut out)?;
+    Ok(out)
+}
+
+pub fn decrypt_bytes(key: &BackupKey, data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(data.len());
+    decrypt_stream(key, &mut &data[..], &mut out)?;
+    Ok(out)
+}
+
+fn read_header(path: &Path) -> Result<Header, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let mut bytes = [0u8; HEADER_SIZE];
+    let read = read_full(&mut file, &mut bytes).map_err(|e| e.to_string())?;
+    Header::parse(&bytes[..read])
+}
+
+/// A readable, unencrypted copy of a backup archive. Temporary copies are deleted on drop.
+pub struct PlainArchive {
+    path: PathBuf,
+    temporary: bool,
+}
+
+impl PlainArchive {
+    /// Reserve a temp file outside the backup folder, so plaintext never lands on a shared drive
+    pub fn temporary(name: &str) -> Self {
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("voidlink-{}-{}-{}", std::process::id(), counter, name));
+        Self { path, temporary: true }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PlainArchive {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// The backup's archive itself, or a decrypted temp copy of it if the backup is encrypted
+pub fn open_archive(backup: &BackupInfo, key: Option<&BackupKey>) -> Result<PlainArchive, String> {
+    if !backup.encrypted {
+        return Ok(PlainArchive { path: PathBuf::from(&backup.path), temporary: false });
+    }
+    let key = key.ok_or("Backup is encrypted but no key was provided")?;
+
+    let plain = PlainArchive::temporary(&backup.name);
+    let mut reader = io::BufReader::new(File::open(&backup.path).map_err(|e| e.to_string())?);
+    let mut writer = io::BufWriter::new(File::create(plain.path()).map_err(|e| e.to_string())?);
+    decrypt_stream(key, &mut reader, &mut writer)
+        .map_err(|e| format!("Failed to decrypt {}: {}", backup.name, e))?;
+    Ok(plain)
+}
+
+pub(crate) fn keys_dir() -> PathBuf {
+    backup::voidlink_dir().unwrap_or_else(|_| PathBuf::from(".")).join("keys")
+}
+
+fn key_path(server_id: &str) -> PathBuf {
+    keys_dir().join(format!("{}.json", server_id))
+}
+
+pub fn load_key(server_id: &str) -> Result<Option<BackupKey>, String> {
+    let path = key_path(server_id);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let stored: StoredKey = serde_json::from_str(&content).map_err(|e| format!("Invalid backup key file: {}", e))?;
+
+    let bytes = from_hex(&stored.key)
+        .and_then(|k| <[u8; KEY_SIZE]>::try_from(k).ok())
+        .ok_or("Invalid backup key file: bad key")?;
+    let salt = from_hex(&stored.salt)
+        .and_then(|s| <[u8; SALT_SIZE]>::try_from(s).ok())
+        .ok_or("Invalid backup key file: bad salt")?;
+    Ok(Some(BackupKey { bytes, source: stored.source, salt }))
+}
+
+fn save_key(server_id: &str, key: &BackupKey) -> Result<(), String> {
+    fs::create_dir_all(keys_dir()).map_err(|e| e.to_string())?;
+    let stored = StoredKey {
+        key: to_hex(&key.bytes),
+        source: key.source,
+        salt: to_hex(&key.salt),
+    };
+    let content = serde_json::to_string_pretty(&stored).map_err(|e| e.to_string())?;
+    write_private_file(&key_path(server_id), &content)
+}
+
+/// Write a file only readable by the current user where the platform supports it
+pub(crate) fn write_private_file(path: &Path, content: impl AsRef<[u8]>) -> Result<(), String> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(|e| e.to_string())?;
+    file.write_all(content.as_ref()).map_err(|e| e.to_string())
+}
+
+/// Key needed to read a backup, None for unencrypted backups
+pub fn key_for(server_id: &str, backup: &BackupInfo) -> Result<Option<BackupKey>, String> {
+    if !backup.encrypted {
+        return Ok(None);
+    }
+    load_key(server_id)?
+        .map(Some)
+        .ok_or_else(|| format!("Backup {} is encrypted but no key is set for this server", backup.name))
+}
+
+fn derive_from_passphrase(passphrase: &str, salt: [u8; SALT_SIZE]) -> Result<BackupKey, String> {
+    let mut bytes = [0u8; KEY_SIZE];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut bytes)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Ok(BackupKey { bytes, source: KeySource::Passphrase, salt })
+}
+
+fn derive_from_key_file(path: &Path) -> Result<BackupKey, String> {
+    let content = fs::read(path).map_err(|e| format!("Failed to read key file: {}", e))?;
+    if content.len() < KEY_SIZE {
+        return Err(format!("Key file must contain at least {} bytes", KEY_SIZE));
+    }
+    let mut bytes = [0u8; KEY_SIZE];
+    bytes.copy_from_slice(&Sha256::digest(&content));
+    Ok(BackupKey { bytes, source: KeySource::KeyFile, salt: [0u8; SALT_SIZE] })
+}
+
+/// Set a server's backup key from a passphrase or a key file. Entering the passphrase or key file
+/// existing encrypted backups were made with (e.g. after reinstalling) recovers access to them;
+/// any other key is refused while such backups exist.
+#[tauri::command]
+pub fn backup_key_set_cmd(
+    server_id: String,
+    passphrase: Option<String>,
+    key_file: Option<String>,
+    custom_path: Option<String>,
+) -> Result<KeyStatus, String> {
+    let backup_dir = backup::get_backup_dir(&server_id, custom_path.as_deref())?;
+    let existing = backup::load_backup_metadata(&backup_dir)?
+        .into_iter()
+        .filter(|b| b.encrypted)
+        // Backups made before the key was recorded in the metadata only have it in their header
+        .find_map(|b| b.key.or_else(|| read_header(Path::new(&b.path)).ok().map(|h| h.record())));
+
+    let key = match (passphrase, key_file) {
+        (Some(passphrase), None) => {
+            if passphrase.is_empty() {
+                return Err("Passphrase must not be empty".to_string());
+            }
+            // Reuse the salt of existing backups so the same passphrase yields the same key
+            let salt = match &existing {
+                Some(record) if record.source == KeySource::Passphrase => from_hex(&record.salt)
+                    .and_then(|s| <[u8; SALT_SIZE]>::try_from(s).ok())
+                    .ok_or("Invalid key salt in backup metadata")?,
+                _ => {
+                    let mut salt = [0u8; SALT_SIZE];
+                    OsRng.fill_bytes(&mut salt);
+                    salt
+                }
+            };
+            derive_from_passphrase(&passphrase, salt)?
+        }
+        (None, Some(key_file)) => derive_from_key_file(Path::new(&key_file))?,
+        _ => return Err("Provide either a passphrase or a key file".to_string()),
+    };
+
+    if let Some(record) = existing {
+        if record.fingerprint != to_hex(&key.fingerprint()) {
+            return Err("Key does not match the one existing encrypted backups were made with".to_string());
+        }
+    }
+
+    save_key(&server_id, &key)?;
+    log::info!("Set {:?} backup key for {}", key.source, server_id);
+    Ok(key.status())
+}
+
+/// Backup key of a server, None if no key is set
+#[tauri::command]
+pub fn backup_key_status_cmd(server_id: String) -> Result<Option<KeyStatus>, String> {
+    Ok(load_key(&server_id)?.map(|key| key.status()))
+}
+
+/// Write a new random key file for use with `backup_key_set_cmd`
+#[tauri::command]
+pub fn backup_key_generate_file_cmd(path: String) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    if path.exists() {
+        return Err("Key file already exists".to_string());
+    }
+    let mut bytes = [0u8; 64];
+    OsRng.fill_bytes(&mut bytes);
+    // Anyone who can read the key file can decrypt the backups
+    write_private_file(&path, bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key(byte: u8) -> BackupKey {
+        BackupKey { bytes: [byte; KEY_SIZE], source: KeySource::KeyFile, salt: [0u8; SALT_SIZE] }
+    }
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn round_trips_across_segment_boundaries() {
+        let key = test_key(7);
+        for len in [0, 1, SEGMENT_SIZE - 1, SEGMENT_SIZE, SEGMENT_SIZE + 1, 2 * SEGMENT_SIZE, 3 * SEGMENT_SIZE + 5] {
+            let data = sample(len);
+            let encrypted = encrypt_bytes(&key, &data).unwrap();
+            assert!(is_encrypted(&encrypted));
+            // One tag per segment, plus the empty last segment when the input fills its last one
+            let segments = len / SEGMENT_SIZE + 1;
+            assert_eq!(encrypted.len(), HEADER_SIZE + len + segments * TAG_SIZE, "length {}", len);
+            assert_eq!(decrypt_bytes(&key, &encrypted).unwrap(), data, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn nonces_differ_between_runs() {
+        let key = test_key(7);
+        let data = sample(100);
+        assert_ne!(encrypt_bytes(&key, &data).unwrap(), encrypt_bytes(&key, &data).unwrap());
+    }
+
+    #[test]
+    fn wrong_key_is_refused() {
+        let encrypted = encrypt_bytes(&test_key(7), &sample(100)).unwrap();
+        let error = decrypt_bytes(&test_key(8), &encrypted).unwrap_err();
+        assert!(error.contains("different key"), "{}", error);
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let key = test_key(7);
+        let encrypted = encrypt_bytes(&key, &sample(2 * SEGMENT_SIZE + 10)).unwrap();
+
+        let mut flipped = encrypted.clone();
+        flipped[HEADER_SIZE + SEGMENT_SIZE + 3] ^= 1;
+        assert!(decrypt_bytes(&key, &flipped).is_err());
+
+        // Dropping the final segment must not decrypt to a shorter backup
+        let truncated = &encrypted[..HEADER_SIZE + 2 * (SEGMENT_SIZE + TAG_SIZE)];
+        assert!(decrypt_bytes(&key, truncated).is_err());
+
+        // Swapping two full segments breaks the STREAM counter
+        let mut reordered = encrypted.clone();
+        let segment = SEGMENT_SIZE + TAG_SIZE;
+        let (first, second) = (HEADER_SIZE, HEADER_SIZE + segment);
+        let copy = reordered[first..first + segment].to_vec();
+        reordered.copy_within(second..second + segment, first);
+        reordered[second..second + segment].copy_from_slice(&copy);
+        assert!(decrypt_bytes(&key, &reordered).is_err());
+
+        // The header is authenticated as associated data
+        let mut header = encrypted.clone();
+        header[HEADER_SIZE - 1] ^= 1;
+        assert!(decrypt_bytes(&key, &header).is_err());
+    }
+
+    #[test]
+    fn header_round_trips() {
+        let header = Header {
+            source: KeySource::Passphrase,
+            fingerprint: [1; FINGERPRINT_SIZE],
+            salt: [2; SALT_SIZE],
+            nonce: [3; NONCE_PREFIX_SIZE],
+        };
+        let bytes = header.to_bytes();
+        assert_eq!(bytes.len(), HEADER_SIZE);
+        let parsed = Header::parse(&bytes).unwrap();
+        assert_eq!(parsed.source, KeySource::Passphrase);
+        assert_eq!((parsed.fingerprint, parsed.salt, parsed.nonce), (header.fingerprint, header.salt, header.nonce));
+
+        assert!(Header::parse(&bytes[..HEADER_SIZE - 1]).is_err());
+        assert!(Header::parse(b"PK\x03\x04 plain zip archive, not encrypted").is_err());
+        let mut newer = bytes.clone();
+        newer[MAGIC.len()] = FORMAT_VERSION + 1;
+        assert!(Header::parse(&newer).is_err_and(|e| e.contains("version")));
+    }
+
+    #[test]
+    fn passphrase_keys_depend_on_the_salt() {
+        let a = derive_from_passphrase("correct horse", [1; SALT_SIZE]).unwrap();
+        let b = derive_from_passphrase("correct horse", [1; SALT_SIZE]).unwrap();
+        let c = derive_from_passphrase("correct horse", [2; SALT_SIZE]).unwrap();
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_ne!(a.fingerprint(), c.fingerprint());
+
+        let encrypted = encrypt_bytes(&a, b"level.dat").unwrap();
+        assert_eq!(decrypt_bytes(&b, &encrypted).unwrap(), b"level.dat");
+    }
+}
@@ -0,0 +1,273 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+// Files and folders left out of backups unless the server's settings say otherwise.
+// Patterns use gitignore syntax relative to the server directory: `*.log` matches at any depth,
+// a trailing `/` only matches directories, a leading `/` anchors to the server directory and
+// `!pattern` re-includes something an earlier pattern excluded.
+const DEFAULT_EXCLUDE_PATTERNS: &[&str] = &[
+    "*.log",
+    "*.log.gz",
+    "*.mixin.out",
+    "crash-reports/",
+    "logs/",
+    ".cache/",
+    "libraries/",
+];
+
+pub fn default_exclude_patterns() -> Vec<String> {
+    DEFAULT_EXCLUDE_PATTERNS.iter().map(|p| p.to_string()).collect()
+}
+
+// A file or directory selected for backup
+pub struct BackupEntry {
+    pub path: PathBuf,
+    pub relative_path: String, // forward slashes, relative to the server directory
+    pub is_dir: bool,
+    pub size: u64, // 0 for directories
+}
+
+// A file or directory left out, with the pattern responsible
+#[derive(Debug, Clone, Serialize)]
+pub struct ExcludedPath {
+    pub path: String,
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewFile {
+    pub path: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupPreview {
+    pub files: Vec<PreviewFile>,
+    pub total_files: u64,
+    pub total_size_bytes: u64,
+    pub excluded: Vec<ExcludedPath>, // excluded directories are listed once, not per file
+}
+
+pub struct BackupFilter {
+    matcher: Gitignore,
+}
+
+impl BackupFilter {
+    pub fn new(server_dir: &Path, patterns: &[String]) -> Result<Self, String> {
+        let mut builder = GitignoreBuilder::new(server_dir);
+        // Matches the old substring matching, and file systems on Windows and macOS
+        builder.case_insensitive(true).map_err(|e| e.to_string())?;
+        for pattern in patterns {
+            builder.add_line(None, pattern)
+                .map_err(|e| format!("Invalid pattern \"{}\": {}", pattern, e))?;
+        }
+        let matcher = builder.build().map_err(|e| e.to_string())?;
+        Ok(Self { matcher })
+    }
+
+    /// The pattern excluding a path relative to the server directory, if any
+    pub fn excluded_by(&self, relative_path: &str, is_dir: bool) -> Option<String> {
+        let matched = self.matcher.matched(relative_path, is_dir);
+        if matched.is_ignore() {
+            matched.inner().map(|glob| glob.original().to_string())
+        } else {
+            None
+        }
+    }
+}
+
+/// Walk the included folders and collect everything the filter lets through.
+/// Excluded directories are not descended into.
+pub fn collect_entries(
+    server_dir: &Path,
+    included_folders: &[String],
+    filter: &BackupFilter,
+    mut on_excluded: impl FnMut(ExcludedPath),
+) -> Vec<BackupEntry> {
+    let mut entries = Vec::new();
+
+    for folder in included_folders {
+        let folder_path = server_dir.join(folder);
+        if !folder_path.exists() {
+            log::warn!("Folder does not exist, skipping: {:?}", folder_path);
+            continue;
+        }
+
+        let walker = WalkDir::new(&folder_path).into_iter().filter_entry(|entry| {
+            let Ok(relative) = entry.path().strip_prefix(server_dir) else { return false };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            match filter.excluded_by(&relative, entry.file_type().is_dir()) {
+                Some(pattern) => {
+                    on_excluded(ExcludedPath { path: relative, pattern });
+                    false
+                }
+                None => true,
+            }
+        });
+
+        for entry in walker {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(e) => {
+                    log::warn!("Error walking directory: {}", e);
+                    continue;
+                }
+            };
+
+            let path = entry.path();
+
+            // Calculate relative path from server directory
+            let relative_path = match path.strip_prefix(server_dir) {
+                Ok(p) => p.to_string_lossy().replace('\\', "/"),
+                Err(_) => continue,
+            };
+
+            if path.is_file() {
+                entries.push(BackupEntry {
+                    path: path.to_path_buf(),
+                    relative_path,
+                    is_dir: false,
+                    size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+                });
+            } else if path.is_dir() && !relative_path.is_empty() {
+                entries.push(BackupEntry {
+                    path: path.to_path_buf(),
+                    relative_path,
+                    is_dir: true,
+                    size: 0,
+                });
+            }
+        }
+    }
+
+    entries
+}
+
+/// Show which files a set of patterns would back up, and their total size
+#[tauri::command]
+pub fn backup_preview_cmd(
+    server_path: String,
+    included_folders: Vec<String>,
+    exclude_patterns: Option<Vec<String>>,
+) -> Result<BackupPreview, String> {
+    let server_dir = PathBuf::from(&server_path);
+    if !server_dir.exists() {
+        return Err("Server path does not exist".to_string());
+    }
+
+    let patterns = exclude_patterns.unwrap_or_else(default_exclude_patterns);
+    let filter = BackupFilter::new(&server_dir, &patterns)?;
+    let mut excluded = Vec::new();
+    let entries = collect_entries(&server_dir, &included_folders, &filter, |path| excluded.push(path));
+
+    let files: Vec<PreviewFile> = entries.into_iter()
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| PreviewFile {
+            path: entry.relative_path,
+            size_bytes: entry.size,
+        })
+        .collect();
+
+    Ok(BackupPreview {
+        total_files: files.len() as u64,
+        total_size_bytes: files.iter().map(|f| f.size_bytes).sum(),
+        files,
+        excluded,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn filter(patterns: &[&str]) -> BackupFilter {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        BackupFilter::new(Path::new("/srv/minecraft"), &patterns).unwrap()
+    }
+
+    #[test]
+    fn default_patterns_exclude_logs_and_caches() {
+        let filter = BackupFilter::new(Path::new("/srv/minecraft"), &default_exclude_patterns()).unwrap();
+        assert_eq!(filter.excluded_by("logs", true).as_deref(), Some("logs/"));
+        assert_eq!(filter.excluded_by("world/debug.log", false).as_deref(), Some("*.log"));
+        assert_eq!(filter.excluded_by("logs/2024-01-01-1.log.gz", false).as_deref(), Some("*.log.gz"));
+        assert_eq!(filter.excluded_by("plugins/.cache", true).as_deref(), Some(".cache/"));
+        assert_eq!(filter.excluded_by("world/region/r.0.0.mca", false), None);
+        assert_eq!(filter.excluded_by("world", true), None);
+    }
+
+    #[test]
+    fn trailing_slash_only_matches_directories() {
+        let filter = filter(&["backups/"]);
+        assert!(filter.excluded_by("backups", true).is_some());
+        assert!(filter.excluded_by("plugins/backups", true).is_some());
+        assert!(filter.excluded_by("backups", false).is_none());
+    }
+
+    #[test]
+    fn leading_slash_anchors_to_the_server_directory() {
+        let filter = filter(&["/cache"]);
+        assert!(filter.excluded_by("cache", true).is_some());
+        assert!(filter.excluded_by("plugins/dynmap/cache", true).is_none());
+    }
+
+    #[test]
+    fn negation_reincludes_paths() {
+        let filter = filter(&["*.json", "!ops.json"]);
+        assert_eq!(filter.excluded_by("usercache.json", false).as_deref(), Some("*.json"));
+        assert_eq!(filter.excluded_by("ops.json", false), None);
+        assert_eq!(filter.excluded_by("world/ops.json", false), None);
+    }
+
+    #[test]
+    fn matching_ignores_case() {
+        let filter = filter(&["*.log", "Crash-Reports/"]);
+        assert!(filter.excluded_by("LATEST.LOG", false).is_some());
+        assert!(filter.excluded_by("crash-reports", true).is_some());
+    }
+
+    #[test]
+    fn invalid_patterns_are_reported() {
+        let error = BackupFilter::new(Path::new("/srv/minecraft"), &["world/{region,poi".to_string()]).err().unwrap();
+        assert!(error.contains("world/{region,poi"), "{}", error);
+    }
+
+    #[test]
+    fn collect_skips_excluded_directories() {
+        let server_dir = std::env::temp_dir().join(format!("voidlink-filter-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&server_dir);
+        for dir in ["world/region", "world/logs", "config"] {
+            fs::create_dir_all(server_dir.join(dir)).unwrap();
+        }
+        fs::write(server_dir.join("world/level.dat"), b"level").unwrap();
+        fs::write(server_dir.join("world/region/r.0.0.mca"), b"region").unwrap();
+        fs::write(server_dir.join("world/logs/a.txt"), b"log").unwrap();
+        fs::write(server_dir.join("world/logs/b.txt"), b"log").unwrap();
+        fs::write(server_dir.join("world/session.log"), b"log").unwrap();
+
+        let filter = BackupFilter::new(&server_dir, &default_exclude_patterns()).unwrap();
+        let mut excluded = Vec::new();
+        let folders = ["world".to_string(), "missing".to_string()];
+        let entries = collect_entries(&server_dir, &folders, &filter, |path| excluded.push(path));
+        let _ = fs::remove_dir_all(&server_dir);
+
+        let mut files: Vec<(&str, u64)> = entries.iter()
+            .filter(|e| !e.is_dir)
+            .map(|e| (e.relative_path.as_str(), e.size))
+            .collect();
+        files.sort();
+        assert_eq!(files, vec![("world/level.dat", 5), ("world/region/r.0.0.mca", 6)]);
+        assert!(entries.iter().any(|e| e.is_dir && e.relative_path == "world/region"));
+
+        // The logs folder is reported once instead of per file
+        let mut excluded: Vec<(String, String)> = excluded.into_iter().map(|e| (e.path, e.pattern)).collect();
+        excluded.sort();
+        assert_eq!(excluded, vec![
+            ("world/logs".to_string(), "logs/".to_string()),
+            ("world/session.log".to_string(), "*.log".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,523 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+use crate::backup::{self, BackupFormat, BackupInfo, OpenedBackup};
+use crate::backup_snapshot;
+use crate::jobs::{self, Job};
+
+// Restores are extracted into a staging directory inside the server directory (same filesystem,
+// so the final swap is a rename), and the replaced folders are parked until the swap succeeds:
+//   <server>/.voidlink-restore-<stamp>/staging/<folder>   extracted backup
+//   <server>/.voidlink-restore-<stamp>/previous/<folder>  originals, removed once committed
+const RESTORE_DIR_PREFIX: &str = ".voidlink-restore-";
+
+// Anvil regions cover 32x32 chunks of 16 blocks
+const REGION_SIZE_BLOCKS: i64 = 512;
+// Folders of a dimension holding r.<x>.<z>.mca files
+const REGION_FOLDERS: &[&str] = &["region", "entities", "poi"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreReport {
+    pub pre_restore: BackupInfo,
+    pub replaced_folders: Vec<String>,
+    pub skipped_folders: Vec<String>, // listed in the backup but not present in it
+    pub restored_files: u64,
+}
+
+// One level of the backup contents
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupTreeEntry {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    pub size_bytes: u64,  // total of all files below a directory
+    pub file_count: u64,
+}
+
+// Block coordinate rectangle (inclusive) within one dimension folder,
+// e.g. "world", "world/DIM-1" or "world_nether/DIM-1"
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegionRect {
+    pub dimension: String,
+    pub min_x: i64,
+    pub min_z: i64,
+    pub max_x: i64,
+    pub max_z: i64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RestoreSelection {
+    #[serde(default)]
+    pub paths: Vec<String>, // files or directories, relative to the server directory
+    #[serde(default)]
+    pub region_rect: Option<RegionRect>,
+}
+
+pub struct RestoreWorkspace {
+    root: PathBuf,
+    keep: bool, // set when originals could not be put back and must survive for manual recovery
+}
+
+impl RestoreWorkspace {
+    pub fn create(server_dir: &Path) -> Result<Self, String> {
+        let stamp = chrono::Local::now().format("%Y%m%d_%H%M%S%3f");
+        let root = server_dir.join(format!("{}{}", RESTORE_DIR_PREFIX, stamp));
+        fs::create_dir_all(root.join("staging")).map_err(|e| e.to_string())?;
+        fs::create_dir_all(root.join("previous")).map_err(|e| e.to_string())?;
+        Ok(Self { root, keep: false })
+    }
+
+    pub fn staging(&self) -> PathBuf {
+        self.root.join("staging")
+    }
+
+    fn previous(&self) -> PathBuf {
+        self.root.join("previous")
+    }
+}
+
+impl Drop for RestoreWorkspace {
+    fn drop(&mut self) {
+        // Unless kept, this runs after a commit or a completed rollback and nothing here is still needed
+        if self.keep {
+            return;
+        }
+        if let Err(e) = fs::remove_dir_all(&self.root) {
+            log::warn!("Failed to clean up restore workspace {:?}: {}", self.root, e);
+        }
+    }
+}
+
+/// Remove restore workspaces left behind by a crash mid-restore
+pub fn cleanup_stale_workspaces(server_dir: &Path) {
+    if let Ok(entries) = fs::read_dir(server_dir) {
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with(RESTORE_DIR_PREFIX) {
+                let previous = entry.path().join("previous");
+                // Never throw away originals that were not put back
+                if fs::read_dir(&previous).map(|mut d| d.next().is_none()).unwrap_or(true) {
+                    let _ = fs::remove_dir_all(entry.path());
+                } else {
+                    log::warn!("Keeping interrupted restore with original data: {:?}", entry.path());
+                }
+            }
+        }
+    }
+}
+
+/// Every file in a backup with its size
+pub fn list_backup_files(opened: &OpenedBackup) -> Result<Vec<(String, u64)>, String> {
+    if opened.backup.format == BackupFormat::Snapshot {
+        let manifest = backup_snapshot::load_manifest(Path::new(&opened.backup.path))?;
+        return Ok(manifest.files.into_iter().map(|f| (f.path, f.size)).collect());
+    }
+
+    let file = File::open(opened.plain.path()).map_err(|e| e.to_string())?;
+    let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let mut files = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        if !entry.is_dir() {
+            files.push((entry.name().trim_end_matches('/').to_string(), entry.size()));
+        }
+    }
+    Ok(files)
+}
+
+/// Direct children of `dir` ("" for the root) built from a flat file list
+pub fn tree_children(files: &[(String, u64)], dir: &str) -> Vec<BackupTreeEntry> {
+    let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+    let mut children: BTreeMap<String, BackupTreeEntry> = BTreeMap::new();
+
+    for (path, size) in files {
+        let Some(rest) = path.strip_prefix(&prefix) else { continue };
+        let (name, is_dir) = match rest.split_once('/') {
+            Some((name, _)) => (name, true),
+            None => (rest, false),
+        };
+        let child = children.entry(name.to_string()).or_insert_with(|| BackupTreeEntry {
+            name: name.to_string(),
+            path: format!("{}{}", prefix, name),
+            is_dir,
+            size_bytes: 0,
+            file_count: 0,
+        });
+        child.size_bytes += size;
+        child.file_count += 1;
+    }
+
+    // Directories first, then files, each alphabetically
+    let mut children: Vec<BackupTreeEntry> = children.into_values().collect();
+    children.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    children
+}
+
+fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/").trim_matches('/').to_string()
+}
+
+fn is_under(path: &str, root: &str) -> bool {
+    path == root || path.strip_prefix(root).is_some_and(|rest| rest.starts_with('/'))
+}
+
+// Parse "<dimension>/<region folder>/r.<x>.<z>.mca" into its region coordinates
+fn region_coords(path: &str, dimension: &str) -> Option<(i64, i64)> {
+    let rest = path.strip_prefix(dimension)?.strip_prefix('/')?;
+    let (folder, file) = rest.split_once('/')?;
+    if !REGION_FOLDERS.contains(&folder) {
+        return None;
+    }
+    let mut parts = file.strip_prefix("r.")?.strip_suffix(".mca")?.split('.');
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    parts.next().is_none().then_some((x, z))
+}
+
+/// Resolve a selection against the files of a backup. Returns the paths to swap in
+/// (no path nested in another) and the requested paths the backup does not contain.
+pub fn resolve_selection(files: &[(String, u64)], selection: &RestoreSelection) -> (Vec<String>, Vec<String>) {
+    let mut roots = Vec::new();
+    let mut unmatched = Vec::new();
+
+    for path in selection.paths.iter().map(|p| normalize_path(p)) {
+        if !path.is_empty() && files.iter().any(|(f, _)| is_under(f, &path)) {
+            roots.push(path);
+        } else {
+            unmatched.push(path);
+        }
+    }
+
+    if let Some(rect) = &selection.region_rect {
+        let dimension = normalize_path(&rect.dimension);
+        let (min_rx, max_rx) = (
+            rect.min_x.min(rect.max_x).div_euclid(REGION_SIZE_BLOCKS),
+            rect.min_x.max(rect.max_x).div_euclid(REGION_SIZE_BLOCKS),
+        );
+        let (min_rz, max_rz) = (
+            rect.min_z.min(rect.max_z).div_euclid(REGION_SIZE_BLOCKS),
+            rect.min_z.max(rect.max_z).div_euclid(REGION_SIZE_BLOCKS),
+        );
+        let before = roots.len();
+        for (path, _) in files {
+            if let Some((x, z)) = region_coords(path, &dimension) {
+                if (min_rx..=max_rx).contains(&x) && (min_rz..=max_rz).contains(&z) {
+                    roots.push(path.clone());
+                }
+            }
+        }
+        if roots.len() == before {
+            unmatched.push(format!("{} regions {}..{}, {}..{}", dimension, min_rx, max_rx, min_rz, max_rz));
+        }
+    }
+
+    // Drop paths already covered by a selected parent directory
+    roots.sort();
+    roots.dedup();
+    let mut resolved: Vec<String> = Vec::with_capacity(roots.len());
+    for root in roots {
+        if !resolved.iter().any(|r| is_under(&root, r)) {
+            resolved.push(root);
+        }
+    }
+    (resolved, unmatched)
+}
+
+/// Extract the entries of a backup accepted by `filter` into `dest`, returning the number of files written
+pub fn extract_backup(
+    opened: &OpenedBackup,
+    dest: &Path,
+    filter: &dyn Fn(&str) -> bool,
+    job: &Job,
+) -> Result<u64, String> {
+    let backup = &opened.backup;
+    if backup.format == BackupFormat::Snapshot {
+        return backup_snapshot::restore_snapshot(&opened.backup_dir, Path::new(&backup.path), dest, filter, job);
+    }
+
+    let file = File::open(opened.plain.path()).map_err(|e| e.to_string())?;
+    let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let mut files = 0u64;
+
+    for i in 0..archive.len() {
+        job.check_cancelled()?;
+        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+        if !filter(file.name().trim_end_matches('/')) {
+            continue;
+        }
+        let relative = file.enclosed_name()
+            .map(Path::to_path_buf)
+            .ok_or_else(|| format!("Unsafe path in backup: {}", file.name()))?;
+        let out_path = dest.join(relative);
+
+        if file.is_dir() {
+            fs::create_dir_all(&out_path).map_err(|e| e.to_string())?;
+        } else {
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let mut outfile = File::create(&out_path).map_err(|e| e.to_string())?;
+            let written = std::io::copy(&mut file, &mut outfile)
+                .map_err(|e| format!("Failed to extract {}: {}", file.name(), e))?;
+            if written != file.size() {
+                return Err(format!("Truncated entry in backup: {}", file.name()));
+            }
+            files += 1;
+        }
+    }
+
+    Ok(files)
+}
+
+/// Split the folders to restore into those present in the staging directory and those missing
+pub fn staged_folders(staging: &Path, folders: &[String]) -> (Vec<String>, Vec<String>) {
+    folders.iter()
+        .cloned()
+        .partition(|folder| staging.join(folder).exists())
+}
+
+/// Move each staged folder or file into the server directory, parking the originals first.
+/// On any error every folder already swapped is put back before returning.
+pub fn swap_in(server_dir: &Path, workspace: &mut RestoreWorkspace, folders: &[String]) -> Result<(), String> {
+    let staging = workspace.staging();
+    let previous = workspace.previous();
+    // (folder, original was parked)
+    let mut swapped: Vec<(&String, bool)> = Vec::new();
+
+    for folder in folders {
+        let target = server_dir.join(folder);
+        let parked = previous.join(folder);
+
+        let result = (|| -> Result<bool, String> {
+            let had_original = target.exists();
+            if had_original {
+                if let Some(parent) = parked.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                fs::rename(&target, &parked)
+                    .map_err(|e| format!("Failed to move {} aside: {}", folder, e))?;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            if let Err(e) = fs::rename(staging.join(folder), &target) {
+                if had_original {
+                    let _ = fs::rename(&parked, &target);
+                }
+                return Err(format!("Failed to move restored {} into place: {}", folder, e));
+            }
+            Ok(had_original)
+        })();
+
+        match result {
+            Ok(had_original) => swapped.push((folder, had_original)),
+            Err(e) => {
+                log::error!("Restore swap failed, rolling back: {}", e);
+                if parked.exists() {
+                    // The original of this folder could not be moved back either
+                    workspace.keep = true;
+                }
+                rollback(server_dir, workspace, &swapped)?;
+                return Err(e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn rollback(server_dir: &Path, workspace: &mut RestoreWorkspace, swapped: &[(&String, bool)]) -> Result<(), String> {
+    let mut failed = Vec::new();
+    for (folder, had_original) in swapped.iter().rev() {
+        let target = server_dir.join(folder);
+        let restored = fs::remove_dir_all(&target).or_else(|_| fs::remove_file(&target));
+        let put_back = if *had_original {
+            restored.and_then(|_| fs::rename(workspace.previous().join(folder), &target))
+        } else {
+            restored
+        };
+        if let Err(e) = put_back {
+            log::error!("Rollback of {} failed: {}", folder, e);
+            failed.push(folder.as_str());
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        workspace.keep = true;
+        Err(format!(
+            "Rollback failed for {}; originals are kept in {}",
+            failed.join(", "),
+            workspace.previous().display()
+        ))
+    }
+}
+
+/// List one level of a backup's contents ("" or None for the top level)
+#[tauri::command]
+pub async fn backup_browse_cmd(
+    server_id: String,
+    backup_id: String,
+    path: Option<String>,
+    custom_path: Option<String>,
+) -> Result<Vec<BackupTreeEntry>, String> {
+    // Remote and encrypted backups are downloaded and decrypted first
+    let handle = tauri::async_runtime::spawn_blocking(move || {
+        let opened = backup::open_backup(&server_id, &backup_id, custom_path.as_deref())?;
+        let files = list_backup_files(&opened)?;
+        Ok(tree_children(&files, &normalize_path(path.as_deref().unwrap_or(""))))
+    });
+    handle.await.map_err(|e| e.to_string())?
+}
+
+/// Restore only the selected files, directories or region range from a backup.
+/// The top-level folders touched are saved in a pre-restore backup first.
+#[tauri::command]
+pub async fn backup_restore_selective_cmd(
+    app: tauri::AppHandle,
+    server_path: String,
+    server_id: String,
+    backup_id: String,
+    selection: RestoreSelection,
+    custom_path: Option<String>,
+    force: Option<bool>,
+) -> Result<RestoreReport, String> {
+    let handle = tauri::async_runtime::spawn_blocking(move || {
+        jobs::run(&app, "restore", &server_id, |job| {
+            restore_selection(&app, job, &server_path, &server_id, &backup_id, &selection, custom_path, force)
+        })
+    });
+
+    handle.await.map_err(|e| e.to_string())?
+}
+
+#[allow(clippy::too_many_arguments)]
+fn restore_selection(
+    app: &tauri::AppHandle,
+    job: &Job,
+    server_path: &str,
+    server_id: &str,
+    backup_id: &str,
+    selection: &RestoreSelection,
+    custom_path: Option<String>,
+    force: Option<bool>,
+) -> Result<RestoreReport, String> {
+    let server_dir = PathBuf::from(server_path);
+
+    // Resolve against the backup before taking the pre-restore backup so bad selections fail fast.
+    // The archive is decrypted once here and read from that copy by every later step.
+    let opened = backup::open_backup(server_id, backup_id, custom_path.as_deref())?;
+    let files = list_backup_files(&opened)?;
+    let (roots, skipped_folders) = resolve_selection(&files, selection);
+    if roots.is_empty() {
+        return Err("Nothing in the backup matches the selection".to_string());
+    }
+
+    let mut top_level: Vec<String> = roots.iter()
+        .map(|r| r.split('/').next().unwrap_or(r).to_string())
+        .collect();
+    top_level.dedup();
+
+    let pre_restore = backup::prepare_restore(app, server_path, server_id, &opened, top_level, custom_path, force)?;
+    job.check_cancelled()?;
+
+    cleanup_stale_workspaces(&server_dir);
+    let mut workspace = RestoreWorkspace::create(&server_dir)?;
+    let restored_files = extract_backup(&opened, &workspace.staging(), &|path| {
+        roots.iter().any(|root| is_under(path, root))
+    }, job)?;
+
+    job.check_cancelled()?;
+    swap_in(&server_dir, &mut workspace, &roots)?;
+
+    log::info!("Selectively restored {} files from {}: {}", restored_files, opened.backup.name, roots.join(", "));
+
+    Ok(RestoreReport {
+        pre_restore,
+        replaced_folders: roots,
+        skipped_folders,
+        restored_files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(paths: &[&str]) -> Vec<(String, u64)> {
+        paths.iter().map(|p| (p.to_string(), 1)).collect()
+    }
+
+    fn rect(dimension: &str, min_x: i64, min_z: i64, max_x: i64, max_z: i64) -> RestoreSelection {
+        RestoreSelection {
+            paths: Vec::new(),
+            region_rect: Some(RegionRect { dimension: dimension.to_string(), min_x, min_z, max_x, max_z }),
+        }
+    }
+
+    #[test]
+    fn region_coords_parses_region_folders_only() {
+        assert_eq!(region_coords("world/region/r.-1.2.mca", "world"), Some((-1, 2)));
+        assert_eq!(region_coords("world/entities/r.0.0.mca", "world"), Some((0, 0)));
+        assert_eq!(region_coords("world/DIM-1/region/r.3.-4.mca", "world/DIM-1"), Some((3, -4)));
+        assert_eq!(region_coords("world/data/r.0.0.mca", "world"), None);
+        assert_eq!(region_coords("world/region/r.0.0.0.mca", "world"), None);
+        assert_eq!(region_coords("world/region/r.a.0.mca", "world"), None);
+        assert_eq!(region_coords("world_nether/region/r.0.0.mca", "world"), None);
+    }
+
+    #[test]
+    fn rect_selects_overlapping_regions() {
+        let backup = files(&[
+            "world/region/r.-1.-1.mca",
+            "world/region/r.-1.0.mca",
+            "world/region/r.0.0.mca",
+            "world/region/r.1.0.mca",
+            "world/entities/r.0.0.mca",
+            "world/poi/r.2.0.mca",
+        ]);
+        // Blocks -1..511 span regions -1 and 0 on x, 0..100 only region 0 on z
+        let (resolved, unmatched) = resolve_selection(&backup, &rect("world", -1, 0, 511, 100));
+        assert_eq!(resolved, vec![
+            "world/entities/r.0.0.mca",
+            "world/region/r.-1.0.mca",
+            "world/region/r.0.0.mca",
+        ]);
+        assert!(unmatched.is_empty());
+    }
+
+    #[test]
+    fn rect_corners_are_normalized() {
+        let backup = files(&["world/region/r.0.0.mca", "world/region/r.1.1.mca"]);
+        let (resolved, _) = resolve_selection(&backup, &rect("world", 1023, 1023, 0, 0));
+        assert_eq!(resolved, vec!["world/region/r.0.0.mca", "world/region/r.1.1.mca"]);
+    }
+
+    #[test]
+    fn rect_stays_in_its_dimension() {
+        let backup = files(&["world/region/r.0.0.mca", "world/DIM-1/region/r.0.0.mca"]);
+        let (resolved, _) = resolve_selection(&backup, &rect("world/DIM-1/", 0, 0, 10, 10));
+        assert_eq!(resolved, vec!["world/DIM-1/region/r.0.0.mca"]);
+    }
+
+    #[test]
+    fn rect_without_regions_is_unmatched() {
+        let backup = files(&["world/region/r.0.0.mca"]);
+        let (resolved, unmatched) = resolve_selection(&backup, &rect("world", 5000, 5000, 6000, 6000));
+        assert!(resolved.is_empty());
+        assert_eq!(unmatched, vec!["world regions 9..11, 9..11"]);
+    }
+
+    #[test]
+    fn paths_covered_by_a_parent_are_dropped() {
+        let backup = files(&["world/region/r.0.0.mca", "world/level.dat", "config/a.toml"]);
+        let mut selection = rect("world", 0, 0, 0, 0);
+        selection.paths = vec!["world".to_string(), "plugins".to_string(), "config\\a.toml".to_string()];
+        let (resolved, unmatched) = resolve_selection(&backup, &selection);
+        assert_eq!(resolved, vec!["config/a.toml", "world"]);
+        assert_eq!(unmatched, vec!["plugins"]);
+    }
+}
@@ -0,0 +1,262 @@
+use chrono::{DateTime, Datelike, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::backup::BackupInfo;
+
+// Grandfather-father-son retention for automatic backups, stored in `BackupSettings`.
+// Each rule keeps the newest backup of its N most recent periods; a backup kept by any rule survives.
+// Manual backups are never rotated and pinned backups are never touched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    #[serde(default)]
+    pub keep_last: u32,
+    #[serde(default)]
+    pub keep_hourly: u32,
+    #[serde(default)]
+    pub keep_daily: u32,
+    #[serde(default)]
+    pub keep_weekly: u32,
+    #[serde(default)]
+    pub keep_monthly: u32,
+    #[serde(default = "default_keep_pre_restore")]
+    pub keep_pre_restore: u32, // pre-restore backups are only ever kept by count
+}
+
+fn default_keep_pre_restore() -> u32 {
+    3
+}
+
+impl RetentionPolicy {
+    /// Policy equivalent to the old "keep the newest `max_backups`" rotation
+    pub fn keep_last(max_backups: u32) -> Self {
+        Self {
+            keep_last: max_backups,
+            keep_hourly: 0,
+            keep_daily: 0,
+            keep_weekly: 0,
+            keep_monthly: 0,
+            keep_pre_restore: default_keep_pre_restore(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedBackup {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+    pub backup_type: String,
+    pub size_bytes: u64,
+    pub reasons: Vec<String>, // why it is kept, empty for deletions
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RotationPlan {
+    pub dry_run: bool,
+    pub keep: Vec<PlannedBackup>,
+    pub delete: Vec<PlannedBackup>,
+    pub freed_bytes: u64,
+}
+
+fn planned(backup: &BackupInfo, reasons: Vec<String>) -> PlannedBackup {
+    PlannedBackup {
+        id: backup.id.clone(),
+        name: backup.name.clone(),
+        created_at: backup.created_at.clone(),
+        backup_type: backup.backup_type.clone(),
+        size_bytes: backup.size_bytes,
+        reasons,
+    }
+}
+
+// Keep the newest backup of each of the `count` most recent periods, as given by `period`
+fn keep_per_period<K: Eq + std::hash::Hash>(
+    dated: &[(usize, DateTime<Local>)],
+    count: u32,
+    label: &str,
+    period: impl Fn(&DateTime<Local>) -> K,
+    reasons: &mut [Vec<String>],
+) {
+    let mut seen = HashSet::new();
+    for (index, created) in dated {
+        if seen.len() >= count as usize {
+            break;
+        }
+        if seen.insert(period(created)) {
+            reasons[*index].push(label.to_string());
+        }
+    }
+}
+
+/// Decide which backups the policy keeps and which it deletes, without deleting anything
+pub fn plan_rotation(backups: &[BackupInfo], policy: &RetentionPolicy) -> RotationPlan {
+    let mut reasons: Vec<Vec<String>> = vec![Vec::new(); backups.len()];
+    let mut auto: Vec<(usize, DateTime<Local>)> = Vec::new();
+    let mut pre_restore: Vec<(usize, DateTime<Local>)> = Vec::new();
+
+    for (index, backup) in backups.iter().enumerate() {
+        if backup.pinned {
+            reasons[index].push("pinned".to_string());
+            continue;
+        }
+        let created = match DateTime::parse_from_rfc3339(&backup.created_at) {
+            Ok(created) => created.with_timezone(&Local),
+            Err(_) => {
+                reasons[index].push("unknown creation date".to_string());
+                continue;
+            }
+        };
+        match backup.backup_type.as_str() {
+            "auto" => auto.push((index, created)),
+            "pre-restore" => pre_restore.push((index, created)),
+            _ => reasons[index].push(format!("{} backup", backup.backup_type)),
+        }
+    }
+
+    // Newest first, so each rule picks the most recent backups of its periods
+    auto.sort_by_key(|b| std::cmp::Reverse(b.1));
+    pre_restore.sort_by_key(|b| std::cmp::Reverse(b.1));
+
+    for (index, _) in auto.iter().take(policy.keep_last as usize) {
+        reasons[*index].push("last".to_string());
+    }
+    keep_per_period(&auto, policy.keep_hourly, "hourly", |d| d.format("%Y-%m-%d %H").to_string(), &mut reasons);
+    keep_per_period(&auto, policy.keep_daily, "daily", |d| d.date_naive(), &mut reasons);
+    keep_per_period(&auto, policy.keep_weekly, "weekly", |d| d.iso_week(), &mut reasons);
+    keep_per_period(&auto, policy.keep_monthly, "monthly", |d| (d.year(), d.month()), &mut reasons);
+    for (index, _) in pre_restore.iter().take(policy.keep_pre_restore as usize) {
+        reasons[*index].push("recent pre-restore".to_string());
+    }
+
+    let mut plan = RotationPlan {
+        dry_run: true,
+        keep: Vec::new(),
+        delete: Vec::new(),
+        freed_bytes: 0,
+    };
+    for (backup, reasons) in backups.iter().zip(reasons) {
+        if reasons.is_empty() {
+            plan.freed_bytes += backup.size_bytes;
+            plan.delete.push(planned(backup, reasons));
+        } else {
+            plan.keep.push(planned(backup, reasons));
+        }
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn backup(id: &str, backup_type: &str, created: DateTime<Local>) -> BackupInfo {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id,
+            "created_at": created.to_rfc3339(),
+            "size_bytes": 10,
+            "path": format!("/backups/{}.zip", id),
+            "backup_type": backup_type,
+            "included_folders": ["world"],
+        })).unwrap()
+    }
+
+    fn at(day: u32, hour: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, day, hour, 0, 0).unwrap()
+    }
+
+    fn policy(keep_last: u32, keep_hourly: u32, keep_daily: u32, keep_weekly: u32, keep_monthly: u32) -> RetentionPolicy {
+        RetentionPolicy { keep_last, keep_hourly, keep_daily, keep_weekly, keep_monthly, keep_pre_restore: 1 }
+    }
+
+    fn ids(backups: &[PlannedBackup]) -> Vec<&str> {
+        let mut ids: Vec<&str> = backups.iter().map(|b| b.id.as_str()).collect();
+        ids.sort();
+        ids
+    }
+
+    fn reasons<'a>(plan: &'a RotationPlan, id: &str) -> &'a [String] {
+        &plan.keep.iter().find(|b| b.id == id).unwrap().reasons
+    }
+
+    #[test]
+    fn keep_last_keeps_newest_auto_backups() {
+        let backups: Vec<BackupInfo> = (1..=5).map(|day| backup(&format!("a{}", day), "auto", at(day, 12))).collect();
+        let plan = plan_rotation(&backups, &RetentionPolicy::keep_last(3));
+        assert_eq!(ids(&plan.keep), vec!["a3", "a4", "a5"]);
+        assert_eq!(ids(&plan.delete), vec!["a1", "a2"]);
+        assert_eq!(plan.freed_bytes, 20);
+        assert!(plan.dry_run);
+    }
+
+    #[test]
+    fn daily_keeps_newest_backup_of_each_day() {
+        let mut backups = Vec::new();
+        for day in 1..=3 {
+            for hour in [6, 12, 18] {
+                backups.push(backup(&format!("d{}h{}", day, hour), "auto", at(day, hour)));
+            }
+        }
+        let plan = plan_rotation(&backups, &policy(0, 0, 2, 0, 0));
+        assert_eq!(ids(&plan.keep), vec!["d2h18", "d3h18"]);
+        assert_eq!(plan.delete.len(), 7);
+    }
+
+    #[test]
+    fn hourly_counts_distinct_hours() {
+        let backups = vec![
+            backup("h12a", "auto", at(1, 12)),
+            backup("h12b", "auto", at(1, 12) + chrono::Duration::minutes(30)),
+            backup("h11", "auto", at(1, 11)),
+            backup("h10", "auto", at(1, 10)),
+        ];
+        let plan = plan_rotation(&backups, &policy(0, 2, 0, 0, 0));
+        assert_eq!(ids(&plan.keep), vec!["h11", "h12b"]);
+    }
+
+    #[test]
+    fn rules_combine_and_record_every_reason() {
+        // 2024-03-04 is a Monday, so day 3 and day 4 fall into different ISO weeks
+        let backups = vec![
+            backup("feb", "auto", Local.with_ymd_and_hms(2024, 2, 20, 12, 0, 0).unwrap()),
+            backup("sun", "auto", at(3, 12)),
+            backup("mon", "auto", at(4, 12)),
+            backup("tue", "auto", at(5, 12)),
+        ];
+        let plan = plan_rotation(&backups, &policy(1, 0, 1, 2, 2));
+        assert_eq!(ids(&plan.keep), vec!["feb", "sun", "tue"]);
+        assert_eq!(ids(&plan.delete), vec!["mon"]);
+        assert_eq!(reasons(&plan, "tue"), ["last", "daily", "weekly", "monthly"]);
+        assert_eq!(reasons(&plan, "sun"), ["weekly"]);
+        assert_eq!(reasons(&plan, "feb"), ["monthly"]);
+    }
+
+    #[test]
+    fn manual_pinned_and_undated_backups_are_kept() {
+        let mut pinned = backup("pinned", "auto", at(1, 12));
+        pinned.pinned = true;
+        let mut undated = backup("undated", "auto", at(2, 12));
+        undated.created_at = "not a date".to_string();
+        let backups = vec![pinned, undated, backup("manual", "manual", at(3, 12)), backup("auto", "auto", at(4, 12))];
+
+        let plan = plan_rotation(&backups, &policy(0, 0, 0, 0, 0));
+        assert_eq!(ids(&plan.keep), vec!["manual", "pinned", "undated"]);
+        assert_eq!(ids(&plan.delete), vec!["auto"]);
+        assert_eq!(reasons(&plan, "manual"), ["manual backup"]);
+    }
+
+    #[test]
+    fn pre_restore_backups_are_kept_by_count_only() {
+        let backups = vec![
+            backup("p1", "pre-restore", at(1, 12)),
+            backup("p2", "pre-restore", at(2, 12)),
+            backup("a1", "auto", at(2, 13)),
+        ];
+        let plan = plan_rotation(&backups, &policy(5, 0, 5, 0, 0));
+        assert_eq!(ids(&plan.keep), vec!["a1", "p2"]);
+        assert_eq!(ids(&plan.delete), vec!["p1"]);
+        assert_eq!(reasons(&plan, "p2"), ["recent pre-restore"]);
+    }
+}
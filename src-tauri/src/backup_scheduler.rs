@@ -0,0 +1,199 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tauri::Emitter;
+
+use crate::backup::{self, BackupSettings};
+use crate::rcon::RconCredentials;
+use crate::server;
+
+// Every server in Documents/VoidLink/servers is checked on each tick, its backup settings
+// decide whether and how often it is backed up. Only the bookkeeping of past runs lives here.
+static SCHEDULE_STATE: LazyLock<Mutex<HashMap<String, ScheduleState>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// How often the scheduler checks whether a backup is due
+const SCHEDULER_TICK_SECS: u64 = 30;
+
+#[derive(Debug, Default)]
+struct ScheduleState {
+    last_attempt: Option<DateTime<Local>>,
+    in_progress: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduledServer {
+    pub server_id: String, // folder name
+    pub server_path: PathBuf,
+    pub rcon: Option<RconCredentials>, // used for save-off / save-all flush
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduleStatus {
+    pub server_id: String,
+    pub enabled: bool,
+    pub interval_minutes: u32,
+    pub last_backup: Option<String>,
+    pub next_backup: Option<String>,
+    pub in_progress: bool,
+}
+
+fn discover_servers() -> Vec<ScheduledServer> {
+    let Some(servers_dir) = dirs::document_dir().map(|d| d.join("VoidLink").join("servers")) else { return Vec::new() };
+    fs::read_dir(servers_dir).into_iter().flatten().flatten()
+        .filter(|entry| entry.path().join("server.json").is_file())
+        .map(|entry| ScheduledServer {
+            server_id: entry.file_name().to_string_lossy().to_string(),
+            rcon: RconCredentials::from_server_dir(&entry.path()),
+            server_path: entry.path(),
+        })
+        .collect()
+}
+
+// Creation time of the newest automatic backup
+fn last_auto_backup(server: &ScheduledServer, settings: &BackupSettings) -> Option<DateTime<Local>> {
+    backup::backup_list_cmd(server.server_id.clone(), settings.custom_path.clone())
+        .ok()?
+        .iter()
+        .filter(|b| b.backup_type == "auto")
+        .filter_map(|b| DateTime::parse_from_rfc3339(&b.created_at).ok())
+        .map(|d| d.with_timezone(&Local))
+        .max()
+}
+
+fn next_due(server: &ScheduledServer, settings: &BackupSettings) -> DateTime<Local> {
+    let last_attempt = SCHEDULE_STATE.lock().ok()
+        .and_then(|state| state.get(&server.server_id).and_then(|s| s.last_attempt));
+    let last = last_auto_backup(server, settings).into_iter().chain(last_attempt).max();
+    match last {
+        Some(last) => last + chrono::Duration::minutes(settings.interval_minutes as i64),
+        None => Local::now(),
+    }
+}
+
+fn is_in_progress(server_id: &str) -> bool {
+    SCHEDULE_STATE.lock()
+        .map(|state| state.get(server_id).is_some_and(|s| s.in_progress))
+        .unwrap_or(false)
+}
+
+#[tauri::command]
+pub fn backup_schedule_status() -> Result<Vec<ScheduleStatus>, String> {
+    let mut statuses = Vec::new();
+    for server in discover_servers() {
+        let settings = backup::load_settings(&server.server_id)?;
+        statuses.push(ScheduleStatus {
+            server_id: server.server_id.clone(),
+            enabled: settings.enabled,
+            interval_minutes: settings.interval_minutes,
+            last_backup: last_auto_backup(&server, &settings).map(|d| d.to_rfc3339()),
+            next_backup: settings.enabled.then(|| next_due(&server, &settings).to_rfc3339()),
+            in_progress: is_in_progress(&server.server_id),
+        });
+    }
+    Ok(statuses)
+}
+
+/// Start the background scheduler - called once from `run()`
+pub fn start(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(SCHEDULER_TICK_SECS));
+        loop {
+            interval.tick().await;
+            let app = app.clone();
+            let _ = tauri::async_runtime::spawn_blocking(move || run_due_backups(&app)).await;
+        }
+    });
+}
+
+fn run_due_backups(app: &tauri::AppHandle) {
+    for server in discover_servers() {
+        if is_in_progress(&server.server_id) {
+            continue;
+        }
+        let settings = match backup::load_settings(&server.server_id) {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::warn!("[Backup scheduler] Failed to load settings for {}: {}", server.server_id, e);
+                continue;
+            }
+        };
+
+        // Only back up servers that are enabled, due and actually running
+        if !settings.enabled || Local::now() < next_due(&server, &settings) {
+            continue;
+        }
+        if !server::is_server_dir_running(&server.server_id, &server.server_path) {
+            continue;
+        }
+
+        if let Ok(mut state) = SCHEDULE_STATE.lock() {
+            let entry = state.entry(server.server_id.clone()).or_default();
+            entry.in_progress = true;
+            entry.last_attempt = Some(Local::now());
+        }
+
+        let app = app.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            run_scheduled_backup(&app, &server, &settings);
+            if let Ok(mut state) = SCHEDULE_STATE.lock() {
+                if let Some(entry) = state.get_mut(&server.server_id) {
+                    entry.in_progress = false;
+                }
+            }
+        });
+    }
+}
+
+fn run_scheduled_backup(app: &tauri::AppHandle, server: &ScheduledServer, settings: &BackupSettings) {
+    log::info!("[Backup scheduler] Creating auto backup for {}", server.server_id);
+    let _ = app.emit("backup-progress", serde_json::json!({
+        "server_id": server.server_id,
+        "stage": "creating"
+    }));
+
+    let result = backup::create_backup_job(
+        app,
+        server.server_path.to_string_lossy().to_string(),
+        server.server_id.clone(),
+        "auto".to_string(),
+        settings.included_folders.clone(),
+        settings.custom_path.clone(),
+        Some(settings.format),
+        server.rcon.clone(),
+    );
+
+    let info = match result {
+        Ok(info) => info,
+        Err(e) => {
+            log::error!("[Backup scheduler] Auto backup failed for {}: {}", server.server_id, e);
+            let _ = app.emit("backup-failed", serde_json::json!({
+                "server_id": server.server_id,
+                "error": e
+            }));
+            return;
+        }
+    };
+
+    let _ = app.emit("backup-progress", serde_json::json!({
+        "server_id": server.server_id,
+        "stage": "rotating"
+    }));
+    let rotated = backup::rotate_backups(
+        &server.server_id,
+        settings.custom_path.as_deref(),
+        false,
+    ).map(|plan| plan.delete.len()).unwrap_or_else(|e| {
+        log::warn!("[Backup scheduler] Rotation failed for {}: {}", server.server_id, e);
+        0
+    });
+
+    let _ = app.emit("backup-completed", serde_json::json!({
+        "server_id": server.server_id,
+        "backup": info,
+        "rotated": rotated
+    }));
+}
@@ -0,0 +1,488 @@
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Write};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use crate::backup_archive::ProgressTracker;
+use crate::backup_filter::BackupEntry;
+use crate::backup_verify::{sha256_hex, to_hex};
+use crate::jobs::Job;
+
+// Content-addressed chunk store shared by all snapshots of a server:
+//   <backup_dir>/chunks/<first 2 hex chars>/<sha256>   zlib-compressed chunk data
+//   <backup_dir>/snapshots/<name>.json                  manifest referencing chunks
+
+const MANIFEST_VERSION: u32 = 1;
+// Non-region files are split into fixed-size chunks
+const FIXED_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+// Anvil region files: 4 KiB location table + 4 KiB timestamp table, data in 4 KiB sectors
+const REGION_SECTOR_SIZE: usize = 4096;
+const REGION_HEADER_SIZE: usize = 2 * REGION_SECTOR_SIZE;
+const REGION_CHUNK_COUNT: usize = 1024;
+
+// One lock per chunk store. Garbage collection deletes every chunk no manifest on disk refers
+// to, which includes the chunks of a snapshot that is still being written, so snapshot writes,
+// GC and rotation of a store take turns. The lock also guards the backup folder's metadata.json.
+static STORE_LOCKS: LazyLock<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub version: u32,
+    pub created_at: String,
+    pub directories: Vec<String>,
+    pub files: Vec<SnapshotFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    pub path: String,
+    pub size: u64,
+    pub chunks: Vec<String>,
+    #[serde(default)]
+    pub sha256: Option<String>, // hash of the whole file
+}
+
+pub struct SnapshotSummary {
+    pub manifest_path: PathBuf,
+    pub logical_size_bytes: u64,
+    pub stored_bytes: u64,
+    pub files: u64,
+    pub new_chunks: u64,
+}
+
+/// Lock of a server's chunk store, hold it while writing a snapshot, deleting backups or
+/// updating the backup metadata
+pub fn store_lock(backup_dir: &Path) -> Arc<Mutex<()>> {
+    let key = fs::canonicalize(backup_dir).unwrap_or_else(|_| backup_dir.to_path_buf());
+    let mut locks = STORE_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+    locks.entry(key).or_default().clone()
+}
+
+fn chunks_dir(backup_dir: &Path) -> PathBuf {
+    backup_dir.join("chunks")
+}
+
+pub fn snapshots_dir(backup_dir: &Path) -> PathBuf {
+    backup_dir.join("snapshots")
+}
+
+fn chunk_path(backup_dir: &Path, hash: &str) -> PathBuf {
+    chunks_dir(backup_dir).join(&hash[..2]).join(hash)
+}
+
+pub fn chunk_exists(backup_dir: &Path, hash: &str) -> bool {
+    chunk_path(backup_dir, hash).exists()
+}
+
+fn is_region_file(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    lower.ends_with(".mca") || lower.ends_with(".mcr")
+}
+
+/// Split a region file along its chunk boundaries so unchanged chunks hash identically
+/// even when other chunks in the same file were rewritten. Returns None for malformed files.
+fn split_region(data: &[u8]) -> Option<Vec<Range<usize>>> {
+    if data.len() < REGION_HEADER_SIZE {
+        return None;
+    }
+
+    let mut chunks = Vec::new();
+    for i in 0..REGION_CHUNK_COUNT {
+        let entry = &data[i * 4..i * 4 + 4];
+        let offset = ((entry[0] as usize) << 16) | ((entry[1] as usize) << 8) | entry[2] as usize;
+        let sectors = entry[3] as usize;
+        if offset == 0 && sectors == 0 {
+            continue;
+        }
+        let start = offset * REGION_SECTOR_SIZE;
+        let end = start + sectors * REGION_SECTOR_SIZE;
+        if start < REGION_HEADER_SIZE || end > data.len() || sectors == 0 {
+            return None;
+        }
+        chunks.push(start..end);
+    }
+    chunks.sort_by_key(|r| r.start);
+
+    let mut ranges = Vec::with_capacity(chunks.len() * 2 + 1);
+    ranges.push(0..REGION_HEADER_SIZE);
+    let mut cursor = REGION_HEADER_SIZE;
+    for chunk in chunks {
+        if chunk.start < cursor {
+            // Overlapping chunks mean the header cannot be trusted
+            return None;
+        }
+        if chunk.start > cursor {
+            ranges.push(cursor..chunk.start);
+        }
+        cursor = chunk.end;
+        ranges.push(chunk);
+    }
+    if cursor < data.len() {
+        ranges.push(cursor..data.len());
+    }
+    Some(ranges)
+}
+
+fn fixed_ranges(len: usize) -> Vec<Range<usize>> {
+    (0..len).step_by(FIXED_CHUNK_SIZE)
+        .map(|start| start..(start + FIXED_CHUNK_SIZE).min(len))
+        .collect()
+}
+
+// Store a chunk if it is not already present, returning the bytes written to disk
+fn store_chunk(backup_dir: &Path, hash: &str, data: &[u8]) -> Result<u64, String> {
+    let path = chunk_path(backup_dir, hash);
+    if path.exists() {
+        return Ok(0);
+    }
+    let parent = path.parent().ok_or("Invalid chunk path")?;
+    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).map_err(|e| e.to_string())?;
+    let compressed = encoder.finish().map_err(|e| e.to_string())?;
+
+    // Write to a temp file first so an interrupted backup never leaves a truncated chunk behind
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, &compressed).map_err(|e| e.to_string())?;
+    fs::rename(&tmp_path, &path).map_err(|e| e.to_string())?;
+    Ok(compressed.len() as u64)
+}
+
+pub fn read_chunk(backup_dir: &Path, hash: &str) -> Result<Vec<u8>, String> {
+    let file = File::open(chunk_path(backup_dir, hash))
+        .map_err(|e| format!("Missing chunk {}: {}", hash, e))?;
+    let mut data = Vec::new();
+    ZlibDecoder::new(file).read_to_end(&mut data)
+        .map_err(|e| format!("Corrupt chunk {}: {}", hash, e))?;
+    Ok(data)
+}
+
+/// Create a snapshot of the given entries, storing only chunks not already in the store.
+/// Chunks stored by a snapshot that fails are removed again. Callers hold `store_lock`.
+pub fn create_snapshot(
+    backup_dir: &Path,
+    snapshot_name: &str,
+    created_at: &str,
+    entries: &[BackupEntry],
+    progress: &ProgressTracker,
+) -> Result<SnapshotSummary, String> {
+    let mut created = Vec::new();
+    let result = write_snapshot(backup_dir, snapshot_name, created_at, entries, progress, &mut created);
+    if result.is_err() {
+        for hash in &created {
+            let _ = fs::remove_file(chunk_path(backup_dir, hash));
+        }
+    }
+    result
+}
+
+fn write_snapshot(
+    backup_dir: &Path,
+    snapshot_name: &str,
+    created_at: &str,
+    entries: &[BackupEntry],
+    progress: &ProgressTracker,
+    created: &mut Vec<String>,
+) -> Result<SnapshotSummary, String> {
+    let mut manifest = SnapshotManifest {
+        version: MANIFEST_VERSION,
+        created_at: created_at.to_string(),
+        directories: Vec::new(),
+        files: Vec::new(),
+    };
+    let mut stored_bytes = 0u64;
+    let mut logical_size_bytes = 0u64;
+
+    for entry in entries {
+        if entry.is_dir {
+            manifest.directories.push(entry.relative_path.clone());
+            continue;
+        }
+
+        progress.check_cancelled()?;
+        progress.start_file(&entry.relative_path);
+        let read_error = |e: std::io::Error| format!("Failed to read {}: {}", entry.relative_path, e);
+        let mut chunks = Vec::new();
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+
+        if is_region_file(&entry.relative_path) {
+            // Region files are split along their chunk table, which needs the whole file
+            let data = fs::read(&entry.path).map_err(read_error)?;
+            let ranges = split_region(&data).unwrap_or_else(|| fixed_ranges(data.len()));
+            for range in ranges {
+                chunks.push(add_chunk(backup_dir, &data[range], &mut stored_bytes, created)?);
+            }
+            hasher.update(&data);
+            size = data.len() as u64;
+            progress.add_bytes(size);
+        } else {
+            // Everything else is streamed one fixed-size chunk at a time
+            let mut reader = BufReader::new(File::open(&entry.path).map_err(read_error)?);
+            let mut piece = Vec::with_capacity(FIXED_CHUNK_SIZE);
+            loop {
+                progress.check_cancelled()?;
+                piece.clear();
+                reader.by_ref().take(FIXED_CHUNK_SIZE as u64).read_to_end(&mut piece).map_err(read_error)?;
+                if piece.is_empty() {
+                    break;
+                }
+                chunks.push(add_chunk(backup_dir, &piece, &mut stored_bytes, created)?);
+                hasher.update(&piece);
+                size += piece.len() as u64;
+                progress.add_bytes(piece.len() as u64);
+            }
+        }
+
+        logical_size_bytes += size;
+        manifest.files.push(SnapshotFile {
+            path: entry.relative_path.clone(),
+            size,
+            chunks,
+            sha256: Some(to_hex(&hasher.finalize())),
+        });
+    }
+
+    let dir = snapshots_dir(backup_dir);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let manifest_path = dir.join(format!("{}.json", snapshot_name));
+    let content = serde_json::to_string(&manifest).map_err(|e| e.to_string())?;
+    fs::write(&manifest_path, content).map_err(|e| e.to_string())?;
+
+    Ok(SnapshotSummary {
+        manifest_path,
+        logical_size_bytes,
+        stored_bytes,
+        files: manifest.files.len() as u64,
+        new_chunks: created.len() as u64,
+    })
+}
+
+// Store one chunk, returning its hash. Chunks new to the store are added to `created`.
+fn add_chunk(backup_dir: &Path, piece: &[u8], stored_bytes: &mut u64, created: &mut Vec<String>) -> Result<String, String> {
+    let hash = sha256_hex(piece);
+    let written = store_chunk(backup_dir, &hash, piece)?;
+    if written > 0 {
+        *stored_bytes += written;
+        created.push(hash.clone());
+    }
+    Ok(hash)
+}
+
+pub fn load_manifest(manifest_path: &Path) -> Result<SnapshotManifest, String> {
+    let content = fs::read_to_string(manifest_path)
+        .map_err(|e| format!("Failed to read snapshot manifest: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid snapshot manifest: {}", e))
+}
+
+/// Rebuild a single file from its chunks
+pub fn restore_file(backup_dir: &Path, file: &SnapshotFile, out_path: &Path) -> Result<(), String> {
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut out = File::create(out_path).map_err(|e| e.to_string())?;
+    for hash in &file.chunks {
+        let data = read_chunk(backup_dir, hash)?;
+        out.write_all(&data).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// Manifest paths are relative and must stay below the restore destination
+fn restore_path(dest: &Path, relative: &str) -> Result<PathBuf, String> {
+    let path = Path::new(relative);
+    if relative.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!("Refusing to restore unsafe path from snapshot: {}", relative));
+    }
+    Ok(dest.join(path))
+}
+
+/// Restore the directories and files of a snapshot accepted by `filter` below `dest`
+pub fn restore_snapshot(
+    backup_dir: &Path,
+    manifest_path: &Path,
+    dest: &Path,
+    filter: &dyn Fn(&str) -> bool,
+    job: &Job,
+) -> Result<u64, String> {
+    let manifest = load_manifest(manifest_path)?;
+
+    for dir in manifest.directories.iter().filter(|d| filter(d)) {
+        fs::create_dir_all(restore_path(dest, dir)?).map_err(|e| e.to_string())?;
+    }
+    let mut restored = 0u64;
+    for file in manifest.files.iter().filter(|f| filter(&f.path)) {
+        job.check_cancelled()?;
+        restore_file(backup_dir, file, &restore_path(dest, &file.path)?)?;
+        restored += 1;
+    }
+
+    Ok(restored)
+}
+
+/// Delete chunks that are no longer referenced by any snapshot, returning the bytes freed.
+/// Callers hold `store_lock`.
+pub fn garbage_collect(backup_dir: &Path) -> Result<u64, String> {
+    let mut referenced = HashSet::new();
+    if let Ok(entries) = fs::read_dir(snapshots_dir(backup_dir)) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|e| e == "json") {
+                // Never delete chunks if a manifest cannot be read
+                let manifest = load_manifest(&path)?;
+                for file in manifest.files {
+                    referenced.extend(file.chunks);
+                }
+            }
+        }
+    }
+
+    let mut freed = 0u64;
+    for entry in walkdir::WalkDir::new(chunks_dir(backup_dir)).into_iter().flatten() {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !referenced.contains(&name) {
+            freed += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+
+    if freed > 0 {
+        log::info!("Snapshot GC freed {} bytes", freed);
+    }
+    Ok(freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Region file with the given (slot, first sector, sector count) chunks, sectors filled with their index
+    fn region(chunks: &[(usize, usize, usize)], total_sectors: usize) -> Vec<u8> {
+        let mut data = vec![0u8; total_sectors * REGION_SECTOR_SIZE];
+        for &(slot, offset, sectors) in chunks {
+            data[slot * 4] = (offset >> 16) as u8;
+            data[slot * 4 + 1] = (offset >> 8) as u8;
+            data[slot * 4 + 2] = offset as u8;
+            data[slot * 4 + 3] = sectors as u8;
+        }
+        for sector in 2..total_sectors {
+            data[sector * REGION_SECTOR_SIZE..(sector + 1) * REGION_SECTOR_SIZE].fill(sector as u8);
+        }
+        data
+    }
+
+    fn sectors(range: &Range<usize>) -> (usize, usize) {
+        (range.start / REGION_SECTOR_SIZE, range.end / REGION_SECTOR_SIZE)
+    }
+
+    #[test]
+    fn fixed_ranges_cover_the_file() {
+        assert!(fixed_ranges(0).is_empty());
+        assert_eq!(fixed_ranges(10), vec![0..10]);
+        assert_eq!(fixed_ranges(FIXED_CHUNK_SIZE), vec![0..FIXED_CHUNK_SIZE]);
+        assert_eq!(
+            fixed_ranges(FIXED_CHUNK_SIZE + 1),
+            vec![0..FIXED_CHUNK_SIZE, FIXED_CHUNK_SIZE..FIXED_CHUNK_SIZE + 1]
+        );
+    }
+
+    #[test]
+    fn region_is_split_along_chunk_boundaries() {
+        // Slot order differs from file order, and sectors 4 and 7 are free
+        let data = region(&[(5, 5, 2), (0, 2, 2), (9, 8, 1)], 9);
+        let ranges = split_region(&data).unwrap();
+        let sectors: Vec<_> = ranges.iter().map(sectors).collect();
+        assert_eq!(sectors, vec![(0, 2), (2, 4), (4, 5), (5, 7), (7, 8), (8, 9)]);
+    }
+
+    #[test]
+    fn trailing_bytes_after_the_last_chunk_are_kept() {
+        let mut data = region(&[(0, 2, 1)], 3);
+        data.extend_from_slice(&[1, 2, 3]);
+        let ranges = split_region(&data).unwrap();
+        assert_eq!(ranges.last().unwrap().clone(), 3 * REGION_SECTOR_SIZE..data.len());
+    }
+
+    #[test]
+    fn unchanged_chunks_keep_their_hash() {
+        let before = region(&[(0, 2, 1), (1, 3, 1)], 4);
+        let mut after = before.clone();
+        after[3 * REGION_SECTOR_SIZE] = 0xff;
+
+        let hashes = |data: &[u8]| -> Vec<String> {
+            split_region(data).unwrap().into_iter().map(|r| sha256_hex(&data[r])).collect()
+        };
+        let (before, after) = (hashes(&before), hashes(&after));
+        assert_eq!(before[..2], after[..2]);
+        assert_ne!(before[2], after[2]);
+    }
+
+    #[test]
+    fn malformed_regions_are_rejected() {
+        // Shorter than the header
+        assert!(split_region(&[0u8; 100]).is_none());
+        // Chunk pointing into the header
+        assert!(split_region(&region(&[(0, 1, 1)], 4)).is_none());
+        // Chunk past the end of the file
+        assert!(split_region(&region(&[(0, 3, 2)], 4)).is_none());
+        // Overlapping chunks
+        assert!(split_region(&region(&[(0, 2, 2), (1, 3, 1)], 5)).is_none());
+        // Offset without sectors
+        assert!(split_region(&region(&[(0, 2, 0)], 4)).is_none());
+    }
+
+    #[test]
+    fn large_files_are_streamed_in_fixed_chunks() {
+        let root = std::env::temp_dir().join(format!("voidlink-snapshot-stream-{}", std::process::id()));
+        let source = root.join("server.jar");
+        fs::create_dir_all(&root).unwrap();
+        let data: Vec<u8> = (0..2 * FIXED_CHUNK_SIZE + 100).map(|i| (i * 31 % 251) as u8).collect();
+        fs::write(&source, &data).unwrap();
+
+        let entries = [BackupEntry { path: source, relative_path: "server.jar".to_string(), is_dir: false, size: data.len() as u64 }];
+        let progress = ProgressTracker::new(data.len() as u64, Arc::new(Default::default()), |_| {});
+        let store = root.join("store");
+        let summary = create_snapshot(&store, "snap", "2024-01-01T00:00:00Z", &entries, &progress).unwrap();
+        assert_eq!(summary.logical_size_bytes, data.len() as u64);
+        assert_eq!(summary.new_chunks, 3);
+
+        let manifest = load_manifest(&summary.manifest_path).unwrap();
+        let file = &manifest.files[0];
+        assert_eq!(file.sha256.as_deref(), Some(sha256_hex(&data).as_str()));
+        let restored = root.join("restored.jar");
+        restore_file(&store, file, &restored).unwrap();
+        assert_eq!(fs::read(&restored).unwrap(), data);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn region_files_are_recognized() {
+        assert!(is_region_file("world/region/r.0.-1.mca"));
+        assert!(is_region_file("world/region/R.0.0.MCR"));
+        assert!(!is_region_file("world/level.dat"));
+    }
+
+    #[test]
+    fn restore_paths_stay_below_the_destination() {
+        let dest = Path::new("staging");
+        assert_eq!(
+            restore_path(dest, "world/region/r.0.0.mca").unwrap(),
+            dest.join("world").join("region").join("r.0.0.mca")
+        );
+        for unsafe_path in ["", "../outside", "world/../../outside", "/etc/passwd", "./world"] {
+            assert!(restore_path(dest, unsafe_path).is_err(), "{} was accepted", unsafe_path);
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+use crate::backup::{self, BackupFormat, BackupInfo};
+use crate::backup_crypto::{self, BackupKey, PlainArchive};
+use crate::backup_snapshot;
+use crate::jobs::{self, Job, JobProgress};
+
+// Per-entry checksums recorded next to a zip archive at creation time
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EntryManifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupVerification {
+    pub backup_id: String,
+    pub is_valid: bool,
+    pub checksum_matches: Option<bool>, // None when no checksum was recorded
+    pub has_manifest: bool,
+    pub verifiable: bool, // false when nothing was recorded to check the backup against
+    pub checked_entries: u64,
+    pub missing: Vec<String>,
+    pub corrupt: Vec<String>,
+    pub error: Option<String>,
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Stream a reader through SHA-256, returning the digest and number of bytes read
+fn sha256_reader<R: Read>(reader: &mut R) -> std::io::Result<(String, u64)> {
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    let mut total = 0u64;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        total += read as u64;
+    }
+    Ok((to_hex(&hasher.finalize()), total))
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    sha256_reader(&mut file).map(|(hash, _)| hash).map_err(|e| e.to_string())
+}
+
+/// Sidecar file holding the entry manifest of an archive
+pub fn entry_manifest_path(archive_path: &Path) -> PathBuf {
+    let mut name = archive_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".manifest.json");
+    archive_path.with_file_name(name)
+}
+
+/// Save the entry manifest, encrypted with the archive's key since it lists every file name
+pub fn save_entry_manifest(archive_path: &Path, manifest: &EntryManifest, key: Option<&BackupKey>) -> Result<(), String> {
+    let content = serde_json::to_vec(manifest).map_err(|e| e.to_string())?;
+    let content = match key {
+        Some(key) => backup_crypto::encrypt_bytes(key, &content)?,
+        None => content,
+    };
+    fs::write(entry_manifest_path(archive_path), content).map_err(|e| e.to_string())
+}
+
+fn load_entry_manifest(archive_path: &Path, key: Option<&BackupKey>) -> Result<Option<EntryManifest>, String> {
+    let path = entry_manifest_path(archive_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read(&path).map_err(|e| e.to_string())?;
+    let content = if backup_crypto::is_encrypted(&content) {
+        let key = key.ok_or("Entry manifest is encrypted but no key was provided")?;
+        backup_crypto::decrypt_bytes(key, &content).map_err(|e| format!("Failed to decrypt entry manifest: {}", e))?
+    } else {
+        content
+    };
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| format!("Invalid entry manifest: {}", e))
+}
+
+/// Re-read a backup and check it against the checksums recorded at creation. `plain` is the
+/// archive opened with `backup_crypto::open_archive`, which decrypts (and so authenticates)
+/// encrypted backups; `key` is needed for their entry manifest.
+pub fn verify_backup(backup_dir: &Path, backup: &BackupInfo, key: Option<&BackupKey>, plain: &PlainArchive) -> BackupVerification {
+    let mut result = BackupVerification {
+        backup_id: backup.id.clone(),
+        is_valid: false,
+        checksum_matches: None,
+        has_manifest: false,
+        verifiable: true,
+        checked_entries: 0,
+        missing: Vec::new(),
+        corrupt: Vec::new(),
+        error: None,
+    };
+
+    let backup_path = PathBuf::from(&backup.path);
+    if !backup_path.exists() {
+        result.error = Some("Backup file does not exist".to_string());
+        return result;
+    }
+
+    if let Some(expected) = &backup.sha256 {
+        match sha256_file(&backup_path) {
+            Ok(actual) => result.checksum_matches = Some(&actual == expected),
+            Err(e) => {
+                result.error = Some(format!("Failed to read backup: {}", e));
+                return result;
+            }
+        }
+    }
+
+    let checked = match backup.format {
+        BackupFormat::Zip => verify_zip(backup, key, plain, &mut result),
+        BackupFormat::Snapshot => verify_snapshot(backup_dir, &backup_path, &mut result),
+    };
+    if let Err(e) = checked {
+        result.error = Some(e);
+    } else if !result.verifiable {
+        result.error = Some("No checksums were recorded for this backup, it cannot be verified".to_string());
+    }
+
+    result.is_valid = result.error.is_none()
+        && result.checksum_matches != Some(false)
+        && result.missing.is_empty()
+        && result.corrupt.is_empty();
+    result
+}
+
+fn verify_zip(backup: &BackupInfo, key: Option<&BackupKey>, plain: &PlainArchive, result: &mut BackupVerification) -> Result<(), String> {
+    let manifest = load_entry_manifest(Path::new(&backup.path), key)?;
+    result.has_manifest = manifest.is_some();
+
+    let file = File::open(plain.path()).map_err(|e| e.to_string())?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("Unreadable archive: {}", e))?;
+
+    // Reading every entry to the end also validates the CRC32 stored in the zip
+    let mut actual: HashMap<String, Option<(String, u64)>> = HashMap::new();
+    for i in 0..archive.len() {
+        let mut entry = match archive.by_index(i) {
+            Ok(entry) => entry,
+            Err(e) => {
+                result.corrupt.push(format!("entry #{}: {}", i, e));
+                continue;
+            }
+        };
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        actual.insert(name, sha256_reader(&mut entry).ok());
+        result.checked_entries += 1;
+    }
+
+    match manifest {
+        Some(manifest) => {
+            for expected in &manifest.entries {
+                match actual.get(&expected.path) {
+                    None => result.missing.push(expected.path.clone()),
+                    Some(None) => result.corrupt.push(expected.path.clone()),
+                    Some(Some((hash, size))) => {
+                        if hash != &expected.sha256 || *size != expected.size {
+                            result.corrupt.push(expected.path.clone());
+                        }
+                    }
+                }
+            }
+        }
+        None => {
+            // No manifest (older backup): the zip's own CRCs only catch damaged entries, not
+            // missing or replaced ones, so the archive checksum has to vouch for the rest
+            for (name, hash) in &actual {
+                if hash.is_none() {
+                    result.corrupt.push(name.clone());
+                }
+            }
+            result.verifiable = backup.sha256.is_some();
+        }
+    }
+
+    Ok(())
+}
+
+fn verify_snapshot(backup_dir: &Path, manifest_path: &Path, result: &mut BackupVerification) -> Result<(), String> {
+    let manifest = backup_snapshot::load_manifest(manifest_path)?;
+    result.has_manifest = true;
+
+    let mut good_chunks: HashSet<String> = HashSet::new();
+    for file in &manifest.files {
+        result.checked_entries += 1;
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        let mut missing = false;
+        let mut corrupt = false;
+
+        for hash in &file.chunks {
+            match backup_snapshot::read_chunk(backup_dir, hash) {
+                Ok(data) => {
+                    // Chunks are content-addressed, so the name is the expected hash
+                    if !good_chunks.contains(hash) {
+                        if &sha256_hex(&data) != hash {
+                            corrupt = true;
+                            break;
+                        }
+                        good_chunks.insert(hash.clone());
+                    }
+                    hasher.update(&data);
+                    size += data.len() as u64;
+                }
+                Err(_) => {
+                    // Unreadable chunks that exist on disk are corrupt, absent ones are missing
+                    if backup_snapshot::chunk_exists(backup_dir, hash) {
+                        corrupt = true;
+                    } else {
+                        missing = true;
+                    }
+                    break;
+                }
+            }
+        }
+
+        if missing {
+            result.missing.push(file.path.clone());
+        } else if corrupt
+            || size != file.size
+            || file.sha256.as_ref().is_some_and(|h| h != &to_hex(&hasher.finalize()))
+        {
+            result.corrupt.push(file.path.clone());
+        }
+    }
+
+    Ok(())
+}
+
+/// Verify a backup's archive and entries against the recorded checksums
+#[tauri::command]
+pub async fn backup_verify_cmd(
+    app: tauri::AppHandle,
+    server_id: String,
+    backup_id: String,
+    custom_path: Option<String>,
+) -> Result<BackupVerification, String> {
+    let handle = tauri::async_runtime::spawn_blocking(move || {
+        jobs::run(&app, "verify", &server_id, |job| verify(job, &server_id, &backup_id, custom_path))
+    });
+    handle.await.map_err(|e| e.to_string())?
+}
+
+fn verify(job: &Job, server_id: &str, backup_id: &str, custom_path: Option<String>) -> Result<BackupVerification, String> {
+    job.progress(JobProgress { done: 0, total: 1, message: "Opening backup".to_string(), eta_secs: None });
+    let opened = backup::open_backup(server_id, backup_id, custom_path.as_deref())?;
+    job.check_cancelled()?;
+    let backup = &opened.backup;
+    job.progress(JobProgress { done: 0, total: 1, message: format!("Verifying {}", backup.name), eta_secs: None });
+    let verification = verify_backup(&opened.backup_dir, backup, opened.key.as_ref(), &opened.plain);
+    log::info!(
+        "Verified backup {}: valid={}, missing={}, corrupt={}",
+        backup.name, verification.is_valid, verification.missing.len(), verification.corrupt.len()
+    );
+    Ok(verification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    // Zip backup as written before entry manifests existed
+    fn legacy_backup(dir: &Path, sha256: bool) -> BackupInfo {
+        let path = dir.join("backup_legacy.zip");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file("world/level.dat", FileOptions::default()).unwrap();
+        zip.write_all(b"level data").unwrap();
+        zip.finish().unwrap();
+
+        let checksum = sha256.then(|| sha256_file(&path).unwrap());
+        serde_json::from_value(serde_json::json!({
+            "id": "legacy",
+            "name": "backup_legacy.zip",
+            "created_at": "2024-01-01T12:00:00+00:00",
+            "size_bytes": 0,
+            "path": path.to_string_lossy(),
+            "backup_type": "manual",
+            "included_folders": ["world"],
+            "sha256": checksum,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn backups_without_manifest_are_checked_against_the_archive_checksum() {
+        let dir = std::env::temp_dir().join(format!("voidlink-verify-legacy-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let backup = legacy_backup(&dir, true);
+        let verification = verify_backup(&dir, &backup, None, &backup_crypto::open_archive(&backup, None).unwrap());
+        assert!(verification.is_valid);
+        assert!(verification.verifiable);
+        assert!(!verification.has_manifest);
+        assert_eq!(verification.checksum_matches, Some(true));
+
+        // A replaced archive still has valid CRCs, but no longer matches the checksum
+        let mut tampered = backup.clone();
+        tampered.sha256 = Some(sha256_hex(b"something else"));
+        let verification = verify_backup(&dir, &tampered, None, &backup_crypto::open_archive(&tampered, None).unwrap());
+        assert!(!verification.is_valid);
+        assert_eq!(verification.checksum_matches, Some(false));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn backups_without_any_checksum_are_unverifiable() {
+        let dir = std::env::temp_dir().join(format!("voidlink-verify-unverifiable-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let backup = legacy_backup(&dir, false);
+        let verification = verify_backup(&dir, &backup, None, &backup_crypto::open_archive(&backup, None).unwrap());
+        assert!(!verification.is_valid);
+        assert!(!verification.verifiable);
+        assert_eq!(verification.checked_entries, 1);
+        assert!(verification.error.is_some());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
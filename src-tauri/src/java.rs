@@ -1,12 +1,18 @@
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use reqwest::blocking::Client;
 use std::fs;
 use anyhow::Result;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 use flate2::read::GzDecoder;
 use tar::Archive;
+use std::io::{Read, Write};
+use crate::backup_verify;
+use crate::java_distribution::{self, JavaDistribution, JavaRelease};
+use crate::java_runtime;
+use crate::jobs::{self, Job, JobProgress};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct JavaInstallation {
@@ -28,16 +34,6 @@ pub struct JavaValidation {
     pub error: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AdoptiumRelease {
-    pub version: String,
-    pub major: u32,
-    pub download_url: String,
-    pub filename: String,
-    pub size: u64,
-    pub checksum: Option<String>,
-}
-
 struct JavaVersionInfo {
     version: String,
     major: u32,
@@ -57,15 +53,26 @@ pub fn validate_java_path_cmd(path: String) -> JavaValidation {
     validate_java_path(&path)
 }
 
+/// Latest release of a major version, from the given distribution or the first one providing it
 #[tauri::command]
-pub fn fetch_adoptium_release_cmd(major: u32) -> Result<AdoptiumRelease, String> {
-    fetch_adoptium_release(major)
+pub async fn fetch_java_release_cmd(major: u32, distribution: Option<JavaDistribution>) -> Result<JavaRelease, String> {
+    let handle = tauri::async_runtime::spawn_blocking(move || java_distribution::fetch_release(distribution, major));
+    
+    handle.await.map_err(|e| e.to_string())?
 }
 
+/// Download and install a JDK as a cancellable job
 #[tauri::command]
-pub async fn download_java_cmd(major: u32, install_dir: String) -> Result<String, String> {
+pub async fn download_java_cmd(
+    app: tauri::AppHandle,
+    major: u32,
+    install_dir: String,
+    distribution: Option<JavaDistribution>,
+) -> Result<String, String> {
     let handle = tauri::async_runtime::spawn_blocking(move || {
-        download_and_install_java(major, install_dir)
+        jobs::run(&app, "java-download", &format!("Java {}", major), |job| {
+            download_and_install_java(&app, major, distribution, install_dir, job)
+        })
     });
     
     handle.await.map_err(|e| e.to_string())?
@@ -80,11 +87,11 @@ pub fn detect_installations(app: &tauri::AppHandle) -> Vec<JavaInstallation> {
     let candidates = collect_java_candidates(app);
 
     for path in candidates {
-        let path_str = path.to_string_lossy().to_string();
-        if seen_paths.contains(&path_str) {
+        // Version managers and /usr/bin/java link to the same JDKs, only the first path found is listed
+        let resolved = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if !seen_paths.insert(resolved) {
             continue;
         }
-        seen_paths.insert(path_str);
 
         if let Some(installation) = validate_and_create_installation(&path) {
             installations.push(installation);
@@ -157,11 +164,20 @@ fn get_java_version_info(java_path: &Path) -> Result<JavaVersionInfo, String> {
          return Err("Not a file".to_string());
     }
 
+    // Reading the JDK's release file is much faster than starting a JVM, which is only done without one
+    if let Some(info) = read_release_file(java_path) {
+        return Ok(info);
+    }
+
     #[cfg(target_os = "windows")]
     const CREATE_NO_WINDOW: u32 = 0x08000000;
 
     let mut command = Command::new(java_path);
     command.arg("-version");
+    // These print a "Picked up ..." banner, and bad options would make a working JDK look broken
+    for var in ["JAVA_TOOL_OPTIONS", "_JAVA_OPTIONS", "JDK_JAVA_OPTIONS"] {
+        command.env_remove(var);
+    }
     
     #[cfg(target_os = "windows")]
     {
@@ -179,9 +195,47 @@ fn get_java_version_info(java_path: &Path) -> Result<JavaVersionInfo, String> {
     parse_java_version_output(&combined)
 }
 
+// JAVA_HOME/release, e.g. JAVA_VERSION="17.0.10", IMPLEMENTOR="Eclipse Adoptium", OS_ARCH="x86_64"
+fn read_release_file(java_path: &Path) -> Option<JavaVersionInfo> {
+    let java_path = fs::canonicalize(java_path).ok()?;
+    let mut home = java_path.parent()?.parent()?;
+    // Java 8 JDKs have a JRE inside (jdk/jre/bin/java), the release file is in the JDK
+    if !home.join("release").is_file() && home.file_name().is_some_and(|name| name == "jre") {
+        home = home.parent()?;
+    }
+    let content = fs::read_to_string(home.join("release")).ok()?;
+
+    let fields: std::collections::HashMap<&str, &str> = content.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim().trim_matches('"')))
+        .collect();
+
+    let version = fields.get("JAVA_VERSION")?.to_string();
+    let major = parse_major_version(&version);
+    if major == 0 {
+        return None;
+    }
+
+    // IMPLEMENTOR alone is too vague for some builds ("Azul Systems, Inc."), IMPLEMENTOR_VERSION names the distribution
+    let implementor = fields.get("IMPLEMENTOR").copied().unwrap_or_default();
+    let implementor_version = fields.get("IMPLEMENTOR_VERSION").copied().unwrap_or_default();
+    let vendor = detect_vendor(&format!("{} {}", implementor, implementor_version))
+        .or_else(|| Some(implementor.to_string()).filter(|s| !s.is_empty()));
+    let arch = fields.get("OS_ARCH").and_then(|arch| detect_architecture(arch));
+
+    Some(JavaVersionInfo {
+        version,
+        major,
+        vendor,
+        arch,
+    })
+}
+
 fn parse_java_version_output(output: &str) -> Result<JavaVersionInfo, String> {
-    let lines: Vec<&str> = output.lines().collect();
-    let version_line = lines.first().unwrap_or(&"");
+    // Skip anything printed before the version line, such as "Picked up _JAVA_OPTIONS: ..."
+    let version_line = output.lines()
+        .find(|line| line.contains(" version \""))
+        .unwrap_or_default();
 
     let version = extract_version_string(version_line)
         .ok_or_else(|| "Could not parse Java version".to_string())?;
@@ -278,19 +332,10 @@ fn collect_java_candidates(app: &tauri::AppHandle) -> Vec<PathBuf> {
 }
 
 fn collect_voidlink_candidates(app: &tauri::AppHandle, candidates: &mut Vec<PathBuf>) {
-    if let Ok(app_data) = app.path().app_data_dir() {
-        let java_root = app_data.join("java");
-        if java_root.exists() {
-             if let Ok(entries) = std::fs::read_dir(&java_root) {
-                for entry in entries.flatten() {
-                    if entry.path().is_dir() {
-                        // Scan inside version dirs (e.g. java/17/jdk-17.../bin/java)
-                        if let Some(bin) = find_java_executable(&entry.path()) {
-                            candidates.push(bin);
-                        }
-                    }
-                }
-             }
+    // Every runtime inside the version dirs (e.g. java/17/jdk-17.../bin/java), old ones kept after updates included
+    for (_, runtime_dir) in java_runtime::runtime_dirs(app) {
+        if let Some(bin) = find_java_executable(&runtime_dir) {
+            candidates.push(bin);
         }
     }
 }
@@ -335,38 +380,56 @@ fn collect_windows_candidates(candidates: &mut Vec<PathBuf>) {
 
 #[cfg(not(target_os = "windows"))]
 fn collect_unix_candidates(candidates: &mut Vec<PathBuf>) {
-    candidates.push(PathBuf::from("/usr/bin/java"));
-    let jvm_dirs = vec!["/usr/lib/jvm", "/usr/lib64/jvm", "/usr/java"];
-    for jvm_dir in jvm_dirs {
-        let dir = Path::new(jvm_dir);
-        if dir.exists() {
-            if let Ok(entries) = std::fs::read_dir(dir) {
-                for entry in entries.flatten() {
-                    let java_path = entry.path().join("bin").join("java");
-                     candidates.push(java_path);
-                }
-            }
+    for jvm_dir in ["/usr/lib/jvm", "/usr/lib64/jvm", "/usr/java", "/Library/Java/JavaVirtualMachines"] {
+        collect_jdk_dirs(Path::new(jvm_dir), candidates);
+    }
+
+    if let Ok(home) = std::env::var("HOME") {
+        let home = Path::new(&home);
+        let env_dir = |var: &str, default: PathBuf| std::env::var(var).map(PathBuf::from).unwrap_or(default);
+        let data_home = env_dir("XDG_DATA_HOME", home.join(".local").join("share"));
+
+        let tool_dirs = [
+            // SDKMAN!
+            home.join(".sdkman").join("candidates").join("java"),
+            // IntelliJ IDEA downloads
+            home.join(".jdks"),
+            home.join("Library").join("Java").join("JavaVirtualMachines"),
+            // asdf, mise and jabba
+            env_dir("ASDF_DATA_DIR", home.join(".asdf")).join("installs").join("java"),
+            env_dir("MISE_DATA_DIR", data_home.join("mise")).join("installs").join("java"),
+            env_dir("JABBA_HOME", home.join(".jabba")).join("jdk"),
+            // Gradle toolchains
+            env_dir("GRADLE_USER_HOME", home.join(".gradle")).join("jdks"),
+        ];
+        for dir in &tool_dirs {
+            collect_jdk_dirs(dir, candidates);
         }
     }
-    let jvm_dir = Path::new("/Library/Java/JavaVirtualMachines");
-    if jvm_dir.exists() {
-        if let Ok(entries) = std::fs::read_dir(jvm_dir) {
-            for entry in entries.flatten() {
-                let java_path = entry.path().join("Contents").join("Home").join("bin").join("java");
-                candidates.push(java_path);
+
+    // Last, so the JDK directories above are listed rather than the links pointing into them
+    if let Some(path) = std::env::var_os("PATH") {
+        for dir in std::env::split_paths(&path) {
+            // Version manager shims are scripts forwarding to JDKs found above
+            if dir.file_name().is_some_and(|name| name == "shims") {
+                continue;
             }
+            candidates.push(dir.join("java"));
         }
     }
-    if let Ok(home) = std::env::var("HOME") {
-        let sdkman_dir = Path::new(&home).join(".sdkman").join("candidates").join("java");
-        if sdkman_dir.exists() {
-            if let Ok(entries) = std::fs::read_dir(&sdkman_dir) {
-                 for entry in entries.flatten() {
-                     if entry.path().is_dir() {
-                         let java_path = entry.path().join("bin").join("java");
-                         candidates.push(java_path);
-                     }
-                 }
+    candidates.push(PathBuf::from("/usr/bin/java"));
+}
+
+// Every JDK directly inside `base`, e.g. ~/.jdks/temurin-21.0.2 or /Library/Java/JavaVirtualMachines/zulu-17.jdk
+#[cfg(not(target_os = "windows"))]
+fn collect_jdk_dirs(base: &Path, candidates: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(base) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.path().is_dir() {
+            if let Some(java_path) = find_java_executable(&entry.path()) {
+                candidates.push(java_path);
             }
         }
     }
@@ -374,82 +437,205 @@ fn collect_unix_candidates(candidates: &mut Vec<PathBuf>) {
 
 // === Download Logic ===
 
-fn get_adoptium_os() -> &'static str {
-    #[cfg(target_os = "windows")] { "windows" }
-    #[cfg(target_os = "macos")] { "mac" }
-    #[cfg(target_os = "linux")] { "linux" }
-}
+// Attempts at downloading a JDK before giving up; the partial file is resumed between attempts
+const DOWNLOAD_ATTEMPTS: u32 = 4;
+const RETRY_DELAY_SECS: u64 = 2;
+const DOWNLOAD_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
 
-fn get_adoptium_arch() -> &'static str {
-    #[cfg(target_arch = "x86_64")] { "x64" }
-    #[cfg(target_arch = "aarch64")] { "aarch64" }
-    #[cfg(target_arch = "x86")] { "x32" }
+// Why a download attempt failed, deciding whether another attempt is worth it
+enum DownloadError {
+    Retry(String),
+    Fatal(String),
 }
 
-pub fn fetch_adoptium_release(major: u32) -> Result<AdoptiumRelease, String> {
-    let os = get_adoptium_os();
-    let arch = get_adoptium_arch();
-    
-    let url = format!(
-        "https://api.adoptium.net/v3/assets/latest/{}/hotspot?architecture={}&image_type=jdk&os={}&vendor=eclipse",
-        major, arch, os
-    );
-
-    let client = Client::builder().user_agent("VoidLink").build().map_err(|e| e.to_string())?;
-    let resp = client.get(&url).send().map_err(|e| format!("Request failed: {}", e))?;
-        
-    if !resp.status().is_success() {
-        return Err(format!("API Error: {}", resp.status()));
+impl DownloadError {
+    fn message(self) -> String {
+        match self {
+            DownloadError::Retry(e) | DownloadError::Fatal(e) => e,
+        }
     }
+}
 
-    let releases: serde_json::Value = resp.json().map_err(|e| e.to_string())?;
-    
-    let release = releases.as_array()
-        .and_then(|a| a.first())
-        .ok_or("No releases found".to_string())
-        .map_err(|e| e)?;
-        
-    let binary = release.get("binary").ok_or("No binary info".to_string()).map_err(|e| e)?;
-    let package = binary.get("package").ok_or("No package info".to_string()).map_err(|e| e)?;
-    let version_data = release.get("version").ok_or("No version info".to_string()).map_err(|e| e)?;
-    
-    let semver = version_data.get("semver").and_then(|v| v.as_str()).unwrap_or("unknown");
-    let download_url = package.get("link").and_then(|v| v.as_str()).ok_or("No download link".to_string()).map_err(|e| e)?;
-    let filename = package.get("name").and_then(|v| v.as_str()).ok_or("No filename".to_string()).map_err(|e| e)?;
-    let size = package.get("size").and_then(|v| v.as_u64()).unwrap_or(0);
-    let checksum = package.get("checksum").and_then(|v| v.as_str()).map(|s| s.to_string());
-
-    Ok(AdoptiumRelease {
-        version: semver.to_string(),
-        major,
-        download_url: download_url.to_string(),
-        filename: filename.to_string(),
-        size,
-        checksum,
-    })
+pub fn download_and_install_java(
+    app: &tauri::AppHandle,
+    major: u32,
+    distribution: Option<JavaDistribution>,
+    install_dir_str: String,
+    job: &Job,
+) -> Result<String, String> {
+    let release = java_distribution::fetch_release(distribution, major)?;
+    install_release(app, &release, Path::new(&install_dir_str), job)
 }
 
-pub fn download_and_install_java(major: u32, install_dir_str: String) -> Result<String, String> {
-    let release = fetch_adoptium_release(major)?;
-    let install_dir = PathBuf::from(&install_dir_str);
-    
-    fs::create_dir_all(&install_dir).map_err(|e| e.to_string())?;
+/// Download, verify and extract a release into `install_dir`, returning the path to its java executable
+pub fn install_release(
+    app: &tauri::AppHandle,
+    release: &JavaRelease,
+    install_dir: &Path,
+    job: &Job,
+) -> Result<String, String> {
+
+    fs::create_dir_all(install_dir).map_err(|e| e.to_string())?;
     let archive_path = install_dir.join(&release.filename);
+    let part_path = install_dir.join(format!("{}.part", release.filename));
     
-    let client = Client::builder().user_agent("VoidLink").build().map_err(|e| e.to_string())?;
-    let mut resp = client.get(&release.download_url).send().map_err(|e| e.to_string())?;
-    
-    let mut file = fs::File::create(&archive_path).map_err(|e| e.to_string())?;
-    std::io::copy(&mut resp, &mut file).map_err(|e| e.to_string())?;
+    // A cancelled download is dropped; a failed one keeps its .part file so the next try resumes it
+    if let Err(e) = download_with_retries(app, release, &part_path, job) {
+        if job.is_cancelled() {
+            let _ = fs::remove_file(&part_path);
+        }
+        return Err(e);
+    }
+    fs::rename(&part_path, &archive_path).map_err(|e| e.to_string())?;
+    job.check_cancelled().inspect_err(|_| { let _ = fs::remove_file(&archive_path); })?;
     
-    let extracted_dir = extract_archive(&archive_path, &install_dir).map_err(|e| e.to_string())?;
+    let extracted = extract_archive(&archive_path, install_dir).map_err(|e| e.to_string());
     let _ = fs::remove_file(&archive_path);
+    let extracted_dir = extracted?;
     
     let bin = find_java_executable(&extracted_dir).ok_or("Could not find java executable in extracted files".to_string()).map_err(|e| e)?;
+    java_runtime::write_marker(&extracted_dir, release);
     
     Ok(bin.to_string_lossy().to_string())
 }
 
+fn download_with_retries(
+    app: &tauri::AppHandle,
+    release: &JavaRelease,
+    part_path: &Path,
+    job: &Job,
+) -> Result<(), String> {
+    let client = Client::builder().user_agent("VoidLink").build().map_err(|e| e.to_string())?;
+    let mut attempt = 1;
+    loop {
+        let error = match download_archive(app, &client, release, part_path, job, attempt) {
+            Ok(()) => match verify_checksum(release, part_path) {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    // Whatever was resumed is unusable, start over
+                    let _ = fs::remove_file(part_path);
+                    DownloadError::Retry(e)
+                }
+            },
+            Err(e) => e,
+        };
+        job.check_cancelled()?;
+        match error {
+            DownloadError::Retry(e) if attempt < DOWNLOAD_ATTEMPTS => {
+                log::warn!("Java {} download attempt {} failed, retrying: {}", release.major, attempt, e);
+                std::thread::sleep(Duration::from_secs(RETRY_DELAY_SECS * attempt as u64));
+                attempt += 1;
+            }
+            error => return Err(error.message()),
+        }
+    }
+}
+
+// Download the release archive into its .part file, continuing where an earlier attempt stopped
+fn download_archive(
+    app: &tauri::AppHandle,
+    client: &Client,
+    release: &JavaRelease,
+    part_path: &Path,
+    job: &Job,
+    attempt: u32,
+) -> Result<(), DownloadError> {
+    let existing = fs::metadata(part_path).map(|m| m.len()).unwrap_or(0);
+    if release.size > 0 && existing >= release.size {
+        return Ok(());
+    }
+    
+    let mut request = client.get(&release.download_url);
+    if existing > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", existing));
+    }
+    let mut resp = request.send().map_err(|e| DownloadError::Retry(e.to_string()))?;
+    let status = resp.status();
+    
+    // 206 continues the partial file, 200 means the server ignored the range and sends everything
+    let resumed = status == reqwest::StatusCode::PARTIAL_CONTENT;
+    if !status.is_success() {
+        let message = format!("Download failed: {}", status);
+        return Err(match status {
+            // The .part file no longer matches the release, start over
+            reqwest::StatusCode::RANGE_NOT_SATISFIABLE => {
+                let _ = fs::remove_file(part_path);
+                DownloadError::Retry(message)
+            }
+            reqwest::StatusCode::TOO_MANY_REQUESTS => DownloadError::Retry(message),
+            _ if status.is_server_error() => DownloadError::Retry(message),
+            _ => DownloadError::Fatal(message),
+        });
+    }
+    let mut done = if resumed { existing } else { 0 };
+    let total = resp.content_length().map(|len| len + done).unwrap_or(release.size);
+    if resumed {
+        log::info!("Resuming Java {} download at {} of {} bytes", release.major, done, total);
+    }
+    
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(part_path)
+        .map_err(|e| DownloadError::Fatal(e.to_string()))?;
+    let mut file = std::io::BufWriter::new(file);
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut last_report = Instant::now();
+    let report = |done: u64| {
+        let _ = app.emit("java-download-progress", serde_json::json!({
+            "major": release.major,
+            "job_id": job.id(),
+            "bytes_done": done,
+            "bytes_total": total,
+            "attempt": attempt
+        }));
+        job.progress(JobProgress {
+            done,
+            total,
+            message: release.filename.clone(),
+            eta_secs: None,
+        });
+    };
+    loop {
+        job.check_cancelled().map_err(DownloadError::Fatal)?;
+        let read = resp.read(&mut buffer).map_err(|e| DownloadError::Retry(e.to_string()))?;
+        if read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..read]).map_err(|e| DownloadError::Fatal(e.to_string()))?;
+        done += read as u64;
+        if last_report.elapsed() >= DOWNLOAD_PROGRESS_INTERVAL {
+            last_report = Instant::now();
+            report(done);
+        }
+    }
+    file.flush().map_err(|e| DownloadError::Fatal(e.to_string()))?;
+    report(done);
+    
+    if done < total {
+        return Err(DownloadError::Retry(format!("Connection closed after {} of {} bytes", done, total)));
+    }
+    Ok(())
+}
+
+// Compare the downloaded archive against the SHA-256 published by the distribution
+fn verify_checksum(release: &JavaRelease, path: &Path) -> Result<(), String> {
+    let Some(expected) = &release.checksum else {
+        log::warn!("No checksum published for {}, skipping verification", release.filename);
+        return Ok(());
+    };
+    let actual = backup_verify::sha256_file(path)?;
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!(
+            "Checksum mismatch for {} (expected {}, got {}), the download is corrupt",
+            release.filename, expected, actual
+        ));
+    }
+    Ok(())
+}
+
 fn extract_archive(archive: &Path, dest: &Path) -> Result<PathBuf> {
     let file = fs::File::open(archive).map_err(|e| anyhow::anyhow!(e))?;
     let extension = archive.extension().and_then(|s| s.to_str()).unwrap_or("");
@@ -496,11 +682,16 @@ fn extract_archive(archive: &Path, dest: &Path) -> Result<PathBuf> {
     }
 }
 
-fn find_java_executable(dir: &Path) -> Option<PathBuf> {
+pub(crate) fn find_java_executable(dir: &Path) -> Option<PathBuf> {
     let bin_java = dir.join("bin").join(java_executable_name());
     if bin_java.exists() {
         return Some(bin_java);
     }
+    // macOS JDKs are bundles: jdk-21.0.2+13/Contents/Home/bin/java
+    let bundle_java = dir.join("Contents").join("Home").join("bin").join(java_executable_name());
+    if bundle_java.exists() {
+        return Some(bundle_java);
+    }
     
     if let Ok(entries) = fs::read_dir(dir) {
         for entry in entries.flatten() {
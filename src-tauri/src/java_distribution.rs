@@ -0,0 +1,580 @@
+use reqwest::blocking::{Client, Response};
+use reqwest::header::CONTENT_LENGTH;
+use serde::{Deserialize, Serialize};
+
+// Where JDKs are downloaded from. Each provider finds the latest GA release of a major version
+// for this machine's OS and architecture; downloading, checksum verification and extraction are
+// shared (see java::install_release).
+pub trait JavaDistributionProvider: Send + Sync {
+    fn distribution(&self) -> JavaDistribution;
+    fn latest_release(&self, major: u32) -> Result<JavaRelease, String>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JavaDistribution {
+    #[default]
+    Temurin,
+    Zulu,
+    Corretto,
+    Microsoft,
+}
+
+// Order tried when no distribution is asked for, e.g. Java 8 for ARM Macs is not built by Temurin
+const FALLBACK_ORDER: &[JavaDistribution] = &[
+    JavaDistribution::Temurin,
+    JavaDistribution::Zulu,
+    JavaDistribution::Corretto,
+    JavaDistribution::Microsoft,
+];
+
+impl JavaDistribution {
+    pub fn vendor(self) -> &'static str {
+        match self {
+            JavaDistribution::Temurin => "Eclipse Temurin",
+            JavaDistribution::Zulu => "Azul Zulu",
+            JavaDistribution::Corretto => "Amazon Corretto",
+            JavaDistribution::Microsoft => "Microsoft",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JavaRelease {
+    pub version: String,
+    pub major: u32,
+    #[serde(default)]
+    pub distribution: JavaDistribution,
+    pub download_url: String,
+    pub filename: String,
+    pub size: u64,
+    pub checksum: Option<String>, // SHA-256, hex
+}
+
+const ADOPTIUM_API: &str = "https://api.adoptium.net";
+const AZUL_API: &str = "https://api.azul.com";
+const CORRETTO_DOWNLOADS: &str = "https://corretto.aws/downloads";
+const MICROSOFT_DOWNLOADS: &str = "https://aka.ms/download-jdk";
+
+pub fn open_provider(distribution: JavaDistribution) -> Result<Box<dyn JavaDistributionProvider>, String> {
+    let base_url = match distribution {
+        JavaDistribution::Temurin => ADOPTIUM_API,
+        JavaDistribution::Zulu => AZUL_API,
+        JavaDistribution::Corretto => CORRETTO_DOWNLOADS,
+        JavaDistribution::Microsoft => MICROSOFT_DOWNLOADS,
+    };
+    open_provider_at(distribution, base_url)
+}
+
+/// Provider talking to `base_url` instead of the vendor's own servers, e.g. a mirror or a test server
+pub fn open_provider_at(distribution: JavaDistribution, base_url: &str) -> Result<Box<dyn JavaDistributionProvider>, String> {
+    let client = Client::builder().user_agent("VoidLink").build().map_err(|e| e.to_string())?;
+    let base_url = base_url.trim_end_matches('/').to_string();
+    Ok(match distribution {
+        JavaDistribution::Temurin => Box::new(AdoptiumProvider { client, base_url }),
+        JavaDistribution::Zulu => Box::new(ZuluProvider { client, base_url }),
+        JavaDistribution::Corretto => Box::new(CorrettoProvider { client, base_url }),
+        JavaDistribution::Microsoft => Box::new(MicrosoftProvider { client, base_url }),
+    })
+}
+
+/// Latest release of a major version from the given distribution, or from the first one
+/// in `FALLBACK_ORDER` that builds it for this machine
+pub fn fetch_release(distribution: Option<JavaDistribution>, major: u32) -> Result<JavaRelease, String> {
+    if let Some(distribution) = distribution {
+        return open_provider(distribution)?.latest_release(major);
+    }
+
+    let mut errors = Vec::new();
+    for distribution in FALLBACK_ORDER {
+        match open_provider(*distribution)?.latest_release(major) {
+            Ok(release) => return Ok(release),
+            Err(e) => {
+                log::info!("{} has no Java {} for this machine: {}", distribution.vendor(), major, e);
+                errors.push(format!("{}: {}", distribution.vendor(), e));
+            }
+        }
+    }
+    Err(format!("No distribution provides Java {} ({})", major, errors.join("; ")))
+}
+
+// === Platform names ===
+
+#[derive(Clone, Copy)]
+enum Os {
+    Windows,
+    Mac,
+    Linux,
+}
+
+fn current_os() -> Os {
+    if cfg!(target_os = "windows") {
+        Os::Windows
+    } else if cfg!(target_os = "macos") {
+        Os::Mac
+    } else {
+        Os::Linux
+    }
+}
+
+// The name most vendors use: x64, aarch64 or x86
+fn current_arch() -> &'static str {
+    #[cfg(target_arch = "x86_64")] { "x64" }
+    #[cfg(target_arch = "aarch64")] { "aarch64" }
+    #[cfg(target_arch = "x86")] { "x86" }
+}
+
+fn archive_extension() -> &'static str {
+    match current_os() {
+        Os::Windows => "zip",
+        Os::Mac | Os::Linux => "tar.gz",
+    }
+}
+
+fn check_status(response: Response, action: &str) -> Result<Response, String> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(format!("{} failed: {}", action, response.status()))
+    }
+}
+
+fn filename_of(url: &str) -> String {
+    url.rsplit('/').next().unwrap_or(url).split('?').next().unwrap_or_default().to_string()
+}
+
+// Follow a "latest" link to the file it currently points at, returning the final URL and its size
+fn resolve_latest(client: &Client, url: &str) -> Result<(String, u64), String> {
+    let response = client.head(url).send().map_err(|e| format!("Request failed: {}", e))?;
+    let response = check_status(response, "Resolving latest release")?;
+    // Read the header, content_length() is 0 for HEAD responses
+    let size = response.headers().get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    Ok((response.url().to_string(), size))
+}
+
+// Checksum files hold the hex digest, optionally followed by the file name. Only used for
+// vendors that publish one next to every download, so a missing checksum is an error rather
+// than a reason to skip verification
+fn fetch_checksum(client: &Client, url: &str) -> Result<String, String> {
+    let response = client.get(url).send().map_err(|e| format!("Fetching checksum failed: {}", e))?;
+    let text = check_status(response, "Fetching checksum")?
+        .text()
+        .map_err(|e| format!("Fetching checksum failed: {}", e))?;
+    text.split_whitespace()
+        .next()
+        .filter(|hash| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(str::to_lowercase)
+        .ok_or_else(|| format!("Malformed checksum file: {}", url))
+}
+
+// ===== Eclipse Temurin (Adoptium API) =====
+
+pub struct AdoptiumProvider {
+    client: Client,
+    base_url: String,
+}
+
+impl JavaDistributionProvider for AdoptiumProvider {
+    fn distribution(&self) -> JavaDistribution {
+        JavaDistribution::Temurin
+    }
+
+    fn latest_release(&self, major: u32) -> Result<JavaRelease, String> {
+        let os = match current_os() {
+            Os::Windows => "windows",
+            Os::Mac => "mac",
+            Os::Linux => "linux",
+        };
+        let arch = match current_arch() {
+            "x86" => "x32",
+            arch => arch,
+        };
+        let url = format!(
+            "{}/v3/assets/latest/{}/hotspot?architecture={}&image_type=jdk&os={}&vendor=eclipse",
+            self.base_url, major, arch, os
+        );
+
+        let resp = self.client.get(&url).send().map_err(|e| format!("Request failed: {}", e))?;
+        if !resp.status().is_success() {
+            return Err(format!("API Error: {}", resp.status()));
+        }
+
+        let releases: serde_json::Value = resp.json().map_err(|e| e.to_string())?;
+        let release = releases.as_array()
+            .and_then(|a| a.first())
+            .ok_or("No releases found")?;
+
+        let binary = release.get("binary").ok_or("No binary info")?;
+        let package = binary.get("package").ok_or("No package info")?;
+        let version_data = release.get("version").ok_or("No version info")?;
+
+        let semver = version_data.get("semver").and_then(|v| v.as_str()).unwrap_or("unknown");
+        let download_url = package.get("link").and_then(|v| v.as_str()).ok_or("No download link")?;
+        let filename = package.get("name").and_then(|v| v.as_str()).ok_or("No filename")?;
+        let size = package.get("size").and_then(|v| v.as_u64()).unwrap_or(0);
+        let checksum = package.get("checksum").and_then(|v| v.as_str()).map(|s| s.to_string());
+
+        Ok(JavaRelease {
+            version: semver.to_string(),
+            major,
+            distribution: self.distribution(),
+            download_url: download_url.to_string(),
+            filename: filename.to_string(),
+            size,
+            checksum,
+        })
+    }
+}
+
+// ===== Azul Zulu (Azul metadata API) =====
+
+pub struct ZuluProvider {
+    client: Client,
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+struct ZuluPackage {
+    package_uuid: String,
+    name: String,
+    download_url: String,
+    java_version: Vec<u32>,
+    #[serde(default)]
+    openjdk_build_number: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ZuluPackageDetails {
+    #[serde(default)]
+    sha256_hash: Option<String>,
+    #[serde(default)]
+    size: Option<u64>,
+}
+
+impl JavaDistributionProvider for ZuluProvider {
+    fn distribution(&self) -> JavaDistribution {
+        JavaDistribution::Zulu
+    }
+
+    fn latest_release(&self, major: u32) -> Result<JavaRelease, String> {
+        let os = match current_os() {
+            Os::Windows => "windows",
+            Os::Mac => "macos",
+            Os::Linux => "linux",
+        };
+        let url = format!(
+            "{}/metadata/v1/zulu/packages/?java_version={}&os={}&arch={}&archive_type={}\
+             &java_package_type=jdk&javafx_bundled=false&crac_supported=false&latest=true\
+             &release_status=ga&availability_types=CA&page_size=1",
+            self.base_url, major, os, current_arch(), archive_extension()
+        );
+        let response = self.client.get(&url).send().map_err(|e| format!("Request failed: {}", e))?;
+        let packages: Vec<ZuluPackage> = check_status(response, "Azul API")?
+            .json()
+            .map_err(|e| e.to_string())?;
+        let package = packages.into_iter().next().ok_or("No releases found")?;
+
+        // The checksum is only part of the package details
+        let details_url = format!("{}/metadata/v1/zulu/packages/{}", self.base_url, package.package_uuid);
+        let response = self.client.get(&details_url).send().map_err(|e| format!("Request failed: {}", e))?;
+        let details: ZuluPackageDetails = check_status(response, "Azul API")?
+            .json()
+            .map_err(|e| e.to_string())?;
+
+        let mut version = package.java_version.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(".");
+        if let Some(build) = package.openjdk_build_number {
+            version.push_str(&format!("+{}", build));
+        }
+
+        Ok(JavaRelease {
+            version,
+            major,
+            distribution: self.distribution(),
+            download_url: package.download_url,
+            filename: package.name,
+            size: details.size.unwrap_or(0),
+            checksum: details.sha256_hash.map(|h| h.to_lowercase()),
+        })
+    }
+}
+
+// ===== Amazon Corretto (permanent "latest" links) =====
+
+pub struct CorrettoProvider {
+    client: Client,
+    base_url: String,
+}
+
+impl JavaDistributionProvider for CorrettoProvider {
+    fn distribution(&self) -> JavaDistribution {
+        JavaDistribution::Corretto
+    }
+
+    fn latest_release(&self, major: u32) -> Result<JavaRelease, String> {
+        let os = match current_os() {
+            Os::Windows => "windows",
+            Os::Mac => "macos",
+            Os::Linux => "linux",
+        };
+        let file = format!("amazon-corretto-{}-{}-{}-jdk.{}", major, current_arch(), os, archive_extension());
+
+        // latest/<file> redirects to resources/<version>/amazon-corretto-<version>-<os>-<arch>.<ext>
+        let (download_url, size) = resolve_latest(&self.client, &format!("{}/latest/{}", self.base_url, file))?;
+        let version = download_url.split('/')
+            .skip_while(|segment| *segment != "resources")
+            .nth(1)
+            .ok_or_else(|| format!("Unexpected download location: {}", download_url))?
+            .to_string();
+        let checksum = fetch_checksum(&self.client, &format!("{}/latest_sha256/{}", self.base_url, file))?;
+
+        Ok(JavaRelease {
+            version,
+            major,
+            distribution: self.distribution(),
+            filename: filename_of(&download_url),
+            download_url,
+            size,
+            checksum: Some(checksum),
+        })
+    }
+}
+
+// ===== Microsoft Build of OpenJDK (aka.ms "latest" links) =====
+
+pub struct MicrosoftProvider {
+    client: Client,
+    base_url: String,
+}
+
+impl JavaDistributionProvider for MicrosoftProvider {
+    fn distribution(&self) -> JavaDistribution {
+        JavaDistribution::Microsoft
+    }
+
+    fn latest_release(&self, major: u32) -> Result<JavaRelease, String> {
+        let os = match current_os() {
+            Os::Windows => "windows",
+            Os::Mac => "macos",
+            Os::Linux => "linux",
+        };
+        let url = format!(
+            "{}/microsoft-jdk-{}-{}-{}.{}",
+            self.base_url, major, os, current_arch(), archive_extension()
+        );
+
+        // Redirects to .../microsoft-jdk-<version>-<os>-<arch>.<ext>
+        let (download_url, size) = resolve_latest(&self.client, &url)?;
+        let filename = filename_of(&download_url);
+        let version = filename.strip_prefix("microsoft-jdk-")
+            .and_then(|rest| rest.split_once(&format!("-{}-", os)))
+            .map(|(version, _)| version.to_string())
+            .ok_or_else(|| format!("Unexpected download location: {}", download_url))?;
+        let checksum = fetch_checksum(&self.client, &format!("{}.sha256sum.txt", url))?;
+
+        Ok(JavaRelease {
+            version,
+            major,
+            distribution: self.distribution(),
+            download_url,
+            filename,
+            size,
+            checksum: Some(checksum),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    const SHA: &str = "4d5c1a7e3b0f2a8c9e6d7b1f0a3c5e8d2b4f6a9c1e3d5b7f0a2c4e6d8b1f3a5c";
+
+    fn os_name(mac: &'static str) -> &'static str {
+        match current_os() {
+            Os::Windows => "windows",
+            Os::Mac => mac,
+            Os::Linux => "linux",
+        }
+    }
+
+    #[test]
+    fn adoptium_reads_the_latest_asset() {
+        let mut server = Server::new();
+        let arch = match current_arch() {
+            "x86" => "x32",
+            arch => arch,
+        };
+        let body = serde_json::json!([{
+            "binary": { "package": {
+                "link": "https://github.com/adoptium/temurin21-binaries/OpenJDK21U-jdk.tar.gz",
+                "name": "OpenJDK21U-jdk.tar.gz",
+                "size": 206_000_000u64,
+                "checksum": SHA,
+            }},
+            "version": { "semver": "21.0.5+11.0.LTS" },
+        }]);
+        let mock = server.mock("GET", "/v3/assets/latest/21/hotspot")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("architecture".into(), arch.into()),
+                Matcher::UrlEncoded("os".into(), os_name("mac").into()),
+                Matcher::UrlEncoded("image_type".into(), "jdk".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(body.to_string())
+            .create();
+
+        let release = open_provider_at(JavaDistribution::Temurin, &server.url()).unwrap().latest_release(21).unwrap();
+        mock.assert();
+        assert_eq!(release.version, "21.0.5+11.0.LTS");
+        assert_eq!(release.distribution, JavaDistribution::Temurin);
+        assert_eq!(release.filename, "OpenJDK21U-jdk.tar.gz");
+        assert_eq!(release.size, 206_000_000);
+        assert_eq!(release.checksum.as_deref(), Some(SHA));
+    }
+
+    #[test]
+    fn adoptium_without_releases_fails() {
+        let mut server = Server::new();
+        server.mock("GET", Matcher::Any).with_body("[]").create();
+        let result = open_provider_at(JavaDistribution::Temurin, &server.url()).unwrap().latest_release(8);
+        assert_eq!(result.unwrap_err(), "No releases found");
+    }
+
+    #[test]
+    fn zulu_combines_package_and_details() {
+        let mut server = Server::new();
+        let list = server.mock("GET", "/metadata/v1/zulu/packages/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("java_version".into(), "17".into()),
+                Matcher::UrlEncoded("os".into(), os_name("macos").into()),
+                Matcher::UrlEncoded("arch".into(), current_arch().into()),
+                Matcher::UrlEncoded("archive_type".into(), archive_extension().into()),
+            ]))
+            .with_body(serde_json::json!([{
+                "package_uuid": "0e6f2a6c-1b1e-4d5a-9d7f-3c2b1a0f9e8d",
+                "name": "zulu17.54.21-ca-jdk17.0.13-linux_x64.tar.gz",
+                "download_url": "https://cdn.azul.com/zulu/bin/zulu17.54.21-ca-jdk17.0.13-linux_x64.tar.gz",
+                "java_version": [17, 0, 13],
+                "openjdk_build_number": 11,
+            }]).to_string())
+            .create();
+        let details = server.mock("GET", "/metadata/v1/zulu/packages/0e6f2a6c-1b1e-4d5a-9d7f-3c2b1a0f9e8d")
+            .with_body(serde_json::json!({ "sha256_hash": SHA.to_uppercase(), "size": 190_000_000u64 }).to_string())
+            .create();
+
+        let release = open_provider_at(JavaDistribution::Zulu, &server.url()).unwrap().latest_release(17).unwrap();
+        list.assert();
+        details.assert();
+        assert_eq!(release.version, "17.0.13+11");
+        assert_eq!(release.filename, "zulu17.54.21-ca-jdk17.0.13-linux_x64.tar.gz");
+        assert_eq!(release.size, 190_000_000);
+        assert_eq!(release.checksum.as_deref(), Some(SHA));
+    }
+
+    #[test]
+    fn zulu_api_errors_are_reported() {
+        let mut server = Server::new();
+        server.mock("GET", Matcher::Any).with_status(503).create();
+        let error = open_provider_at(JavaDistribution::Zulu, &server.url()).unwrap().latest_release(17).unwrap_err();
+        assert!(error.starts_with("Azul API failed: 503"), "{}", error);
+    }
+
+    fn corretto_file() -> String {
+        format!("amazon-corretto-17-{}-{}-jdk.{}", current_arch(), os_name("macos"), archive_extension())
+    }
+
+    fn mock_corretto_latest(server: &mut Server) -> String {
+        let resource = format!(
+            "/resources/17.0.13.11.1/amazon-corretto-17.0.13.11.1-{}-{}.{}",
+            os_name("macos"), current_arch(), archive_extension()
+        );
+        server.mock("HEAD", format!("/latest/{}", corretto_file()).as_str())
+            .with_status(302)
+            .with_header("location", &format!("{}{}", server.url(), resource))
+            .create();
+        server.mock("HEAD", resource.as_str()).create();
+        resource
+    }
+
+    #[test]
+    fn corretto_follows_the_latest_link_and_reads_its_checksum() {
+        let mut server = Server::new();
+        let resource = mock_corretto_latest(&mut server);
+        server.mock("GET", format!("/latest_sha256/{}", corretto_file()).as_str())
+            .with_body(SHA)
+            .create();
+
+        let release = open_provider_at(JavaDistribution::Corretto, &server.url()).unwrap().latest_release(17).unwrap();
+        assert_eq!(release.version, "17.0.13.11.1");
+        assert_eq!(release.download_url, format!("{}{}", server.url(), resource));
+        assert_eq!(release.filename, filename_of(&resource));
+        assert_eq!(release.checksum.as_deref(), Some(SHA));
+    }
+
+    #[test]
+    fn corretto_fails_when_the_checksum_cannot_be_fetched() {
+        let mut server = Server::new();
+        mock_corretto_latest(&mut server);
+        server.mock("GET", format!("/latest_sha256/{}", corretto_file()).as_str())
+            .with_status(500)
+            .create();
+
+        let error = open_provider_at(JavaDistribution::Corretto, &server.url()).unwrap().latest_release(17).unwrap_err();
+        assert!(error.starts_with("Fetching checksum failed"), "{}", error);
+    }
+
+    fn microsoft_link(major: u32) -> String {
+        format!("/microsoft-jdk-{}-{}-{}.{}", major, os_name("macos"), current_arch(), archive_extension())
+    }
+
+    fn mock_microsoft_latest(server: &mut Server) -> String {
+        let resource = format!(
+            "/java/jdk/microsoft-jdk-21.0.5-{}-{}.{}",
+            os_name("macos"), current_arch(), archive_extension()
+        );
+        server.mock("HEAD", microsoft_link(21).as_str())
+            .with_status(301)
+            .with_header("location", &format!("{}{}", server.url(), resource))
+            .create();
+        server.mock("HEAD", resource.as_str()).create();
+        resource
+    }
+
+    #[test]
+    fn microsoft_parses_the_version_from_the_redirect() {
+        let mut server = Server::new();
+        let resource = mock_microsoft_latest(&mut server);
+        server.mock("GET", format!("{}.sha256sum.txt", microsoft_link(21)).as_str())
+            .with_body(format!("{}  {}\n", SHA.to_uppercase(), filename_of(&resource)))
+            .create();
+
+        let release = open_provider_at(JavaDistribution::Microsoft, &server.url()).unwrap().latest_release(21).unwrap();
+        assert_eq!(release.version, "21.0.5");
+        assert_eq!(release.filename, filename_of(&resource));
+        assert_eq!(release.checksum.as_deref(), Some(SHA));
+    }
+
+    #[test]
+    fn microsoft_rejects_a_malformed_checksum() {
+        let mut server = Server::new();
+        mock_microsoft_latest(&mut server);
+        server.mock("GET", format!("{}.sha256sum.txt", microsoft_link(21)).as_str())
+            .with_body("<html>Not found</html>")
+            .create();
+
+        let error = open_provider_at(JavaDistribution::Microsoft, &server.url()).unwrap().latest_release(21).unwrap_err();
+        assert!(error.starts_with("Malformed checksum file"), "{}", error);
+    }
+
+    #[test]
+    fn missing_latest_link_is_an_error() {
+        let mut server = Server::new();
+        server.mock("HEAD", Matcher::Any).with_status(404).create();
+        let error = open_provider_at(JavaDistribution::Microsoft, &server.url()).unwrap().latest_release(11).unwrap_err();
+        assert_eq!(error, "Resolving latest release failed: 404 Not Found");
+    }
+}
@@ -0,0 +1,414 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+use crate::java::{self, JavaInstallation};
+use crate::java_distribution::{self, JavaRelease};
+
+// Works out which Java a server needs from what is on disk, in order of trust:
+//   1. version.json inside the server jar (vanilla, and Paper/Purpur which embed it), which
+//      carries the exact Java the version was built for
+//   2. Forge/NeoForge libraries installed next to the server, or their installer's profile
+//   3. install.properties of the Fabric/Quilt server launcher
+//   4. the version recorded in server.json when the server was created
+// The Minecraft version and loader then give the supported range.
+
+// Java used when nothing tells which Minecraft version the server runs
+const DEFAULT_JAVA_MAJOR: u32 = 21;
+// Class file major version of Java 8 is 52, Java 17 is 61, ...
+const CLASS_VERSION_OFFSET: u32 = 44;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JavaRequirement {
+    pub min_major: u32,
+    pub max_major: Option<u32>, // None when any newer Java works
+    pub recommended_major: u32,
+    pub mc_version: Option<String>,
+    pub loader: String, // "vanilla", "paper", "fabric", "quilt", "forge", "neoforge", ...
+    pub source: String, // where the Minecraft version came from
+}
+
+impl JavaRequirement {
+    pub fn accepts(&self, major: u32) -> bool {
+        major >= self.min_major && !self.max_major.is_some_and(|max| major > max)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JavaResolution {
+    pub requirement: JavaRequirement,
+    pub installation: Option<JavaInstallation>, // best installed match
+    pub download: Option<JavaRelease>, // offered when nothing installed matches
+}
+
+// version.json of Mojang's server jar (1.14+)
+#[derive(Deserialize)]
+struct JarVersion {
+    id: String,
+    java_version: Option<u32>,
+}
+
+#[derive(Default)]
+struct ServerHints {
+    mc_version: Option<(String, &'static str)>, // version and where it was found
+    loader: Option<String>,
+    jar_java: Option<u32>, // Java the jar declares or was compiled for
+}
+
+impl ServerHints {
+    fn set_version(&mut self, version: String, source: &'static str) {
+        if self.mc_version.is_none() && !version.is_empty() {
+            self.mc_version = Some((version, source));
+        }
+    }
+}
+
+/// Resolve the Java a server needs and the best installation for it
+#[tauri::command]
+pub async fn java_resolve_cmd(app: tauri::AppHandle, server_path: String) -> Result<JavaResolution, String> {
+    let handle = tauri::async_runtime::spawn_blocking(move || {
+        let requirement = detect_requirement(Path::new(&server_path));
+        let installation = best_installation(&java::detect_installations(&app), &requirement);
+        let download = match installation {
+            Some(_) => None,
+            None => java_distribution::fetch_release(None, requirement.recommended_major)
+                .inspect_err(|e| log::warn!("No Java {} release to offer: {}", requirement.recommended_major, e))
+                .ok(),
+        };
+        JavaResolution { requirement, installation, download }
+    });
+
+    handle.await.map_err(|e| e.to_string())
+}
+
+/// The installed Java closest to the recommended version among those the server accepts
+pub fn best_installation(installations: &[JavaInstallation], requirement: &JavaRequirement) -> Option<JavaInstallation> {
+    installations.iter()
+        .filter(|j| j.is_valid)
+        .filter_map(|j| j.major.map(|major| (major, j)))
+        .filter(|(major, _)| requirement.accepts(*major))
+        .min_by_key(|(major, _)| major.abs_diff(requirement.recommended_major))
+        .map(|(_, j)| j.clone())
+}
+
+/// Work out the Java range a server directory needs
+pub fn detect_requirement(server_dir: &Path) -> JavaRequirement {
+    let meta: Option<serde_json::Value> = fs::read_to_string(server_dir.join("server.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+    let meta_str = |pointer: &str| {
+        meta.as_ref()
+            .and_then(|m| m.pointer(pointer))
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+
+    let mut hints = ServerHints::default();
+    inspect_server_jars(server_dir, meta_str("/jarFile").as_deref(), &mut hints);
+    inspect_forge_libraries(server_dir, &mut hints);
+    inspect_installers(server_dir, &mut hints);
+
+    if hints.loader.is_none() {
+        hints.loader = meta_str("/modpack/loader").or_else(|| meta_str("/type"));
+    }
+    if let Some(version) = meta_str("/version").filter(|v| java_version_rule(v).is_some()) {
+        hints.set_version(version, "server.json");
+    }
+
+    let loader = hints.loader.unwrap_or_else(|| "vanilla".to_string()).to_lowercase();
+    let (mc_version, source) = match hints.mc_version {
+        Some((version, source)) => (Some(version), source.to_string()),
+        None => (None, "unknown".to_string()),
+    };
+
+    let (mut min, mut max, mut recommended) = mc_version.as_deref()
+        .and_then(java_version_rule)
+        .map(|rule| rule.for_loader(&loader))
+        .unwrap_or((8, None, DEFAULT_JAVA_MAJOR));
+
+    // What the jar itself asks for wins over the version table
+    if let Some(jar_java) = hints.jar_java {
+        min = min.max(jar_java);
+        recommended = recommended.max(jar_java);
+        if max.is_some_and(|max| max < min) {
+            max = Some(min);
+        }
+    }
+
+    JavaRequirement {
+        min_major: min,
+        max_major: max,
+        recommended_major: recommended,
+        mc_version,
+        loader,
+        source,
+    }
+}
+
+// Vanilla, Paper, Fabric and friends run on any newer Java; only Forge and NeoForge of older
+// versions break on Java releases newer than the one their Minecraft version shipped with
+#[derive(Clone, Copy)]
+enum VersionRule {
+    Legacy,    // up to 1.12: Java 8, Forge only on 8
+    Flattened, // 1.13 - 1.16: Java 8, 11 recommended except for Forge which needs 8
+    Java16,    // 1.17, Forge up to 17
+    Java17,    // 1.18 - 1.20.4, Forge of these versions breaks on 21
+    Java21,    // 1.20.5+ and the year-based versions
+}
+
+impl VersionRule {
+    // (min, max, recommended)
+    fn for_loader(self, loader: &str) -> (u32, Option<u32>, u32) {
+        let forge = loader == "forge" || loader == "neoforge";
+        match self {
+            VersionRule::Legacy => (8, forge.then_some(8), 8),
+            VersionRule::Flattened if forge => (8, Some(8), 8),
+            VersionRule::Flattened => (8, None, 11),
+            VersionRule::Java16 => (16, forge.then_some(17), 17),
+            VersionRule::Java17 => (17, forge.then_some(17), 17),
+            VersionRule::Java21 => (21, None, 21),
+        }
+    }
+}
+
+// 1.X.Y (e.g. 1.20.4) and, from 2025 on, YY.X (e.g. 26.1, 26.1-snapshot-2)
+fn java_version_rule(mc_version: &str) -> Option<VersionRule> {
+    let clean = mc_version.split('-').next().unwrap_or("");
+    let parts: Vec<u32> = clean.split('.').map_while(|p| p.parse().ok()).collect();
+    match parts.as_slice() {
+        [year, ..] if *year >= 25 => Some(VersionRule::Java21),
+        [1, minor, rest @ ..] => {
+            let patch = rest.first().copied().unwrap_or(0);
+            Some(match *minor {
+                0..=12 => VersionRule::Legacy,
+                13..=16 => VersionRule::Flattened,
+                17 => VersionRule::Java16,
+                18 | 19 => VersionRule::Java17,
+                20 if patch < 5 => VersionRule::Java17,
+                _ => VersionRule::Java21,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn read_jar_entry(jar: &Path, name: &str) -> Option<Vec<u8>> {
+    let mut archive = ZipArchive::new(File::open(jar).ok()?).ok()?;
+    let mut entry = archive.by_name(name).ok()?;
+    let mut content = Vec::new();
+    entry.read_to_end(&mut content).ok()?;
+    Some(content)
+}
+
+fn top_level_jars(server_dir: &Path) -> Vec<PathBuf> {
+    let mut jars: Vec<PathBuf> = fs::read_dir(server_dir)
+        .map(|entries| entries.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default();
+    jars.retain(|p| p.is_file() && p.extension().is_some_and(|e| e.eq_ignore_ascii_case("jar")));
+    jars.sort();
+    jars
+}
+
+// Read version.json, the launcher's install.properties and the main class of the server jar
+fn inspect_server_jars(server_dir: &Path, jar_file: Option<&str>, hints: &mut ServerHints) {
+    // The jar the server is started with first
+    let mut jars = vec![server_dir.join("server.jar")];
+    if let Some(jar_file) = jar_file {
+        jars.insert(0, server_dir.join(jar_file));
+    }
+    jars.extend(top_level_jars(server_dir));
+    let mut seen = HashSet::new();
+    jars.retain(|jar| seen.insert(jar.clone()));
+
+    for jar in jars.iter().filter(|j| j.is_file()) {
+        if let Some(version) = read_jar_entry(jar, "version.json")
+            .and_then(|content| serde_json::from_slice::<JarVersion>(&content).ok())
+        {
+            hints.set_version(version.id, "server jar");
+            hints.jar_java = hints.jar_java.max(version.java_version);
+        }
+
+        if let Some(properties) = read_jar_entry(jar, "install.properties") {
+            let properties = String::from_utf8_lossy(&properties);
+            for line in properties.lines() {
+                if let Some(version) = line.strip_prefix("game-version=") {
+                    hints.set_version(version.trim().to_string(), "launcher install.properties");
+                }
+            }
+            let name = jar.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+            if hints.loader.is_none() {
+                hints.loader = Some(if name.contains("quilt") { "quilt" } else { "fabric" }.to_string());
+            }
+        }
+
+        if hints.jar_java.is_none() {
+            hints.jar_java = main_class_java(jar).filter(|major| *major > 8);
+        }
+    }
+}
+
+// Java version the jar's Main-Class was compiled for
+fn main_class_java(jar: &Path) -> Option<u32> {
+    let manifest = read_jar_entry(jar, "META-INF/MANIFEST.MF")?;
+    let manifest = String::from_utf8_lossy(&manifest);
+    let main_class = manifest.lines()
+        .find_map(|line| line.strip_prefix("Main-Class:"))?
+        .trim()
+        .replace('.', "/");
+    let class = read_jar_entry(jar, &format!("{}.class", main_class))?;
+    // u4 magic, u2 minor_version, u2 major_version
+    if class.len() < 8 || class[..4] != [0xCA, 0xFE, 0xBA, 0xBE] {
+        return None;
+    }
+    let class_version = u16::from_be_bytes([class[6], class[7]]) as u32;
+    class_version.checked_sub(CLASS_VERSION_OFFSET)
+}
+
+// Names of the version folders of an installed library, e.g. libraries/net/minecraftforge/forge/1.20.1-47.2.0
+fn library_versions(server_dir: &Path, library: &[&str]) -> Vec<String> {
+    let mut dir = server_dir.join("libraries");
+    for part in library {
+        dir.push(part);
+    }
+    let mut versions: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries.flatten()
+                .filter(|e| e.path().is_dir())
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    versions.sort();
+    versions
+}
+
+// NeoForge versions follow the Minecraft version without the leading "1.": 20.4.237 is for 1.20.4,
+// 21.0.x for 1.21; year-based versions keep it: 26.1.0.5 is for 26.1
+fn neoforge_mc_version(version: &str) -> Option<String> {
+    let parts: Vec<u32> = version.split(['.', '-']).map_while(|p| p.parse().ok()).collect();
+    match parts.as_slice() {
+        [year, minor, patch, _, ..] if *year >= 25 && *patch > 0 => Some(format!("{}.{}.{}", year, minor, patch)),
+        [year, minor, ..] if *year >= 25 => Some(format!("{}.{}", year, minor)),
+        [major, 0, ..] => Some(format!("1.{}", major)),
+        [major, minor, ..] => Some(format!("1.{}.{}", major, minor)),
+        _ => None,
+    }
+}
+
+fn inspect_forge_libraries(server_dir: &Path, hints: &mut ServerHints) {
+    if let Some(version) = library_versions(server_dir, &["net", "neoforged", "neoforge"]).pop() {
+        hints.loader.get_or_insert_with(|| "neoforge".to_string());
+        if let Some(mc_version) = neoforge_mc_version(&version) {
+            hints.set_version(mc_version, "NeoForge libraries");
+        }
+    }
+    // NeoForge for 1.20.1 kept Forge's <mc>-<forge> versioning
+    for (library, loader) in [
+        (["net", "neoforged", "forge"], "neoforge"),
+        (["net", "minecraftforge", "forge"], "forge"),
+    ] {
+        if let Some(version) = library_versions(server_dir, &library).pop() {
+            hints.loader.get_or_insert_with(|| loader.to_string());
+            if let Some((mc_version, _)) = version.split_once('-') {
+                hints.set_version(mc_version.to_string(), "Forge libraries");
+            }
+        }
+    }
+}
+
+// Forge and NeoForge installers left next to the server name the Minecraft version in install_profile.json
+fn inspect_installers(server_dir: &Path, hints: &mut ServerHints) {
+    for jar in top_level_jars(server_dir) {
+        let name = jar.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+        if !name.contains("installer") {
+            continue;
+        }
+        let Some(profile) = read_jar_entry(&jar, "install_profile.json")
+            .and_then(|content| serde_json::from_slice::<serde_json::Value>(&content).ok())
+        else {
+            continue;
+        };
+        // "minecraft" at the top level since Forge 1.13, under "install" before that
+        let version = profile.get("minecraft")
+            .or_else(|| profile.pointer("/install/minecraft"))
+            .and_then(|v| v.as_str());
+        if let Some(version) = version {
+            hints.set_version(version.to_string(), "installer profile");
+        }
+        let loader = if name.contains("neoforge") { "neoforge" } else { "forge" };
+        hints.loader.get_or_insert_with(|| loader.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(mc_version: &str, loader: &str) -> Option<(u32, Option<u32>, u32)> {
+        java_version_rule(mc_version).map(|rule| rule.for_loader(loader))
+    }
+
+    #[test]
+    fn only_forge_and_neoforge_get_a_maximum() {
+        assert_eq!(range("1.8.9", "vanilla"), Some((8, None, 8)));
+        assert_eq!(range("1.12.2", "forge"), Some((8, Some(8), 8)));
+        assert_eq!(range("1.16.5", "paper"), Some((8, None, 11)));
+        assert_eq!(range("1.16.5", "forge"), Some((8, Some(8), 8)));
+        assert_eq!(range("1.17.1", "fabric"), Some((16, None, 17)));
+        assert_eq!(range("1.17.1", "forge"), Some((16, Some(17), 17)));
+        assert_eq!(range("1.20.4", "vanilla"), Some((17, None, 17)));
+        assert_eq!(range("1.20.4", "neoforge"), Some((17, Some(17), 17)));
+        assert_eq!(range("1.20.1", "forge"), Some((17, Some(17), 17)));
+    }
+
+    #[test]
+    fn newer_versions_need_java_21() {
+        assert_eq!(range("1.20.5", "vanilla"), Some((21, None, 21)));
+        assert_eq!(range("1.21.1", "neoforge"), Some((21, None, 21)));
+        assert_eq!(range("26.1", "vanilla"), Some((21, None, 21)));
+        assert_eq!(range("26.1-snapshot-2", "fabric"), Some((21, None, 21)));
+    }
+
+    #[test]
+    fn version_rule_parsing() {
+        assert_eq!(range("1.20", "vanilla"), Some((17, None, 17)));
+        assert_eq!(range("1.18-pre1", "vanilla"), Some((17, None, 17)));
+        assert_eq!(range("1.7.10", "forge"), Some((8, Some(8), 8)));
+        assert_eq!(range("", "vanilla"), None);
+        assert_eq!(range("latest", "vanilla"), None);
+        assert_eq!(range("24w14a", "vanilla"), None);
+        assert_eq!(range("2.0", "vanilla"), None);
+    }
+
+    #[test]
+    fn requirement_accepts_its_range() {
+        let requirement = JavaRequirement {
+            min_major: 17,
+            max_major: Some(17),
+            recommended_major: 17,
+            mc_version: Some("1.20.1".to_string()),
+            loader: "forge".to_string(),
+            source: "server jar".to_string(),
+        };
+        assert!(requirement.accepts(17));
+        assert!(!requirement.accepts(16));
+        assert!(!requirement.accepts(21));
+        assert!(JavaRequirement { max_major: None, ..requirement }.accepts(25));
+    }
+
+    #[test]
+    fn neoforge_versions_map_to_minecraft_versions() {
+        assert_eq!(neoforge_mc_version("20.4.237").as_deref(), Some("1.20.4"));
+        assert_eq!(neoforge_mc_version("20.2.3-beta").as_deref(), Some("1.20.2"));
+        assert_eq!(neoforge_mc_version("21.0.167").as_deref(), Some("1.21"));
+        assert_eq!(neoforge_mc_version("21.1.77").as_deref(), Some("1.21.1"));
+        assert_eq!(neoforge_mc_version("26.1.0.5").as_deref(), Some("26.1"));
+        assert_eq!(neoforge_mc_version("26.1.1.3-beta").as_deref(), Some("26.1.1"));
+        assert_eq!(neoforge_mc_version("26.1.0").as_deref(), Some("26.1"));
+        assert_eq!(neoforge_mc_version("neoforge"), None);
+    }
+}
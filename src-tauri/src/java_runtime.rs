@@ -0,0 +1,397 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use tauri::Manager;
+use walkdir::WalkDir;
+
+use crate::java;
+use crate::java_distribution::{self, JavaDistribution, JavaRelease};
+use crate::jobs::{self, Job};
+
+// Runtimes downloaded by VoidLink live in <app data>/java/<major>/<release folder>, e.g.
+// java/21/jdk-21.0.2+13. Updates are extracted next to the runtime they replace, which stays
+// until no server or setting points at it anymore.
+const MARKER_NAME: &str = "voidlink-runtime.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RuntimeMarker {
+    version: String,
+    major: u32,
+    #[serde(default)]
+    distribution: JavaDistribution, // markers written before other distributions were Temurin
+    vendor: String,
+    installed_at: String,
+    checksum: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ManagedRuntime {
+    pub id: String, // "<major>/<folder>"
+    pub major: u32,
+    pub distribution: JavaDistribution, // Temurin for runtimes installed before markers existed
+    pub version: Option<String>,
+    pub vendor: Option<String>,
+    pub java_path: String,
+    pub size_bytes: u64,
+    pub installed_at: Option<String>,
+    pub used_by: Vec<String>, // servers and settings pointing at this runtime
+    pub superseded: bool, // a newer runtime of the same major and distribution is installed
+    pub latest_version: Option<String>, // only filled when checking for updates
+    pub update_available: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeUpdate {
+    pub runtime: Option<ManagedRuntime>, // None if already up to date
+    pub repointed: Vec<String>, // servers and settings moved to the new runtime
+    pub kept: Vec<String>, // old runtimes still in use, e.g. by a running server
+    pub removed: Vec<String>,
+}
+
+// Something pointing at a java executable
+struct RuntimeReference {
+    label: String,
+    java_path: String,
+    file: PathBuf, // server.json or settings.json holding the path
+    pointer: &'static str, // where the path sits in that file
+}
+
+fn java_root(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app.path().app_data_dir().map_err(|e| e.to_string())?.join("java"))
+}
+
+/// Every managed runtime folder with the major version of the folder it sits in
+pub fn runtime_dirs(app: &tauri::AppHandle) -> Vec<(u32, PathBuf)> {
+    let Ok(root) = java_root(app) else { return Vec::new() };
+    let mut dirs = Vec::new();
+    for major_dir in fs::read_dir(root).into_iter().flatten().flatten() {
+        let Some(major) = major_dir.file_name().to_str().and_then(|n| n.parse::<u32>().ok()) else { continue };
+        for runtime in fs::read_dir(major_dir.path()).into_iter().flatten().flatten() {
+            if runtime.path().is_dir() {
+                dirs.push((major, runtime.path()));
+            }
+        }
+    }
+    dirs.sort();
+    dirs
+}
+
+/// Record which release a freshly extracted runtime came from
+pub fn write_marker(runtime_dir: &Path, release: &JavaRelease) {
+    let marker = RuntimeMarker {
+        version: release.version.clone(),
+        major: release.major,
+        distribution: release.distribution,
+        vendor: release.distribution.vendor().to_string(),
+        installed_at: chrono::Local::now().to_rfc3339(),
+        checksum: release.checksum.clone(),
+    };
+    let written = serde_json::to_string_pretty(&marker)
+        .map_err(|e| e.to_string())
+        .and_then(|content| fs::write(runtime_dir.join(MARKER_NAME), content).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        log::warn!("Failed to write runtime marker in {:?}: {}", runtime_dir, e);
+    }
+}
+
+fn read_marker(runtime_dir: &Path) -> Option<RuntimeMarker> {
+    let content = fs::read_to_string(runtime_dir.join(MARKER_NAME)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+// Version from the JDK's `release` file, for runtimes installed before markers existed
+fn release_file_version(runtime_dir: &Path) -> Option<String> {
+    let home = [runtime_dir.to_path_buf(), runtime_dir.join("Contents").join("Home")]
+        .into_iter()
+        .find(|home| home.join("release").is_file())?;
+    let content = fs::read_to_string(home.join("release")).ok()?;
+    let value = |key: &str| {
+        content.lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            .map(|v| v.trim().trim_matches('"').to_string())
+    };
+    // SEMANTIC_VERSION matches Adoptium's version numbers, also for Java 8 ("8.0.402+6")
+    value("SEMANTIC_VERSION").or_else(|| value("JAVA_RUNTIME_VERSION"))
+}
+
+// Compare versions such as "21.0.2+13" number by number, ignoring components only one of them has
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let numbers = |v: &str| -> Vec<u64> {
+        v.split(|c: char| !c.is_ascii_digit()).filter_map(|n| n.parse().ok()).collect()
+    };
+    numbers(a).iter().zip(numbers(b).iter())
+        .map(|(x, y)| x.cmp(y))
+        .find(|o| o.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    WalkDir::new(dir).into_iter()
+        .flatten()
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+// Java paths used by servers (server.json) and the per-major defaults in settings.json
+fn collect_references() -> Vec<RuntimeReference> {
+    let Some(voidlink_dir) = dirs::document_dir().map(|d| d.join("VoidLink")) else { return Vec::new() };
+    let mut references = Vec::new();
+
+    for entry in fs::read_dir(voidlink_dir.join("servers")).into_iter().flatten().flatten() {
+        let file = entry.path().join("server.json");
+        let Some(meta) = fs::read_to_string(&file).ok()
+            .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+        else {
+            continue;
+        };
+        let Some(java_path) = meta.pointer("/javaSettings/path").and_then(|v| v.as_str()) else { continue };
+        let folder = entry.file_name().to_string_lossy().to_string();
+        references.push(RuntimeReference {
+            label: meta.get("name").and_then(|v| v.as_str()).unwrap_or(&folder).to_string(),
+            java_path: java_path.to_string(),
+            file,
+            pointer: "/javaSettings/path",
+        });
+    }
+
+    let settings_file = voidlink_dir.join("settings.json");
+    if let Some(settings) = fs::read_to_string(&settings_file).ok()
+        .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+    {
+        for (key, pointer) in [
+            ("java8", "/javaInstallations/java8"),
+            ("java11", "/javaInstallations/java11"),
+            ("java17", "/javaInstallations/java17"),
+            ("java21", "/javaInstallations/java21"),
+        ] {
+            if let Some(java_path) = settings.pointer(pointer).and_then(|v| v.as_str()).filter(|p| !p.is_empty()) {
+                references.push(RuntimeReference {
+                    label: format!("Settings ({})", key),
+                    java_path: java_path.to_string(),
+                    file: settings_file.clone(),
+                    pointer,
+                });
+            }
+        }
+    }
+
+    references
+}
+
+// Resolve symlinks and `..` so paths written differently still compare equal
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn points_into(reference: &RuntimeReference, runtime_dir: &Path) -> bool {
+    canonical(Path::new(&reference.java_path)).starts_with(canonical(runtime_dir))
+}
+
+// Executables of all running processes, also servers started outside VoidLink
+fn running_executables() -> Vec<PathBuf> {
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::new().with_exe(UpdateKind::OnlyIfNotSet),
+    );
+    sys.processes().values()
+        .filter_map(|process| process.exe())
+        .map(canonical)
+        .collect()
+}
+
+fn runtime_running(runtime_dir: &Path, executables: &[PathBuf]) -> bool {
+    let runtime_dir = canonical(runtime_dir);
+    executables.iter().any(|exe| exe.starts_with(&runtime_dir))
+}
+
+fn set_reference(reference: &RuntimeReference, java_path: &str) -> Result<(), String> {
+    let content = fs::read_to_string(&reference.file).map_err(|e| e.to_string())?;
+    let mut value: serde_json::Value = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    let slot = value.pointer_mut(reference.pointer)
+        .ok_or_else(|| format!("{} no longer has a Java path", reference.label))?;
+    *slot = serde_json::json!(java_path);
+    let content = serde_json::to_string_pretty(&value).map_err(|e| e.to_string())?;
+    fs::write(&reference.file, content).map_err(|e| e.to_string())
+}
+
+fn list_runtimes(app: &tauri::AppHandle) -> Vec<ManagedRuntime> {
+    let references = collect_references();
+    let mut runtimes: Vec<ManagedRuntime> = runtime_dirs(app).into_iter()
+        .filter_map(|(major, dir)| {
+            let java_path = java::find_java_executable(&dir)?;
+            let marker = read_marker(&dir);
+            let folder = dir.file_name()?.to_string_lossy().to_string();
+            Some(ManagedRuntime {
+                id: format!("{}/{}", major, folder),
+                major,
+                distribution: marker.as_ref().map(|m| m.distribution).unwrap_or_default(),
+                version: marker.as_ref().map(|m| m.version.clone()).or_else(|| release_file_version(&dir)),
+                vendor: marker.as_ref().map(|m| m.vendor.clone()),
+                java_path: java_path.to_string_lossy().to_string(),
+                size_bytes: dir_size(&dir),
+                installed_at: marker.map(|m| m.installed_at),
+                used_by: references.iter()
+                    .filter(|r| points_into(r, &dir))
+                    .map(|r| r.label.clone())
+                    .collect(),
+                superseded: false,
+                latest_version: None,
+                update_available: false,
+            })
+        })
+        .collect();
+
+    // Newest version of each major and distribution, to flag the runtimes an update replaced
+    let mut newest: HashMap<(u32, JavaDistribution), String> = HashMap::new();
+    for runtime in &runtimes {
+        if let Some(version) = &runtime.version {
+            let current = newest.entry((runtime.major, runtime.distribution)).or_insert_with(|| version.clone());
+            if compare_versions(version, current).is_gt() {
+                *current = version.clone();
+            }
+        }
+    }
+    for runtime in &mut runtimes {
+        runtime.superseded = match (&runtime.version, newest.get(&(runtime.major, runtime.distribution))) {
+            (Some(version), Some(newest)) => compare_versions(version, newest).is_lt(),
+            _ => false,
+        };
+    }
+    runtimes
+}
+
+/// List the runtimes VoidLink installed, optionally checking their distributions for newer releases
+#[tauri::command]
+pub async fn java_runtime_list_cmd(app: tauri::AppHandle, check_updates: bool) -> Result<Vec<ManagedRuntime>, String> {
+    let handle = tauri::async_runtime::spawn_blocking(move || {
+        let mut runtimes = list_runtimes(&app);
+        if check_updates {
+            let mut latest: HashMap<(u32, JavaDistribution), Option<String>> = HashMap::new();
+            for runtime in &mut runtimes {
+                let release = latest.entry((runtime.major, runtime.distribution)).or_insert_with(|| {
+                    java_distribution::fetch_release(Some(runtime.distribution), runtime.major)
+                        .inspect_err(|e| log::warn!("Failed to check Java {} for updates: {}", runtime.major, e))
+                        .ok()
+                        .map(|r| r.version)
+                });
+                runtime.latest_version = release.clone();
+                runtime.update_available = !runtime.superseded && match (runtime.version.as_deref(), release.as_deref()) {
+                    (Some(version), Some(latest)) => compare_versions(latest, version).is_gt(),
+                    _ => false,
+                };
+            }
+        }
+        runtimes
+    });
+
+    handle.await.map_err(|e| e.to_string())
+}
+
+/// Install the latest release of a major version as a cancellable job, moving stopped servers and
+/// settings over to it and removing replaced runtimes nothing uses anymore
+#[tauri::command]
+pub async fn java_runtime_update_cmd(
+    app: tauri::AppHandle,
+    major: u32,
+    distribution: Option<JavaDistribution>,
+) -> Result<RuntimeUpdate, String> {
+    let distribution = distribution.unwrap_or_default();
+    let handle = tauri::async_runtime::spawn_blocking(move || {
+        jobs::run(&app, "java-update", &format!("{} {}", distribution.vendor(), major), |job| {
+            update_runtime(&app, job, major, distribution)
+        })
+    });
+
+    handle.await.map_err(|e| e.to_string())?
+}
+
+fn update_runtime(
+    app: &tauri::AppHandle,
+    job: &Job,
+    major: u32,
+    distribution: JavaDistribution,
+) -> Result<RuntimeUpdate, String> {
+    let installed: Vec<ManagedRuntime> = list_runtimes(app).into_iter()
+        .filter(|r| r.major == major && r.distribution == distribution)
+        .collect();
+    let release = java_distribution::fetch_release(Some(distribution), major)?;
+    let up_to_date = installed.iter()
+        .filter_map(|r| r.version.as_deref())
+        .any(|version| compare_versions(&release.version, version).is_le());
+    if up_to_date {
+        return Ok(RuntimeUpdate { runtime: None, repointed: Vec::new(), kept: Vec::new(), removed: Vec::new() });
+    }
+
+    let java_path = java::install_release(app, &release, &java_root(app)?.join(major.to_string()), job)?;
+    log::info!("Installed {} {} at {}", distribution.vendor(), release.version, java_path);
+
+    let mut update = RuntimeUpdate { runtime: None, repointed: Vec::new(), kept: Vec::new(), removed: Vec::new() };
+    let references = collect_references();
+    let executables = running_executables();
+    for old in &installed {
+        let old_dir = runtime_dir(app, old)?;
+        // Reinstalling a runtime without version information lands in the same folder
+        if canonical(Path::new(&java_path)).starts_with(canonical(&old_dir)) {
+            continue;
+        }
+        // A running server keeps the runtime it was started with until it is restarted
+        let mut in_use = runtime_running(&old_dir, &executables);
+        for reference in references.iter().filter(|r| points_into(r, &old_dir)) {
+            match set_reference(reference, &java_path) {
+                Ok(()) => update.repointed.push(reference.label.clone()),
+                Err(e) => {
+                    log::warn!("Failed to move {} to Java {}: {}", reference.label, release.version, e);
+                    in_use = true;
+                }
+            }
+        }
+        if in_use {
+            update.kept.push(old.id.clone());
+        } else if let Err(e) = fs::remove_dir_all(&old_dir) {
+            log::warn!("Failed to remove replaced runtime {}: {}", old.id, e);
+            update.kept.push(old.id.clone());
+        } else {
+            update.removed.push(old.id.clone());
+        }
+    }
+
+    update.runtime = list_runtimes(app).into_iter().find(|r| r.java_path == java_path);
+    Ok(update)
+}
+
+fn runtime_dir(app: &tauri::AppHandle, runtime: &ManagedRuntime) -> Result<PathBuf, String> {
+    let (major, folder) = runtime.id.split_once('/').ok_or("Invalid runtime id")?;
+    Ok(java_root(app)?.join(major).join(folder))
+}
+
+/// Delete a managed runtime, refusing while any server or setting still points at it
+#[tauri::command]
+pub fn java_runtime_remove_cmd(app: tauri::AppHandle, runtime_id: String) -> Result<(), String> {
+    // Look the id up instead of joining it to a path, so it cannot point outside the java folder
+    let runtime = list_runtimes(&app).into_iter()
+        .find(|r| r.id == runtime_id)
+        .ok_or("Runtime not found")?;
+    if !runtime.used_by.is_empty() {
+        return Err(format!("Java runtime is still used by: {}", runtime.used_by.join(", ")));
+    }
+
+    let dir = runtime_dir(&app, &runtime)?;
+    if runtime_running(&dir, &running_executables()) {
+        return Err("Java runtime is used by a running process, stop it first".to_string());
+    }
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to remove runtime: {}", e))?;
+    log::info!("Removed Java runtime {}", runtime.id);
+
+    // Drop the major folder once its last runtime is gone
+    if let Some(parent) = dir.parent() {
+        let _ = fs::remove_dir(parent);
+    }
+    Ok(())
+}
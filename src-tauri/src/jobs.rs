@@ -0,0 +1,178 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+// Long-running operations (backups, restores, Java downloads) register here while they run so
+// the UI can list them, follow their progress and cancel them. Every job emits
+//   job-started  { job }
+//   job-progress { job }                         at most every PROGRESS_INTERVAL
+//   job-finished { job, status, error }          status: "completed" | "failed" | "cancelled"
+
+// Error returned by operations that stopped because their job was cancelled
+pub const CANCELLED: &str = "Cancelled";
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+static JOBS: LazyLock<Mutex<HashMap<String, RunningJob>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: String,
+    pub kind: String, // "backup", "restore", "java-download", ...
+    pub label: String, // what the job works on, e.g. the server id
+    pub started_at: String,
+    pub progress: Option<JobProgress>,
+    pub cancelling: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobProgress {
+    pub done: u64,
+    pub total: u64,
+    pub message: String,
+    pub eta_secs: Option<u64>,
+}
+
+struct RunningJob {
+    info: JobInfo,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Handle of a registered job, removed from the registry when dropped
+pub struct Job {
+    id: String,
+    app: tauri::AppHandle,
+    cancelled: Arc<AtomicBool>,
+    last_progress: Mutex<Option<Instant>>,
+    outcome: Option<Result<(), String>>,
+}
+
+impl Job {
+    fn start(app: &tauri::AppHandle, kind: &str, label: &str) -> Self {
+        let id = format!("{}-{}", kind, NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let info = JobInfo {
+            id: id.clone(),
+            kind: kind.to_string(),
+            label: label.to_string(),
+            started_at: chrono::Local::now().to_rfc3339(),
+            progress: None,
+            cancelling: false,
+        };
+
+        log::info!("Started job {} ({})", id, label);
+        let _ = app.emit("job-started", serde_json::json!({ "job": info }));
+        if let Ok(mut jobs) = JOBS.lock() {
+            jobs.insert(id.clone(), RunningJob { info, cancelled: cancelled.clone() });
+        }
+
+        Self {
+            id,
+            app: app.clone(),
+            cancelled,
+            last_progress: Mutex::new(None),
+            outcome: None,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Err(CANCELLED) once the job was cancelled, for use with `?` between steps
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Flag set on cancellation, for code that runs on other threads
+    pub fn cancel_flag(&self) -> Arc<AtomicBool> {
+        self.cancelled.clone()
+    }
+
+    /// Record progress, emitting `job-progress` unless one was emitted very recently
+    pub fn progress(&self, progress: JobProgress) {
+        let info = match JOBS.lock() {
+            Ok(mut jobs) => match jobs.get_mut(&self.id) {
+                Some(job) => {
+                    job.info.progress = Some(progress);
+                    job.info.clone()
+                }
+                None => return,
+            },
+            Err(_) => return,
+        };
+
+        let finished = info.progress.as_ref().is_some_and(|p| p.total > 0 && p.done >= p.total);
+        if let Ok(mut last) = self.last_progress.lock() {
+            if !finished && last.is_some_and(|l| l.elapsed() < PROGRESS_INTERVAL) {
+                return;
+            }
+            *last = Some(Instant::now());
+        }
+        let _ = self.app.emit("job-progress", serde_json::json!({ "job": info }));
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        let info = JOBS.lock().ok().and_then(|mut jobs| jobs.remove(&self.id)).map(|job| job.info);
+        let (status, error) = match self.outcome.take() {
+            Some(Ok(())) => ("completed", None),
+            Some(Err(_)) if self.is_cancelled() => ("cancelled", None),
+            Some(Err(e)) => ("failed", Some(e)),
+            None => ("failed", Some("Job ended unexpectedly".to_string())),
+        };
+        log::info!("Job {} {}", self.id, status);
+        let _ = self.app.emit("job-finished", serde_json::json!({
+            "job": info,
+            "job_id": self.id,
+            "status": status,
+            "error": error
+        }));
+    }
+}
+
+/// Run `f` as a registered job
+pub fn run<T>(
+    app: &tauri::AppHandle,
+    kind: &str,
+    label: &str,
+    f: impl FnOnce(&Job) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut job = Job::start(app, kind, label);
+    let result = f(&job);
+    job.outcome = Some(result.as_ref().map(|_| ()).map_err(|e| e.clone()));
+    result
+}
+
+/// Request cancellation of a running job. The job stops at its next checkpoint and cleans up.
+#[tauri::command]
+pub fn job_cancel(job_id: String) -> Result<(), String> {
+    let mut jobs = JOBS.lock().map_err(|e| e.to_string())?;
+    let job = jobs.get_mut(&job_id).ok_or("Job not found")?;
+    job.cancelled.store(true, Ordering::Relaxed);
+    job.info.cancelling = true;
+    log::info!("Cancelling job {}", job_id);
+    Ok(())
+}
+
+/// List the jobs currently running
+#[tauri::command]
+pub fn job_list() -> Result<Vec<JobInfo>, String> {
+    let jobs = JOBS.lock().map_err(|e| e.to_string())?;
+    let mut list: Vec<JobInfo> = jobs.values().map(|job| job.info.clone()).collect();
+    list.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    Ok(list)
+}
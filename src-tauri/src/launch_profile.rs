@@ -0,0 +1,570 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::java::{self, JavaValidation};
+use crate::java_requirement;
+use crate::server_bundle::read_server_meta;
+
+// How a server's JVM is started: Java, heap, garbage collector, flags and environment.
+// Stored in server.json as "launchProfile". The heap size, Java path and free-form flags are
+// also what the server page edits in "javaSettings", which wins when both are present and is
+// kept in sync when a profile is saved.
+
+// Heap below this does not even get a vanilla server through startup
+const MIN_HEAP_MB: u32 = 512;
+// Initial heap when the profile does not set one and the preset does not want it fixed
+const DEFAULT_MIN_HEAP_MB: u32 = 1024;
+// Left to the OS and to the JVM's own memory outside the heap
+const RESERVED_SYSTEM_MEMORY_MB: u64 = 1024;
+// Aikar's flags use a larger young generation above this heap size
+const AIKAR_LARGE_HEAP_MB: u32 = 12 * 1024;
+// Largest heap a 32-bit JVM can reserve on any OS
+const MAX_HEAP_32_BIT_MB: u32 = 1536;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GarbageCollector {
+    #[default]
+    Default, // whatever the JVM picks for the machine
+    G1,
+    Zgc,
+    GenerationalZgc,
+    Shenandoah,
+    Parallel,
+    Serial,
+}
+
+impl GarbageCollector {
+    fn label(self) -> &'static str {
+        match self {
+            GarbageCollector::Default => "the default collector",
+            GarbageCollector::G1 => "G1",
+            GarbageCollector::Zgc => "ZGC",
+            GarbageCollector::GenerationalZgc => "Generational ZGC",
+            GarbageCollector::Shenandoah => "Shenandoah",
+            GarbageCollector::Parallel => "the Parallel collector",
+            GarbageCollector::Serial => "the Serial collector",
+        }
+    }
+
+    fn min_major(self) -> u32 {
+        match self {
+            GarbageCollector::Default | GarbageCollector::G1 | GarbageCollector::Parallel | GarbageCollector::Serial => 8,
+            GarbageCollector::Shenandoah => 12,
+            GarbageCollector::Zgc => 15, // experimental before
+            GarbageCollector::GenerationalZgc => 21,
+        }
+    }
+
+    fn flags(self, major: u32) -> Vec<String> {
+        let flags: &[&str] = match self {
+            GarbageCollector::Default => &[],
+            GarbageCollector::G1 => &["-XX:+UseG1GC"],
+            GarbageCollector::Zgc => &["-XX:+UseZGC"],
+            // Generational is the only mode from Java 23 on, where -XX:+ZGenerational is deprecated
+            GarbageCollector::GenerationalZgc if major >= 23 => &["-XX:+UseZGC"],
+            GarbageCollector::GenerationalZgc => &["-XX:+UseZGC", "-XX:+ZGenerational"],
+            GarbageCollector::Shenandoah => &["-XX:+UseShenandoahGC"],
+            GarbageCollector::Parallel => &["-XX:+UseParallelGC"],
+            GarbageCollector::Serial => &["-XX:+UseSerialGC"],
+        };
+        flags.iter().map(|f| f.to_string()).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FlagPreset {
+    #[default]
+    None,
+    Aikar, // https://docs.papermc.io/paper/aikars-flags
+    Zgc,
+    LowMemory,
+}
+
+impl FlagPreset {
+    fn label(self) -> &'static str {
+        match self {
+            FlagPreset::None => "No preset",
+            FlagPreset::Aikar => "Aikar's flags",
+            FlagPreset::Zgc => "The ZGC preset",
+            FlagPreset::LowMemory => "The low memory preset",
+        }
+    }
+
+    fn collector(self) -> GarbageCollector {
+        match self {
+            FlagPreset::None => GarbageCollector::Default,
+            FlagPreset::Aikar => GarbageCollector::G1,
+            FlagPreset::Zgc => GarbageCollector::GenerationalZgc,
+            FlagPreset::LowMemory => GarbageCollector::Serial,
+        }
+    }
+
+    // Both pre-touch the heap, which only pays off when it is allocated up front
+    fn wants_fixed_heap(self) -> bool {
+        matches!(self, FlagPreset::Aikar | FlagPreset::Zgc)
+    }
+
+    // Tuning on top of the collector selection
+    fn flags(self, max_memory_mb: u32) -> Vec<String> {
+        let mut flags: Vec<&str> = match self {
+            FlagPreset::None => vec![],
+            FlagPreset::Aikar => vec![
+                "-XX:+ParallelRefProcEnabled",
+                "-XX:MaxGCPauseMillis=200",
+                "-XX:+UnlockExperimentalVMOptions",
+                "-XX:+DisableExplicitGC",
+                "-XX:+AlwaysPreTouch",
+                "-XX:G1HeapWastePercent=5",
+                "-XX:G1MixedGCCountTarget=4",
+                "-XX:G1MixedGCLiveThresholdPercent=90",
+                "-XX:G1RSetUpdatingPauseTimePercent=5",
+                "-XX:SurvivorRatio=32",
+                "-XX:+PerfDisableSharedMem",
+                "-XX:MaxTenuringThreshold=1",
+            ],
+            FlagPreset::Zgc => vec![
+                "-XX:+AlwaysPreTouch",
+                "-XX:+DisableExplicitGC",
+                "-XX:+PerfDisableSharedMem",
+            ],
+            FlagPreset::LowMemory => vec![
+                "-XX:ReservedCodeCacheSize=64m",
+                // Hand unused heap back to the OS
+                "-XX:MinHeapFreeRatio=10",
+                "-XX:MaxHeapFreeRatio=30",
+            ],
+        };
+        if self == FlagPreset::Aikar {
+            if max_memory_mb > AIKAR_LARGE_HEAP_MB {
+                flags.extend([
+                    "-XX:G1NewSizePercent=40",
+                    "-XX:G1MaxNewSizePercent=50",
+                    "-XX:G1HeapRegionSize=16M",
+                    "-XX:G1ReservePercent=15",
+                    "-XX:InitiatingHeapOccupancyPercent=20",
+                ]);
+            } else {
+                flags.extend([
+                    "-XX:G1NewSizePercent=30",
+                    "-XX:G1MaxNewSizePercent=40",
+                    "-XX:G1HeapRegionSize=8M",
+                    "-XX:G1ReservePercent=20",
+                    "-XX:InitiatingHeapOccupancyPercent=15",
+                ]);
+            }
+            flags.extend(["-Dusing.aikars.flags=https://mcflags.emc.gs", "-Daikars.new.flags=true"]);
+        }
+        flags.into_iter().map(String::from).collect()
+    }
+}
+
+// Flags the JVM refuses to start with before a given Java version
+const MIN_MAJOR_FLAGS: &[(&str, u32)] = &[
+    ("--add-opens", 9),
+    ("--add-exports", 9),
+    ("--add-modules", 9),
+    ("-XX:+UseShenandoahGC", 12),
+    ("-XX:+UseZGC", 15),
+    ("-XX:+ZGenerational", 21),
+    ("-XX:-ZGenerational", 21),
+];
+
+// Flags newer JVMs ignore with a warning
+const REMOVED_FLAGS: &[(&str, u32)] = &[
+    ("-XX:+UseParNewGC", 10),
+    ("-XX:+AggressiveOpts", 12),
+    ("-XX:+UseConcMarkSweepGC", 14),
+    ("-XX:+CMSClassUnloadingEnabled", 14),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaunchProfile {
+    pub java_path: Option<String>, // None picks the best installed Java for the server
+    pub max_memory_mb: u32,
+    pub min_memory_mb: Option<u32>, // None: the whole heap for presets that pre-touch it, 1 GB otherwise
+    pub gc: GarbageCollector, // Default leaves it to the preset
+    pub preset: FlagPreset,
+    pub extra_args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+impl Default for LaunchProfile {
+    fn default() -> Self {
+        Self {
+            java_path: None,
+            max_memory_mb: 4096,
+            min_memory_mb: None,
+            gc: GarbageCollector::Default,
+            preset: FlagPreset::None,
+            extra_args: Vec::new(),
+            env: BTreeMap::new(),
+        }
+    }
+}
+
+// Ready to spawn: `java_path` with `jvm_args`, then `-jar <jar> nogui`
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchPlan {
+    pub java_path: String,
+    pub java_major: u32,
+    pub jvm_args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub warnings: Vec<String>,
+}
+
+impl LaunchProfile {
+    /// Check the profile against the Java and the machine it runs on, and assemble the JVM arguments
+    pub fn build(&self, java_path: &str, java: &JavaValidation, total_memory_bytes: u64) -> Result<LaunchPlan, String> {
+        let major = match java.major {
+            Some(major) if java.is_valid => major,
+            _ => {
+                return Err(format!(
+                    "Java at {} is not usable: {}",
+                    java_path,
+                    java.error.as_deref().unwrap_or("unknown version")
+                ))
+            }
+        };
+        let mut warnings = Vec::new();
+
+        // Heap
+        let max_mb = self.max_memory_mb;
+        if max_mb < MIN_HEAP_MB {
+            return Err(format!("A heap of {} MB is too small, use at least {} MB", max_mb, MIN_HEAP_MB));
+        }
+        let min_mb = self.min_memory_mb.unwrap_or(if self.preset.wants_fixed_heap() {
+            max_mb
+        } else {
+            max_mb.min(DEFAULT_MIN_HEAP_MB)
+        });
+        if min_mb > max_mb {
+            return Err(format!("The initial heap ({} MB) is larger than the maximum ({} MB)", min_mb, max_mb));
+        }
+        let total_mb = total_memory_bytes / (1024 * 1024);
+        if total_mb > 0 {
+            if max_mb as u64 > total_mb {
+                return Err(format!("A heap of {} MB is more than the {} MB of memory this machine has", max_mb, total_mb));
+            }
+            if max_mb as u64 + RESERVED_SYSTEM_MEMORY_MB > total_mb {
+                warnings.push(format!(
+                    "A heap of {} MB leaves less than {} MB for the system, the server may be swapped out or killed",
+                    max_mb, RESERVED_SYSTEM_MEMORY_MB
+                ));
+            }
+        }
+        if java.arch.as_deref() == Some("x86") && max_mb > MAX_HEAP_32_BIT_MB {
+            return Err(format!("The selected Java is 32-bit and cannot use more than {} MB of heap", MAX_HEAP_32_BIT_MB));
+        }
+
+        // Garbage collector, an explicit choice has to agree with the preset's
+        let gc = match (self.gc, self.preset.collector()) {
+            (GarbageCollector::Default, preset_gc) => preset_gc,
+            (gc, GarbageCollector::Default) => gc,
+            (gc, preset_gc) if gc == preset_gc => gc,
+            (gc, preset_gc) => {
+                return Err(format!("{} is tuned for {}, not {}", self.preset.label(), preset_gc.label(), gc.label()))
+            }
+        };
+        if major < gc.min_major() {
+            return Err(format!("{} needs Java {} or newer, the selected Java is {}", gc.label(), gc.min_major(), major));
+        }
+
+        for arg in &self.extra_args {
+            let name = arg.split('=').next().unwrap_or(arg);
+            if name.starts_with("-Xmx") || name.starts_with("-Xms") || name == "-XX:MaxHeapSize" || name == "-XX:InitialHeapSize" {
+                return Err(format!("{} sets the heap, use the memory settings instead", arg));
+            }
+            if gc != GarbageCollector::Default && name.starts_with("-XX:+Use") && name.ends_with("GC") {
+                return Err(format!("{} selects a garbage collector, but the profile already uses {}", arg, gc.label()));
+            }
+            if let Some((_, min)) = MIN_MAJOR_FLAGS.iter().find(|(flag, _)| *flag == name) {
+                if major < *min {
+                    return Err(format!("{} needs Java {} or newer, the selected Java is {}", arg, min, major));
+                }
+            }
+            if let Some((_, removed)) = REMOVED_FLAGS.iter().find(|(flag, _)| *flag == name) {
+                if major >= *removed {
+                    warnings.push(format!("{} was removed in Java {} and is ignored", arg, removed));
+                }
+            }
+        }
+
+        let mut jvm_args = vec![format!("-Xms{}M", min_mb), format!("-Xmx{}M", max_mb)];
+        jvm_args.extend(gc.flags(major));
+        jvm_args.extend(self.preset.flags(max_mb));
+        jvm_args.extend(self.extra_args.iter().cloned());
+
+        Ok(LaunchPlan {
+            java_path: java_path.to_string(),
+            java_major: major,
+            jvm_args,
+            env: self.env.clone(),
+            warnings,
+        })
+    }
+}
+
+/// The saved profile of a server, with the javaSettings the server page edits applied on top
+pub fn load_profile(server_dir: &Path) -> Result<LaunchProfile, String> {
+    let meta = read_server_meta(server_dir)?;
+    let mut profile = match meta.get("launchProfile") {
+        Some(value) => serde_json::from_value(value.clone()).map_err(|e| format!("Invalid launch profile: {}", e))?,
+        None => LaunchProfile::default(),
+    };
+
+    if let Some(settings) = meta.get("javaSettings") {
+        // Memory is in GB there, in steps of 0.5
+        if let Some(memory_gb) = settings.get("memory").and_then(|v| v.as_f64()) {
+            profile.max_memory_mb = (memory_gb * 1024.0).round() as u32;
+        }
+        // "java" means pick one automatically
+        if let Some(path) = settings.get("path").and_then(|v| v.as_str()) {
+            profile.java_path = Some(path.to_string()).filter(|p| !p.is_empty() && p != "java");
+        }
+        if let Some(flags) = settings.get("flags").and_then(|v| v.as_str()) {
+            profile.extra_args = flags.split_whitespace().map(String::from).collect();
+        }
+    }
+
+    Ok(profile)
+}
+
+fn save_profile(server_dir: &Path, profile: &LaunchProfile) -> Result<(), String> {
+    let mut meta = read_server_meta(server_dir)?;
+    meta["launchProfile"] = serde_json::to_value(profile).map_err(|e| e.to_string())?;
+    meta["javaSettings"] = serde_json::json!({
+        "memory": profile.max_memory_mb as f64 / 1024.0,
+        "path": profile.java_path.as_deref().unwrap_or("java"),
+        "flags": profile.extra_args.join(" "),
+    });
+    let content = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
+    fs::write(server_dir.join("server.json"), content).map_err(|e| e.to_string())
+}
+
+// === Commands ===
+
+#[tauri::command]
+pub fn launch_profile_get_cmd(server_path: String) -> Result<LaunchProfile, String> {
+    load_profile(Path::new(&server_path))
+}
+
+#[tauri::command]
+pub fn launch_profile_save_cmd(server_path: String, profile: LaunchProfile) -> Result<(), String> {
+    save_profile(Path::new(&server_path), &profile)
+}
+
+/// Validate a profile (the saved one when none is given) and build the JVM arguments for it
+#[tauri::command]
+pub async fn launch_profile_build_cmd(
+    app: tauri::AppHandle,
+    server_path: String,
+    profile: Option<LaunchProfile>,
+) -> Result<LaunchPlan, String> {
+    let handle = tauri::async_runtime::spawn_blocking(move || {
+        let server_dir = Path::new(&server_path);
+        let profile = match profile {
+            Some(profile) => profile,
+            None => load_profile(server_dir)?,
+        };
+
+        let requirement = java_requirement::detect_requirement(server_dir);
+        let java_path = match &profile.java_path {
+            Some(path) => path.clone(),
+            None => java_requirement::best_installation(&java::detect_installations(&app), &requirement)
+                .map(|installation| installation.path)
+                .ok_or_else(|| format!("No installed Java fits this server, it needs Java {}", requirement.recommended_major))?,
+        };
+
+        let java = java::validate_java_path(&java_path);
+        let mut plan = profile.build(&java_path, &java, crate::get_system_info().total_memory_bytes)?;
+        if !requirement.accepts(plan.java_major) {
+            plan.warnings.push(format!(
+                "Minecraft {} ({}) expects Java {}, the selected Java is {}",
+                requirement.mc_version.as_deref().unwrap_or("of this server"),
+                requirement.loader,
+                match requirement.max_major {
+                    Some(max) if max != requirement.min_major => format!("{} to {}", requirement.min_major, max),
+                    Some(_) => requirement.min_major.to_string(),
+                    None => format!("{} or newer", requirement.min_major),
+                },
+                plan.java_major
+            ));
+        }
+        Ok(plan)
+    });
+
+    handle.await.map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    fn java(major: u32) -> JavaValidation {
+        JavaValidation {
+            is_valid: true,
+            version: Some(format!("{}.0.1", major)),
+            major: Some(major),
+            vendor: None,
+            arch: Some("x64".to_string()),
+            error: None,
+        }
+    }
+
+    fn profile(max_memory_mb: u32, gc: GarbageCollector, preset: FlagPreset) -> LaunchProfile {
+        LaunchProfile { max_memory_mb, gc, preset, ..LaunchProfile::default() }
+    }
+
+    fn build(profile: &LaunchProfile, major: u32) -> Result<LaunchPlan, String> {
+        profile.build("/opt/java/bin/java", &java(major), 32 * GB)
+    }
+
+    #[test]
+    fn generational_zgc_needs_java_21() {
+        let zgc = profile(8192, GarbageCollector::Default, FlagPreset::Zgc);
+        let error = build(&zgc, 17).unwrap_err();
+        assert_eq!(error, "Generational ZGC needs Java 21 or newer, the selected Java is 17");
+
+        let plan = build(&zgc, 21).unwrap();
+        assert!(plan.jvm_args.contains(&"-XX:+ZGenerational".to_string()));
+        // The only mode from Java 23 on, where the flag is deprecated
+        let plan = build(&zgc, 23).unwrap();
+        assert!(plan.jvm_args.contains(&"-XX:+UseZGC".to_string()));
+        assert!(!plan.jvm_args.contains(&"-XX:+ZGenerational".to_string()));
+
+        // Non-generational ZGC is fine on 17
+        let plan = build(&profile(8192, GarbageCollector::Zgc, FlagPreset::None), 17).unwrap();
+        assert!(plan.jvm_args.contains(&"-XX:+UseZGC".to_string()));
+    }
+
+    #[test]
+    fn extra_args_are_checked_against_the_java_version() {
+        let mut p = profile(4096, GarbageCollector::Default, FlagPreset::None);
+        p.extra_args = vec!["-XX:+UseZGC".to_string(), "-XX:+ZGenerational".to_string()];
+        let error = build(&p, 17).unwrap_err();
+        assert_eq!(error, "-XX:+ZGenerational needs Java 21 or newer, the selected Java is 17");
+        assert!(build(&p, 21).is_ok());
+
+        p.extra_args = vec!["--add-opens=java.base/java.lang=ALL-UNNAMED".to_string()];
+        assert!(build(&p, 8).unwrap_err().starts_with("--add-opens"));
+
+        p.extra_args = vec!["-XX:+UseConcMarkSweepGC".to_string()];
+        assert_eq!(build(&p, 11).unwrap().warnings, Vec::<String>::new());
+        assert_eq!(
+            build(&p, 17).unwrap().warnings,
+            vec!["-XX:+UseConcMarkSweepGC was removed in Java 14 and is ignored".to_string()]
+        );
+    }
+
+    #[test]
+    fn extra_args_cannot_override_heap_or_collector() {
+        let mut p = profile(4096, GarbageCollector::G1, FlagPreset::None);
+        p.extra_args = vec!["-Xmx8G".to_string()];
+        assert_eq!(build(&p, 17).unwrap_err(), "-Xmx8G sets the heap, use the memory settings instead");
+        p.extra_args = vec!["-XX:MaxHeapSize=8g".to_string()];
+        assert!(build(&p, 17).is_err());
+        p.extra_args = vec!["-XX:+UseParallelGC".to_string()];
+        assert_eq!(
+            build(&p, 17).unwrap_err(),
+            "-XX:+UseParallelGC selects a garbage collector, but the profile already uses G1"
+        );
+        // Without a collector of its own the profile leaves the choice to the flags
+        p.gc = GarbageCollector::Default;
+        assert!(build(&p, 17).is_ok());
+    }
+
+    #[test]
+    fn heap_is_checked_against_system_memory() {
+        let p = profile(16384, GarbageCollector::Default, FlagPreset::None);
+        assert_eq!(
+            p.build("java", &java(21), 8 * GB).unwrap_err(),
+            "A heap of 16384 MB is more than the 8192 MB of memory this machine has"
+        );
+        let plan = p.build("java", &java(21), 16 * GB + 512 * 1024 * 1024).unwrap();
+        assert_eq!(plan.warnings.len(), 1);
+        assert!(plan.warnings[0].contains("leaves less than 1024 MB"));
+        assert!(p.build("java", &java(21), 32 * GB).unwrap().warnings.is_empty());
+        // Unknown total memory skips the check
+        assert!(p.build("java", &java(21), 0).is_ok());
+    }
+
+    #[test]
+    fn heap_bounds() {
+        let small = profile(256, GarbageCollector::Default, FlagPreset::None);
+        assert_eq!(build(&small, 17).unwrap_err(), "A heap of 256 MB is too small, use at least 512 MB");
+
+        let mut p = profile(2048, GarbageCollector::Default, FlagPreset::None);
+        p.min_memory_mb = Some(4096);
+        assert!(build(&p, 17).unwrap_err().starts_with("The initial heap (4096 MB)"));
+
+        let mut java32 = java(8);
+        java32.arch = Some("x86".to_string());
+        let p = profile(2048, GarbageCollector::Default, FlagPreset::None);
+        assert!(p.build("java", &java32, 4 * GB).unwrap_err().contains("32-bit"));
+    }
+
+    #[test]
+    fn initial_heap_follows_the_preset() {
+        let plan = build(&profile(6144, GarbageCollector::Default, FlagPreset::None), 17).unwrap();
+        assert_eq!(plan.jvm_args[..2], ["-Xms1024M".to_string(), "-Xmx6144M".to_string()]);
+        let plan = build(&profile(6144, GarbageCollector::Default, FlagPreset::Aikar), 17).unwrap();
+        assert_eq!(plan.jvm_args[..2], ["-Xms6144M".to_string(), "-Xmx6144M".to_string()]);
+        let plan = build(&profile(768, GarbageCollector::Default, FlagPreset::LowMemory), 8).unwrap();
+        assert_eq!(plan.jvm_args[..3], ["-Xms768M".to_string(), "-Xmx768M".to_string(), "-XX:+UseSerialGC".to_string()]);
+    }
+
+    #[test]
+    fn aikar_flags_scale_with_the_heap() {
+        let plan = build(&profile(8192, GarbageCollector::G1, FlagPreset::Aikar), 17).unwrap();
+        assert!(plan.jvm_args.contains(&"-XX:+UseG1GC".to_string()));
+        assert!(plan.jvm_args.contains(&"-XX:G1HeapRegionSize=8M".to_string()));
+        let plan = build(&profile(16384, GarbageCollector::Default, FlagPreset::Aikar), 17).unwrap();
+        assert!(plan.jvm_args.contains(&"-XX:G1HeapRegionSize=16M".to_string()));
+        assert!(plan.jvm_args.contains(&"-XX:G1NewSizePercent=40".to_string()));
+    }
+
+    #[test]
+    fn collector_has_to_match_the_preset() {
+        let p = profile(4096, GarbageCollector::Shenandoah, FlagPreset::Aikar);
+        assert_eq!(build(&p, 17).unwrap_err(), "Aikar's flags is tuned for G1, not Shenandoah");
+        let p = profile(4096, GarbageCollector::Shenandoah, FlagPreset::None);
+        assert!(build(&p, 11).unwrap_err().starts_with("Shenandoah needs Java 12"));
+    }
+
+    #[test]
+    fn unusable_java_is_rejected() {
+        let broken = JavaValidation { is_valid: false, major: None, error: Some("not found".to_string()), ..java(17) };
+        let p = LaunchProfile::default();
+        assert_eq!(p.build("/missing/java", &broken, 8 * GB).unwrap_err(), "Java at /missing/java is not usable: not found");
+    }
+
+    #[test]
+    fn java_settings_override_the_saved_profile() {
+        let dir = std::env::temp_dir().join(format!("voidlink-launch-profile-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let saved = profile(2048, GarbageCollector::Default, FlagPreset::Aikar);
+        fs::write(dir.join("server.json"), r#"{"name": "Test"}"#).unwrap();
+        save_profile(&dir, &saved).unwrap();
+
+        let mut meta: serde_json::Value = serde_json::from_str(&fs::read_to_string(dir.join("server.json")).unwrap()).unwrap();
+        assert_eq!(meta["name"], "Test");
+        assert_eq!(meta["javaSettings"]["memory"], 2.0);
+        assert_eq!(meta["javaSettings"]["path"], "java");
+        meta["javaSettings"] = serde_json::json!({ "memory": 6.5, "path": "/opt/jdk-21/bin/java", "flags": "-Dfoo=1  -Dbar=2" });
+        fs::write(dir.join("server.json"), meta.to_string()).unwrap();
+
+        let loaded = load_profile(&dir).unwrap();
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(loaded.preset, FlagPreset::Aikar);
+        assert_eq!(loaded.max_memory_mb, 6656);
+        assert_eq!(loaded.java_path.as_deref(), Some("/opt/jdk-21/bin/java"));
+        assert_eq!(loaded.extra_args, vec!["-Dfoo=1".to_string(), "-Dbar=2".to_string()]);
+    }
+}
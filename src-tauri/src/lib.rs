@@ -3,11 +3,31 @@ mod rcon;
 mod utils;
 mod logs;
 mod java;
+mod java_distribution;
+mod java_requirement;
+mod java_runtime;
+mod launch_profile;
 mod backup;
+mod backup_snapshot;
+mod backup_restore;
+mod backup_target;
+mod backup_scheduler;
+mod backup_verify;
+mod backup_crypto;
+mod backup_retention;
+mod backup_filter;
+mod backup_archive;
+mod server;
+mod restart;
+mod jobs;
+mod metrics;
+mod metrics_store;
+mod server_bundle;
+mod server_perf;
 
 use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
-use sysinfo::{Pid, ProcessesToUpdate, System};
+use sysinfo::System;
 use local_ip_address::local_ip;
 use tauri::{
     menu::{Menu, MenuItem},
@@ -48,166 +68,21 @@ pub struct TrayServer {
 }
 
 #[tauri::command]
-fn get_process_info(pid: u32) -> Option<ProcessInfo> {
-    let mut sys = System::new();
-    sys.refresh_processes(ProcessesToUpdate::All, true);
-    
-    let target_pid = Pid::from_u32(pid);
-    
-    // Check if target exists
-    if sys.process(target_pid).is_none() {
-        return None;
-    }
-    
-    // Windows: Find the actual Java child process (the shell wrapper doesn't use CPU)
-    #[cfg(target_os = "windows")]
-    let cpu_pid = find_java_child(&sys, target_pid).unwrap_or(pid);
-    #[cfg(not(target_os = "windows"))]
-    let cpu_pid = pid;
-    
-    // Get memory - sum child processes on Windows
-    #[cfg(target_os = "windows")]
-    let mem = {
-        use std::collections::HashSet;
-        let mut visited = HashSet::new();
+fn get_process_info(pid: u32, server_id: String) -> Option<ProcessInfo> {
+    // Sampled in the background (see metrics.rs), so this never waits for a CPU measurement
+    metrics::track(&server_id, pid);
 
-        fn sum_memory(sys: &System, pid: Pid, visited: &mut HashSet<Pid>) -> u64 {
-            if !visited.insert(pid) {
-                return 0;
-            }
-            let mut mem = 0;
-            if let Some(proc) = sys.process(pid) {
-                mem += proc.memory();
-            }
-            for (child_pid, child_proc) in sys.processes() {
-                if let Some(parent) = child_proc.parent() {
-                    if parent == pid {
-                        mem += sum_memory(sys, *child_pid, visited);
-                    }
-                }
-            }
-            mem
-        }
-        sum_memory(&sys, target_pid, &mut visited)
-    };
-    
-    // Linux/macOS: Only main process (avoid shared lib double-counting)
-    #[cfg(not(target_os = "windows"))]
-    let mem = match sys.process(target_pid) {
-        Some(proc) => proc.memory(),
-        None => return None,
-    };
-    
-    // CPU: Platform-specific measurement
-    let cpu = get_process_cpu(cpu_pid);
-    
-    log::info!("Process {} (cpu_pid={}): {} bytes, {}% CPU", pid, cpu_pid, mem, cpu);
-    
-    Some(ProcessInfo {
-        memory_bytes: mem,
-        cpu_usage: cpu,
-    })
-}
-
-// Find Java child process (recursive search) - Windows only
-#[cfg(target_os = "windows")]
-fn find_java_child(sys: &System, parent_pid: Pid) -> Option<u32> {
-    for (child_pid, child_proc) in sys.processes() {
-        if let Some(parent) = child_proc.parent() {
-            if parent == parent_pid {
-                let name = child_proc.name().to_string_lossy().to_lowercase();
-                if name.contains("java") || name.contains("openjdk") {
-                    log::info!("Found Java child: PID {} ({})", child_pid.as_u32(), name);
-                    return Some(child_pid.as_u32());
-                }
-                // Recursively check children
-                if let Some(java_pid) = find_java_child(sys, *child_pid) {
-                    return Some(java_pid);
-                }
-            }
-        }
-    }
-    None
-}
-
-#[cfg(target_os = "windows")]
-fn get_process_cpu(pid: u32) -> f32 {
-    use windows::Win32::System::Threading::{
-        OpenProcess, GetProcessTimes, PROCESS_QUERY_INFORMATION,
-    };
-    use windows::Win32::Foundation::{CloseHandle, FILETIME};
-    
-    unsafe {
-        let handle = match OpenProcess(PROCESS_QUERY_INFORMATION, false, pid) {
-            Ok(h) => h,
-            Err(e) => {
-                log::warn!("Failed to open process {}: {:?}", pid, e);
-                return 0.0;
-            }
-        };
-        
-        let mut creation = FILETIME::default();
-        let mut exit = FILETIME::default();
-        let mut kernel1 = FILETIME::default();
-        let mut user1 = FILETIME::default();
-        
-        if GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel1, &mut user1).is_err() {
-            log::warn!("GetProcessTimes failed for PID {}", pid);
-            let _ = CloseHandle(handle);
-            return 0.0;
-        }
-        
-        let time1 = std::time::Instant::now();
-        std::thread::sleep(std::time::Duration::from_millis(200));
-        
-        let mut kernel2 = FILETIME::default();
-        let mut user2 = FILETIME::default();
-        
-        if GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel2, &mut user2).is_err() {
-            let _ = CloseHandle(handle);
-            return 0.0;
-        }
-        
-        let elapsed = time1.elapsed().as_nanos() as u64 / 100;
-        let _ = CloseHandle(handle);
-        
-        let kernel_diff = filetime_to_u64(kernel2).saturating_sub(filetime_to_u64(kernel1));
-        let user_diff = filetime_to_u64(user2).saturating_sub(filetime_to_u64(user1));
-        let cpu_time = kernel_diff + user_diff;
-        
-        if elapsed > 0 {
-            let raw = (cpu_time as f32 / elapsed as f32) * 100.0;
-            let cpu_count = std::thread::available_parallelism()
-                .map(|n| n.get() as f32)
-                .unwrap_or(1.0);
-            let normalized = raw / cpu_count;
-            log::info!("PID {} CPU: raw={}%, cores={}, normalized={}%", pid, raw, cpu_count, normalized);
-            normalized
-        } else {
-            0.0
-        }
-    }
-}
-
-#[cfg(target_os = "windows")]
-fn filetime_to_u64(ft: windows::Win32::Foundation::FILETIME) -> u64 {
-    ((ft.dwHighDateTime as u64) << 32) | (ft.dwLowDateTime as u64)
-}
-
-#[cfg(not(target_os = "windows"))]
-fn get_process_cpu(pid: u32) -> f32 {
-    let mut sys = System::new();
-    sys.refresh_processes(ProcessesToUpdate::All, true);
-    std::thread::sleep(std::time::Duration::from_millis(200));
-    sys.refresh_processes(ProcessesToUpdate::All, true);
-    
-    let target_pid = Pid::from_u32(pid);
-    if let Some(proc) = sys.process(target_pid) {
-        let cpu = proc.cpu_usage();
-        let cpu_count = sys.cpus().len() as f32;
-        if cpu_count > 0.0 { cpu / cpu_count } else { cpu }
-    } else {
-        0.0
+    match metrics::latest(&server_id, pid) {
+        Some(sample) => Some(ProcessInfo {
+            memory_bytes: sample.memory_bytes,
+            cpu_usage: sample.cpu_usage,
+        }),
+        // Not sampled yet
+        None if server::is_pid_alive(pid) => Some(ProcessInfo {
+            memory_bytes: 0,
+            cpu_usage: 0.0,
+        }),
+        None => None,
     }
 }
 
@@ -275,38 +150,10 @@ fn update_tray_servers(servers: Vec<TrayServer>) -> Result<(), String> {
     Ok(())
 }
 
-#[tauri::command]
-fn kill_process(pid: u32) -> bool {
-    #[cfg(windows)]
-    {
-        // Use /T to kill the entire process tree (important for Java which spawns child processes)
-        let result = std::process::Command::new("taskkill")
-            .args(["/F", "/T", "/PID", &pid.to_string()])
-            .output();
-        
-        match result {
-            Ok(output) => {
-                log::info!("Killed process tree for PID {}: {:?}", pid, output.status.success());
-                output.status.success()
-            }
-            Err(e) => {
-                log::error!("Failed to kill process {}: {}", pid, e);
-                false
-            }
-        }
-    }
-    #[cfg(not(windows))]
-    {
-        let result = std::process::Command::new("kill")
-            .args(["-9", &pid.to_string()])
-            .output();
-        result.map(|o| o.status.success()).unwrap_or(false)
-    }
-}
-
 #[tauri::command]
 fn quit_app() {
     frp::stop_all_tunnels();
+    server::stop_all_servers();
     std::process::exit(0);
 }
 
@@ -314,6 +161,8 @@ fn quit_app() {
 fn cleanup_and_quit(app: tauri::AppHandle) {
     // Stop all FRP tunnels
     frp::stop_all_tunnels();
+    // Ask running servers to shut down
+    server::stop_all_servers();
     // Exit the app
     app.exit(0);
 }
@@ -347,6 +196,7 @@ pub fn run() {
                     
                     if id == "quit" {
                         frp::stop_all_tunnels();
+                        server::stop_all_servers();
                         app.exit(0);
                     } else if id == "show" {
                         if let Some(window) = app.get_webview_window("main") {
@@ -380,6 +230,13 @@ pub fn run() {
 
             #[cfg(desktop)]
             let _ = app.handle().plugin(tauri_plugin_updater::Builder::new().build());
+
+            // Automatic backups run in the backend so they keep going while the window is hidden
+            backup_scheduler::start(app.handle().clone());
+            // Resource usage of running servers, pushed as "server-metrics" events
+            metrics::start(app.handle().clone());
+            // In-game TPS/MSPT over RCON, pushed as "server-performance" events
+            server_perf::start(app.handle().clone());
             
             Ok(())
         })
@@ -407,15 +264,37 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             get_process_info,
             get_system_info,
+            metrics::metrics_history_cmd,
+            metrics_store::metrics_query_cmd,
+            metrics_store::metrics_report_game_cmd,
             get_local_ip,
             update_tray_servers,
-            kill_process,
             quit_app,
             cleanup_and_quit,
             // FRP Tunnel commands
             frp::frp_start_tunnel,
             frp::frp_stop_tunnel,
             frp::frp_get_status,
+            // Server process commands
+            server::server_start,
+            server::server_send_input,
+            server::server_stop,
+            server::server_kill,
+            server::server_set_restart_policy,
+            server::server_get_status,
+            server::server_list_running,
+            server::server_get_console,
+            // In-game performance commands
+            server_perf::server_perf_register,
+            server_perf::server_perf_unregister,
+            server_perf::server_perf_latest,
+            // Server bundle commands
+            server_bundle::server_export_cmd,
+            server_bundle::server_import_cmd,
+            server_bundle::server_bundle_inspect_cmd,
+            // Job commands
+            jobs::job_cancel,
+            jobs::job_list,
             // RCON commands
             rcon::rcon_send_command,
             rcon::rcon_stop_server,
@@ -423,8 +302,16 @@ pub fn run() {
             // Java commands
             java::detect_java_installations_cmd,
             java::validate_java_path_cmd,
-            java::fetch_adoptium_release_cmd,
+            java::fetch_java_release_cmd,
             java::download_java_cmd,
+            java_requirement::java_resolve_cmd,
+            java_runtime::java_runtime_list_cmd,
+            java_runtime::java_runtime_update_cmd,
+            java_runtime::java_runtime_remove_cmd,
+            // Launch profile commands
+            launch_profile::launch_profile_get_cmd,
+            launch_profile::launch_profile_save_cmd,
+            launch_profile::launch_profile_build_cmd,
             // Logs commands
             logs::list_crash_reports_cmd,
             logs::read_crash_report_cmd,
@@ -434,15 +321,31 @@ pub fn run() {
             backup::backup_restore_cmd,
             backup::backup_delete_cmd,
             backup::backup_rotate_cmd,
+            backup::backup_set_pinned_cmd,
             backup::backup_stats_cmd,
             backup::backup_get_folders_cmd,
+            backup_filter::backup_preview_cmd,
             backup::backup_load_settings_cmd,
             backup::backup_save_settings_cmd,
+            backup_verify::backup_verify_cmd,
+            backup::backup_remote_list_cmd,
+            backup_target::backup_target_test_cmd,
+            backup_crypto::backup_key_set_cmd,
+            backup_crypto::backup_key_status_cmd,
+            backup_crypto::backup_key_generate_file_cmd,
+            backup_restore::backup_browse_cmd,
+            backup_restore::backup_restore_selective_cmd,
+            backup_scheduler::backup_schedule_status,
         ])
         .on_window_event(|_window, event| {
-            if let tauri::WindowEvent::CloseRequested { .. } = event {
-                // Cleanup all FRP tunnels when window closes
-                frp::stop_all_tunnels();
+            match event {
+                tauri::WindowEvent::CloseRequested { .. } => {
+                    // Cleanup all FRP tunnels when window closes
+                    frp::stop_all_tunnels();
+                }
+                // Closing to the tray only hides the window, servers are stopped once it is really gone
+                tauri::WindowEvent::Destroyed => server::stop_all_servers(),
+                _ => {}
             }
         })
         .run(tauri::generate_context!())
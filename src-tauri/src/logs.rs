@@ -27,7 +27,9 @@ pub fn list_crash_reports_cmd(server_path: String) -> Result<Vec<CrashReport>, S
             let path = entry.path();
             if path.is_file() {
                 if let Ok(metadata) = path.metadata() {
-                    let created = metadata.created().unwrap_or(std::time::SystemTime::UNIX_EPOCH)
+                    // Not every filesystem records creation time, fall back to modification time
+                    let created = metadata.created().or_else(|_| metadata.modified())
+                        .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
                         .duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
                         
                     reports.push(CrashReport {
@@ -51,3 +53,11 @@ pub fn list_crash_reports_cmd(server_path: String) -> Result<Vec<CrashReport>, S
 pub fn read_crash_report_cmd(path: String) -> Result<String, String> {
     fs::read_to_string(path).map_err(|e| e.to_string())
 }
+
+/// Find the newest crash report written at or after `since` (unix seconds)
+pub fn find_crash_report_since(server_path: &str, since: u64) -> Option<CrashReport> {
+    list_crash_reports_cmd(server_path.to_string())
+        .ok()?
+        .into_iter()
+        .find(|report| report.created >= since)
+}
@@ -0,0 +1,242 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, System};
+use tauri::Emitter;
+
+use crate::metrics_store;
+
+// One background sampler for every tracked server process. A single System is kept between
+// ticks so CPU usage is measured over the whole interval instead of a sleep inside each call.
+
+const SAMPLE_INTERVAL_SECS: u64 = 2;
+// 30 minutes of samples per server
+const HISTORY_SAMPLES: usize = 900;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricSample {
+    pub timestamp: i64, // unix millis
+    pub cpu_usage: f32, // percent of the whole machine
+    pub memory_bytes: u64,
+    pub threads: Option<u32>, // not reported on every OS
+    pub open_files: Option<u32>,
+    pub disk_read_bytes_per_sec: u64,
+    pub disk_write_bytes_per_sec: u64,
+}
+
+struct TrackedServer {
+    pid: Option<u32>, // None once the process is gone, the history is kept
+    // Process actually measured, the Java child of the cmd wrapper on Windows
+    metrics_pid: Option<u32>,
+    // CPU and disk rates need a previous refresh of the same process
+    primed: bool,
+    history: VecDeque<MetricSample>,
+}
+
+struct Sampler {
+    system: System,
+    last_refresh: Option<Instant>,
+}
+
+static TRACKED: LazyLock<Mutex<HashMap<String, TrackedServer>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static SAMPLER: LazyLock<Mutex<Sampler>> = LazyLock::new(|| Mutex::new(Sampler {
+    system: System::new(),
+    last_refresh: None,
+}));
+
+pub fn start(app: tauri::AppHandle) {
+    metrics_store::init(&app);
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(SAMPLE_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let app = app.clone();
+            let _ = tauri::async_runtime::spawn_blocking(move || sample_all(&app)).await;
+        }
+    });
+}
+
+/// Start sampling a server's process; a new PID (after a restart) continues the same history
+pub fn track(server_id: &str, pid: u32) {
+    let Ok(mut tracked) = TRACKED.lock() else { return };
+    let server = tracked.entry(server_id.to_string()).or_insert_with(|| TrackedServer {
+        pid: None,
+        metrics_pid: None,
+        primed: false,
+        history: VecDeque::new(),
+    });
+    if server.pid != Some(pid) {
+        server.pid = Some(pid);
+        server.metrics_pid = None;
+        server.primed = false;
+    }
+}
+
+/// Latest sample of a server's current process, if it was taken in the last few ticks
+pub fn latest(server_id: &str, pid: u32) -> Option<MetricSample> {
+    let tracked = TRACKED.lock().ok()?;
+    let server = tracked.get(server_id).filter(|s| s.pid == Some(pid))?;
+    let oldest = chrono::Local::now().timestamp_millis() - 3 * SAMPLE_INTERVAL_SECS as i64 * 1000;
+    server.history.back().filter(|s| s.timestamp >= oldest).cloned()
+}
+
+fn sample_all(app: &tauri::AppHandle) {
+    let targets: Vec<(String, u32, Option<u32>)> = match TRACKED.lock() {
+        Ok(tracked) => tracked.iter()
+            .filter_map(|(id, server)| server.pid.map(|pid| (id.clone(), pid, server.metrics_pid)))
+            .collect(),
+        Err(_) => return,
+    };
+    if targets.is_empty() {
+        return;
+    }
+
+    let Ok(mut sampler) = SAMPLER.lock() else { return };
+    let sampler = &mut *sampler;
+
+    #[cfg(target_os = "windows")]
+    let targets: Vec<(String, u32, Option<u32>)> = {
+        // The cmd wrapper starts Java later, look it up until it is there
+        if targets.iter().any(|(_, _, metrics_pid)| metrics_pid.is_none()) {
+            sampler.system.refresh_processes(ProcessesToUpdate::All, true);
+        }
+        targets.into_iter()
+            .map(|(id, pid, metrics_pid)| {
+                let metrics_pid = metrics_pid
+                    .or_else(|| find_java_child(&sampler.system, Pid::from_u32(pid)))
+                    // Started without the wrapper, the tracked process is Java itself
+                    .or_else(|| {
+                        let process = sampler.system.process(Pid::from_u32(pid))?;
+                        is_java(process).then_some(pid)
+                    });
+                (id, pid, metrics_pid)
+            })
+            .collect()
+    };
+
+    let pids: Vec<Pid> = targets.iter()
+        .flat_map(|(_, pid, metrics_pid)| [Some(*pid), *metrics_pid])
+        .flatten()
+        .map(Pid::from_u32)
+        .collect();
+    sampler.system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&pids),
+        true,
+        ProcessRefreshKind::new().with_cpu().with_memory().with_disk_usage(),
+    );
+    let elapsed_secs = sampler.last_refresh.map(|t| t.elapsed().as_secs_f64()).unwrap_or(0.0);
+    sampler.last_refresh = Some(Instant::now());
+    let cpu_count = std::thread::available_parallelism().map(|n| n.get() as f32).unwrap_or(1.0);
+    let now = chrono::Local::now().timestamp_millis();
+
+    let Ok(mut tracked) = TRACKED.lock() else { return };
+    for (server_id, pid, metrics_pid) in targets {
+        let Some(server) = tracked.get_mut(&server_id) else { continue };
+        // Tracking moved to a new process while sampling
+        if server.pid != Some(pid) {
+            continue;
+        }
+
+        let alive = sampler.system.process(Pid::from_u32(pid))
+            .is_some_and(|p| !matches!(p.status(), ProcessStatus::Zombie | ProcessStatus::Dead));
+        if !alive {
+            server.pid = None;
+            server.metrics_pid = None;
+            metrics_store::flush(&server_id);
+            let _ = app.emit("server-metrics-stopped", serde_json::json!({ "server_id": server_id }));
+            continue;
+        }
+        if server.metrics_pid != metrics_pid {
+            server.metrics_pid = metrics_pid;
+            server.primed = false;
+        }
+
+        let measured = metrics_pid.unwrap_or(pid);
+        let Some(process) = sampler.system.process(Pid::from_u32(measured)) else { continue };
+        if !server.primed || elapsed_secs <= 0.0 {
+            server.primed = true;
+            continue;
+        }
+
+        let disk = process.disk_usage();
+        let sample = MetricSample {
+            timestamp: now,
+            cpu_usage: process.cpu_usage() / cpu_count,
+            memory_bytes: process.memory(),
+            threads: thread_count(measured),
+            open_files: open_file_count(measured),
+            disk_read_bytes_per_sec: (disk.read_bytes as f64 / elapsed_secs) as u64,
+            disk_write_bytes_per_sec: (disk.written_bytes as f64 / elapsed_secs) as u64,
+        };
+        if server.history.len() >= HISTORY_SAMPLES {
+            server.history.pop_front();
+        }
+        server.history.push_back(sample.clone());
+        metrics_store::record(&server_id, &sample);
+        let _ = app.emit("server-metrics", serde_json::json!({
+            "server_id": server_id,
+            "sample": sample
+        }));
+    }
+}
+
+// Find Java child process (recursive search) - Windows only
+#[cfg(target_os = "windows")]
+fn find_java_child(sys: &System, parent_pid: Pid) -> Option<u32> {
+    for (child_pid, child_proc) in sys.processes() {
+        if let Some(parent) = child_proc.parent() {
+            if parent == parent_pid {
+                if is_java(child_proc) {
+                    log::info!("Found Java child: PID {} ({})", child_pid.as_u32(), child_proc.name().to_string_lossy());
+                    return Some(child_pid.as_u32());
+                }
+                // Recursively check children
+                if let Some(java_pid) = find_java_child(sys, *child_pid) {
+                    return Some(java_pid);
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(target_os = "windows")]
+fn is_java(process: &sysinfo::Process) -> bool {
+    let name = process.name().to_string_lossy().to_lowercase();
+    name.contains("java") || name.contains("openjdk")
+}
+
+#[cfg(target_os = "linux")]
+fn thread_count(pid: u32) -> Option<u32> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    status.lines().find_map(|line| line.strip_prefix("Threads:"))?.trim().parse().ok()
+}
+
+#[cfg(target_os = "linux")]
+fn open_file_count(pid: u32) -> Option<u32> {
+    Some(std::fs::read_dir(format!("/proc/{}/fd", pid)).ok()?.count() as u32)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn thread_count(_pid: u32) -> Option<u32> {
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_file_count(_pid: u32) -> Option<u32> {
+    None
+}
+
+// === Commands ===
+
+/// Samples of a server from the last `minutes` (everything kept when not given)
+#[tauri::command]
+pub fn metrics_history_cmd(server_id: String, minutes: Option<u32>) -> Result<Vec<MetricSample>, String> {
+    let tracked = TRACKED.lock().map_err(|e| e.to_string())?;
+    let Some(server) = tracked.get(&server_id) else { return Ok(Vec::new()) };
+    let since = minutes
+        .map(|m| chrono::Local::now().timestamp_millis() - m as i64 * 60_000)
+        .unwrap_or(i64::MIN);
+    Ok(server.history.iter().filter(|s| s.timestamp >= since).cloned().collect())
+}
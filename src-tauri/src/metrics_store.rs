@@ -0,0 +1,501 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::metrics::MetricSample;
+
+// Long-term per-server metrics in the app data dir, one JSON line per point:
+//   metrics/<server>/raw.jsonl    every sample of the sampler (see metrics.rs), kept for an hour
+//   metrics/<server>/1m.jsonl     one-minute averages, kept for two days
+//   metrics/<server>/15m.jsonl    15-minute averages, kept for 30 days
+// Buckets still being filled live in memory and are written once they close or the server
+// stops, so a restart of the app loses at most the current minute and quarter hour. A server
+// restarted within a bucket writes it twice; queries merge points with the same timestamp.
+
+const MINUTE_MS: i64 = 60_000;
+const COMPACT_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// Players and TPS are reported separately and only count while this recent
+const GAME_STATS_MAX_AGE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+    Raw,
+    Minute,
+    Quarter,
+}
+
+impl Resolution {
+    const ALL: [Resolution; 3] = [Resolution::Raw, Resolution::Minute, Resolution::Quarter];
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Resolution::Raw => "raw.jsonl",
+            Resolution::Minute => "1m.jsonl",
+            Resolution::Quarter => "15m.jsonl",
+        }
+    }
+
+    fn retention_ms(self) -> i64 {
+        match self {
+            Resolution::Raw => 60 * MINUTE_MS,
+            Resolution::Minute => 2 * 24 * 60 * MINUTE_MS,
+            Resolution::Quarter => 30 * 24 * 60 * MINUTE_MS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricPoint {
+    pub timestamp: i64, // unix millis, start of the bucket for aggregated points
+    pub cpu_avg: f32,
+    pub cpu_max: f32,
+    pub memory_avg: u64,
+    pub memory_max: u64,
+    #[serde(default)]
+    pub tps: Option<f32>, // average
+    #[serde(default)]
+    pub players: Option<u32>, // peak
+    pub samples: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MetricSeries {
+    pub server_id: String,
+    pub resolution: Resolution,
+    pub points: Vec<MetricPoint>,
+}
+
+// Running totals of a bucket that is still open
+#[derive(Default)]
+struct Bucket {
+    start: i64,
+    cpu_sum: f64,
+    cpu_max: f32,
+    memory_sum: u128,
+    memory_max: u64,
+    tps_sum: f64,
+    tps_count: u32,
+    players_max: Option<u32>,
+    samples: u32,
+}
+
+impl Bucket {
+    // Weighted by the samples already behind the point, 1 for raw samples
+    fn add(&mut self, point: &MetricPoint) {
+        let weight = point.samples.max(1);
+        self.cpu_sum += point.cpu_avg as f64 * weight as f64;
+        self.cpu_max = self.cpu_max.max(point.cpu_max);
+        self.memory_sum += point.memory_avg as u128 * weight as u128;
+        self.memory_max = self.memory_max.max(point.memory_max);
+        if let Some(tps) = point.tps {
+            self.tps_sum += tps as f64 * weight as f64;
+            self.tps_count += weight;
+        }
+        if let Some(players) = point.players {
+            self.players_max = Some(self.players_max.unwrap_or(0).max(players));
+        }
+        self.samples += weight;
+    }
+
+    fn point(&self) -> MetricPoint {
+        let samples = self.samples.max(1);
+        MetricPoint {
+            timestamp: self.start,
+            cpu_avg: (self.cpu_sum / samples as f64) as f32,
+            cpu_max: self.cpu_max,
+            memory_avg: (self.memory_sum / samples as u128) as u64,
+            memory_max: self.memory_max,
+            tps: (self.tps_count > 0).then(|| (self.tps_sum / self.tps_count as f64) as f32),
+            players: self.players_max,
+            samples: self.samples,
+        }
+    }
+}
+
+struct GameStats {
+    tps: Option<f32>,
+    players: Option<u32>,
+    updated: Instant,
+}
+
+#[derive(Default)]
+struct SeriesState {
+    minute: Option<Bucket>,
+    quarter: Option<Bucket>,
+}
+
+static STORE_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+// Also held while a server's files are written or compacted
+static SERIES: LazyLock<Mutex<HashMap<String, SeriesState>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static GAME_STATS: LazyLock<Mutex<HashMap<String, GameStats>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub fn init(app: &tauri::AppHandle) {
+    match app.path().app_data_dir() {
+        Ok(dir) => {
+            if let Ok(mut store_dir) = STORE_DIR.lock() {
+                *store_dir = Some(dir.join("metrics"));
+            }
+        }
+        Err(e) => {
+            log::warn!("Metrics will not be stored: {}", e);
+            return;
+        }
+    }
+
+    // Retention also applies to servers that are not running
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(COMPACT_INTERVAL);
+        loop {
+            interval.tick().await;
+            let _ = tauri::async_runtime::spawn_blocking(compact_all).await;
+        }
+    });
+}
+
+fn series_dir(server_id: &str) -> Option<PathBuf> {
+    // Server ids are folder names or generated ids, keep them to one safe path segment
+    let safe: String = server_id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    STORE_DIR.lock().ok()?.as_ref().map(|dir| dir.join(safe))
+}
+
+/// Remember the latest TPS and player count of a server, stored with its next samples
+pub fn report_game_stats(server_id: &str, tps: Option<f32>, players: Option<u32>) {
+    let Ok(mut stats) = GAME_STATS.lock() else { return };
+    let entry = stats.entry(server_id.to_string()).or_insert(GameStats { tps: None, players: None, updated: Instant::now() });
+    // Either can be reported on its own, the other one stays until it is too old
+    if entry.updated.elapsed() > GAME_STATS_MAX_AGE {
+        entry.tps = None;
+        entry.players = None;
+    }
+    entry.tps = tps.or(entry.tps);
+    entry.players = players.or(entry.players);
+    entry.updated = Instant::now();
+}
+
+/// Store a sample and roll it into the minute and quarter-hour buckets
+pub fn record(server_id: &str, sample: &MetricSample) {
+    let Some(dir) = series_dir(server_id) else { return };
+    let (tps, players) = GAME_STATS.lock().ok()
+        .and_then(|stats| stats.get(server_id)
+            .filter(|s| s.updated.elapsed() <= GAME_STATS_MAX_AGE)
+            .map(|s| (s.tps, s.players)))
+        .unwrap_or_default();
+    let point = MetricPoint {
+        timestamp: sample.timestamp,
+        cpu_avg: sample.cpu_usage,
+        cpu_max: sample.cpu_usage,
+        memory_avg: sample.memory_bytes,
+        memory_max: sample.memory_bytes,
+        tps,
+        players,
+        samples: 1,
+    };
+
+    let Ok(mut series) = SERIES.lock() else { return };
+    if let Err(e) = write_point(&dir, Resolution::Raw, &point) {
+        log::warn!("Failed to store metrics of {}: {}", server_id, e);
+        return;
+    }
+
+    let state = series.entry(server_id.to_string()).or_default();
+    for (resolution, bucket_ms, bucket) in [
+        (Resolution::Minute, MINUTE_MS, &mut state.minute),
+        (Resolution::Quarter, 15 * MINUTE_MS, &mut state.quarter),
+    ] {
+        let start = point.timestamp - point.timestamp.rem_euclid(bucket_ms);
+        if let Some(open) = bucket.as_ref().filter(|b| b.start != start) {
+            if let Err(e) = write_point(&dir, resolution, &open.point()) {
+                log::warn!("Failed to store metrics of {}: {}", server_id, e);
+            }
+            *bucket = None;
+        }
+        bucket.get_or_insert_with(|| Bucket { start, ..Default::default() }).add(&point);
+    }
+}
+
+/// Write the buckets a server is still filling, called when its process stops
+pub fn flush(server_id: &str) {
+    let Some(dir) = series_dir(server_id) else { return };
+    let Ok(mut series) = SERIES.lock() else { return };
+    let Some(state) = series.remove(server_id) else { return };
+    for (resolution, bucket) in [(Resolution::Minute, state.minute), (Resolution::Quarter, state.quarter)] {
+        let Some(bucket) = bucket else { continue };
+        if let Err(e) = write_point(&dir, resolution, &bucket.point()) {
+            log::warn!("Failed to store metrics of {}: {}", server_id, e);
+        }
+    }
+}
+
+fn write_point(dir: &Path, resolution: Resolution, point: &MetricPoint) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(resolution.file_name()))
+        .map_err(|e| e.to_string())?;
+    let line = serde_json::to_string(point).map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())
+}
+
+fn read_points(dir: &Path, resolution: Resolution) -> Vec<MetricPoint> {
+    let Ok(file) = File::open(dir.join(resolution.file_name())) else { return Vec::new() };
+    // A line cut short by a crash is skipped
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect()
+}
+
+fn compact_all() {
+    let Some(store_dir) = STORE_DIR.lock().ok().and_then(|dir| dir.clone()) else { return };
+    let Ok(entries) = fs::read_dir(&store_dir) else { return };
+    let now = chrono::Local::now().timestamp_millis();
+    for entry in entries.flatten().filter(|e| e.path().is_dir()) {
+        // Not while the sampler appends to the same files
+        let Ok(_series) = SERIES.lock() else { return };
+        compact(&entry.path(), now);
+    }
+}
+
+// Drop points past each tier's retention
+fn compact(dir: &Path, now: i64) {
+    for resolution in Resolution::ALL {
+        let points = read_points(dir, resolution);
+        let cutoff = now - resolution.retention_ms();
+        if !points.iter().any(|p| p.timestamp < cutoff) {
+            continue;
+        }
+        let kept: Vec<String> = points.iter()
+            .filter(|p| p.timestamp >= cutoff)
+            .filter_map(|p| serde_json::to_string(p).ok())
+            .collect();
+
+        let path = dir.join(resolution.file_name());
+        let temp = dir.join(format!("{}.tmp", resolution.file_name()));
+        let content = kept.iter().map(|line| format!("{}\n", line)).collect::<String>();
+        if let Err(e) = fs::write(&temp, content).and_then(|_| fs::rename(&temp, &path)) {
+            log::warn!("Failed to compact {}: {}", path.display(), e);
+        }
+    }
+}
+
+// Points of a tier in a time range, oldest first, with points of the same bucket merged
+fn query_points(dir: &Path, resolution: Resolution, from: i64, to: i64) -> Vec<MetricPoint> {
+    let mut points: Vec<MetricPoint> = read_points(dir, resolution)
+        .into_iter()
+        .filter(|p| p.timestamp >= from && p.timestamp <= to)
+        .collect();
+    points.sort_by_key(|p| p.timestamp);
+    if resolution == Resolution::Raw {
+        return points;
+    }
+
+    let mut merged: Vec<MetricPoint> = Vec::with_capacity(points.len());
+    let mut bucket: Option<Bucket> = None;
+    for point in &points {
+        if let Some(open) = bucket.as_ref().filter(|b| b.start != point.timestamp) {
+            merged.push(open.point());
+            bucket = None;
+        }
+        bucket.get_or_insert_with(|| Bucket { start: point.timestamp, ..Default::default() }).add(point);
+    }
+    merged.extend(bucket.map(|b| b.point()));
+    merged
+}
+
+// Finest tier that still holds data from `from`
+fn pick_resolution(from: i64, now: i64) -> Resolution {
+    Resolution::ALL.into_iter()
+        .find(|r| now - from <= r.retention_ms())
+        .unwrap_or(Resolution::Quarter)
+}
+
+// === Commands ===
+
+/// Points of a server between two unix-millis timestamps; the finest resolution covering
+/// the range is used when none is given
+#[tauri::command]
+pub fn metrics_query_cmd(
+    server_id: String,
+    from: i64,
+    to: Option<i64>,
+    resolution: Option<Resolution>,
+) -> Result<MetricSeries, String> {
+    let dir = series_dir(&server_id).ok_or("Metrics storage is not available")?;
+    let now = chrono::Local::now().timestamp_millis();
+    let to = to.unwrap_or(now);
+    let resolution = resolution.unwrap_or_else(|| pick_resolution(from, now));
+
+    Ok(MetricSeries {
+        server_id,
+        resolution,
+        points: query_points(&dir, resolution, from, to),
+    })
+}
+
+/// Player count and TPS as seen by the frontend (e.g. from RCON `list`)
+#[tauri::command]
+pub fn metrics_report_game_cmd(server_id: String, tps: Option<f32>, players: Option<u32>) {
+    report_game_stats(&server_id, tps, players);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every test uses the same store, with a server id of its own
+    fn store_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("voidlink-metrics-{}", std::process::id()));
+        *STORE_DIR.lock().unwrap() = Some(dir.clone());
+        dir
+    }
+
+    fn sample(timestamp: i64, cpu_usage: f32, memory_bytes: u64) -> MetricSample {
+        MetricSample {
+            timestamp,
+            cpu_usage,
+            memory_bytes,
+            threads: None,
+            open_files: None,
+            disk_read_bytes_per_sec: 0,
+            disk_write_bytes_per_sec: 0,
+        }
+    }
+
+    fn point(timestamp: i64, cpu: f32, memory: u64, samples: u32) -> MetricPoint {
+        MetricPoint {
+            timestamp,
+            cpu_avg: cpu,
+            cpu_max: cpu,
+            memory_avg: memory,
+            memory_max: memory,
+            tps: None,
+            players: None,
+            samples,
+        }
+    }
+
+    // Quarter-hour aligned, so the first minutes fall into one quarter bucket
+    const T0: i64 = 1_700_000_100_000;
+
+    #[test]
+    fn bucket_averages_and_peaks() {
+        let mut bucket = Bucket { start: T0, ..Default::default() };
+        bucket.add(&MetricPoint { tps: Some(20.0), players: Some(3), ..point(T0, 10.0, 1000, 1) });
+        bucket.add(&MetricPoint { tps: Some(17.0), ..point(T0 + 2000, 30.0, 3000, 1) });
+        bucket.add(&MetricPoint { players: Some(5), ..point(T0 + 4000, 20.0, 2000, 1) });
+
+        let p = bucket.point();
+        assert_eq!(p.timestamp, T0);
+        assert_eq!(p.samples, 3);
+        assert!((p.cpu_avg - 20.0).abs() < 1e-4);
+        assert_eq!(p.cpu_max, 30.0);
+        assert_eq!(p.memory_avg, 2000);
+        assert_eq!(p.memory_max, 3000);
+        assert!((p.tps.unwrap() - 18.5).abs() < 1e-4);
+        assert_eq!(p.players, Some(5));
+    }
+
+    #[test]
+    fn aggregated_points_are_weighted_by_their_samples() {
+        let mut bucket = Bucket { start: T0, ..Default::default() };
+        bucket.add(&point(T0, 10.0, 1000, 30));
+        bucket.add(&point(T0, 40.0, 4000, 10));
+        let p = bucket.point();
+        assert_eq!(p.samples, 40);
+        assert!((p.cpu_avg - 17.5).abs() < 1e-4);
+        assert_eq!(p.memory_avg, 1750);
+    }
+
+    #[test]
+    fn samples_roll_into_minute_and_quarter_buckets() {
+        let dir = store_dir().join("rollup");
+        fs::remove_dir_all(&dir).ok();
+
+        // Two samples in each of the first three minutes
+        for minute in 0..3 {
+            let start = T0 + minute * MINUTE_MS;
+            record("rollup", &sample(start, 10.0 * (minute + 1) as f32, 100));
+            record("rollup", &sample(start + 30_000, 10.0 * (minute + 1) as f32, 300));
+        }
+        assert_eq!(read_points(&dir, Resolution::Raw).len(), 6);
+        // Only closed minutes are written, the quarter hour is still open
+        let minutes = read_points(&dir, Resolution::Minute);
+        assert_eq!(minutes.iter().map(|p| p.timestamp).collect::<Vec<_>>(), vec![T0, T0 + MINUTE_MS]);
+        assert_eq!(minutes[1].samples, 2);
+        assert_eq!(minutes[1].memory_avg, 200);
+        assert!((minutes[1].cpu_avg - 20.0).abs() < 1e-4);
+        assert!(read_points(&dir, Resolution::Quarter).is_empty());
+
+        // Stopping the server writes what is still open
+        flush("rollup");
+        let minutes = read_points(&dir, Resolution::Minute);
+        assert_eq!(minutes.len(), 3);
+        let quarters = read_points(&dir, Resolution::Quarter);
+        assert_eq!(quarters.len(), 1);
+        assert_eq!(quarters[0].timestamp, T0 - T0.rem_euclid(15 * MINUTE_MS));
+        assert_eq!(quarters[0].samples, 6);
+        assert!((quarters[0].cpu_avg - 20.0).abs() < 1e-4);
+        assert_eq!(quarters[0].cpu_max, 30.0);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn a_restart_within_a_bucket_is_merged_on_query() {
+        let dir = store_dir().join("restart");
+        fs::remove_dir_all(&dir).ok();
+
+        record("restart", &sample(T0, 10.0, 1000));
+        record("restart", &sample(T0 + 2000, 10.0, 1000));
+        flush("restart");
+        record("restart", &sample(T0 + 40_000, 40.0, 4000));
+        flush("restart");
+
+        assert_eq!(read_points(&dir, Resolution::Minute).len(), 2);
+        let points = query_points(&dir, Resolution::Minute, 0, i64::MAX);
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].samples, 3);
+        assert!((points[0].cpu_avg - 20.0).abs() < 1e-4);
+        assert_eq!(points[0].memory_max, 4000);
+        // Raw samples are never merged
+        assert_eq!(query_points(&dir, Resolution::Raw, T0, T0 + 2000).len(), 2);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn compaction_drops_points_past_retention() {
+        let dir = store_dir().join("compact");
+        fs::remove_dir_all(&dir).ok();
+        let now = T0 + 3 * 24 * 60 * MINUTE_MS;
+        for timestamp in [T0, now - 2 * 60 * MINUTE_MS, now - 30 * MINUTE_MS, now] {
+            for resolution in Resolution::ALL {
+                write_point(&dir, resolution, &point(timestamp, 1.0, 1, 1)).unwrap();
+            }
+        }
+
+        compact(&dir, now);
+        let timestamps = |r| read_points(&dir, r).iter().map(|p| p.timestamp).collect::<Vec<_>>();
+        assert_eq!(timestamps(Resolution::Raw), vec![now - 30 * MINUTE_MS, now]);
+        assert_eq!(timestamps(Resolution::Minute), vec![now - 2 * 60 * MINUTE_MS, now - 30 * MINUTE_MS, now]);
+        assert_eq!(timestamps(Resolution::Quarter).len(), 4);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn finest_resolution_covering_the_range() {
+        let now = T0;
+        assert_eq!(pick_resolution(now - 30 * MINUTE_MS, now), Resolution::Raw);
+        assert_eq!(pick_resolution(now - 6 * 60 * MINUTE_MS, now), Resolution::Minute);
+        assert_eq!(pick_resolution(now - 7 * 24 * 60 * MINUTE_MS, now), Resolution::Quarter);
+        assert_eq!(pick_resolution(now - 90 * 24 * 60 * MINUTE_MS, now), Resolution::Quarter);
+    }
+}
@@ -1,5 +1,7 @@
 use rcon::Connection;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
 use tokio::net::TcpStream;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -8,6 +10,71 @@ pub struct RconResult {
     pub response: String,
 }
 
+// Connection details for a server's RCON endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RconCredentials {
+    pub host: String,
+    pub port: u16,
+    pub password: String,
+}
+
+impl RconCredentials {
+    /// Local RCON endpoint from a server's server.properties, if RCON is enabled
+    pub fn from_server_dir(server_dir: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(server_dir.join("server.properties")).ok()?;
+        let properties: HashMap<&str, &str> = content.lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect();
+
+        if properties.get("enable-rcon") != Some(&"true") {
+            return None;
+        }
+        let password = properties.get("rcon.password").filter(|p| !p.is_empty())?;
+        Some(Self {
+            host: "127.0.0.1".to_string(),
+            port: properties.get("rcon.port").and_then(|p| p.parse().ok()).unwrap_or(25575),
+            password: password.to_string(),
+        })
+    }
+}
+
+/// One RCON connection for several commands from synchronous code (must not be used from
+/// inside an async task)
+pub struct BlockingSession {
+    conn: Connection<TcpStream>,
+}
+
+impl BlockingSession {
+    pub fn connect(credentials: &RconCredentials) -> Result<Self, String> {
+        let address = format!("{}:{}", credentials.host, credentials.port);
+        let conn = tauri::async_runtime::block_on(
+            <Connection<TcpStream>>::builder()
+                .enable_minecraft_quirks(true)
+                .connect(address, &credentials.password),
+        )
+        .map_err(|e| format!("RCON connection failed: {}", e))?;
+        Ok(Self { conn })
+    }
+
+    pub fn cmd(&mut self, command: &str) -> Result<String, String> {
+        tauri::async_runtime::block_on(self.conn.cmd(command))
+            .map_err(|e| format!("RCON command failed: {}", e))
+    }
+}
+
+/// Send a command from synchronous code (must not be called from inside an async task)
+pub fn send_command_blocking(credentials: &RconCredentials, command: &str) -> Result<String, String> {
+    tauri::async_runtime::block_on(rcon_send_command(
+        credentials.host.clone(),
+        credentials.port,
+        credentials.password.clone(),
+        command.to_string(),
+    ))
+    .map(|result| result.response)
+}
+
 #[tauri::command]
 pub async fn rcon_send_command(
     host: String,
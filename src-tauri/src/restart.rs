@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Automatic restart policy for crashed servers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestartPolicy {
+    pub enabled: bool,
+    pub max_restarts_per_window: u32,
+    pub window_minutes: u32,
+    pub initial_backoff_secs: u64,
+    pub max_backoff_secs: u64,
+    pub give_up_after: u32, // consecutive crashes without reaching "Done"
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_restarts_per_window: 5,
+            window_minutes: 60,
+            initial_backoff_secs: 10,
+            max_backoff_secs: 300,
+            give_up_after: 3,
+        }
+    }
+}
+
+// Restart bookkeeping for one server
+#[derive(Debug, Default)]
+pub struct RestartState {
+    pub consecutive_crashes: u32,
+    pub recent_restarts: VecDeque<Instant>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RestartDecision {
+    Disabled,
+    Restart { attempt: u32, delay: Duration },
+    GiveUp { reason: String },
+}
+
+impl RestartState {
+    /// The server finished starting up, so earlier crashes no longer count as consecutive
+    pub fn mark_healthy(&mut self) {
+        self.consecutive_crashes = 0;
+    }
+}
+
+/// Decide what to do after a crash and record the attempt
+pub fn plan_restart(policy: &RestartPolicy, state: &mut RestartState, now: Instant) -> RestartDecision {
+    if !policy.enabled {
+        return RestartDecision::Disabled;
+    }
+
+    state.consecutive_crashes += 1;
+    if state.consecutive_crashes > policy.give_up_after {
+        return RestartDecision::GiveUp {
+            reason: format!("Crashed {} times in a row without finishing startup", state.consecutive_crashes),
+        };
+    }
+
+    let window = Duration::from_secs(policy.window_minutes as u64 * 60);
+    while let Some(first) = state.recent_restarts.front() {
+        if now.duration_since(*first) > window {
+            state.recent_restarts.pop_front();
+        } else {
+            break;
+        }
+    }
+    if state.recent_restarts.len() >= policy.max_restarts_per_window as usize {
+        return RestartDecision::GiveUp {
+            reason: format!(
+                "Restarted {} times within {} minutes",
+                state.recent_restarts.len(),
+                policy.window_minutes
+            ),
+        };
+    }
+
+    state.recent_restarts.push_back(now);
+
+    // Exponential backoff: initial, 2x, 4x, ... capped at max_backoff_secs
+    let exponent = (state.consecutive_crashes - 1).min(16);
+    let delay_secs = policy.initial_backoff_secs
+        .saturating_mul(1u64 << exponent)
+        .min(policy.max_backoff_secs);
+
+    RestartDecision::Restart {
+        attempt: state.consecutive_crashes,
+        delay: Duration::from_secs(delay_secs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RestartPolicy {
+        RestartPolicy {
+            enabled: true,
+            max_restarts_per_window: 10,
+            window_minutes: 60,
+            initial_backoff_secs: 10,
+            max_backoff_secs: 60,
+            give_up_after: 10,
+        }
+    }
+
+    fn delay_of(decision: RestartDecision) -> u64 {
+        match decision {
+            RestartDecision::Restart { delay, .. } => delay.as_secs(),
+            other => panic!("expected a restart, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn disabled_policy_never_restarts() {
+        let mut state = RestartState::default();
+        let policy = RestartPolicy::default();
+        assert_eq!(plan_restart(&policy, &mut state, Instant::now()), RestartDecision::Disabled);
+        assert_eq!(state.consecutive_crashes, 0);
+        assert!(state.recent_restarts.is_empty());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let mut state = RestartState::default();
+        let now = Instant::now();
+        let delays: Vec<u64> = (0..5).map(|_| delay_of(plan_restart(&policy(), &mut state, now))).collect();
+        assert_eq!(delays, vec![10, 20, 40, 60, 60]);
+    }
+
+    #[test]
+    fn attempts_count_consecutive_crashes() {
+        let mut state = RestartState::default();
+        let now = Instant::now();
+        plan_restart(&policy(), &mut state, now);
+        match plan_restart(&policy(), &mut state, now) {
+            RestartDecision::Restart { attempt, .. } => assert_eq!(attempt, 2),
+            other => panic!("expected a restart, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn gives_up_after_consecutive_crashes() {
+        let policy = RestartPolicy { give_up_after: 2, ..policy() };
+        let mut state = RestartState::default();
+        let now = Instant::now();
+        delay_of(plan_restart(&policy, &mut state, now));
+        delay_of(plan_restart(&policy, &mut state, now));
+        assert!(matches!(plan_restart(&policy, &mut state, now), RestartDecision::GiveUp { .. }));
+    }
+
+    #[test]
+    fn healthy_start_resets_backoff() {
+        let mut state = RestartState::default();
+        let now = Instant::now();
+        delay_of(plan_restart(&policy(), &mut state, now));
+        delay_of(plan_restart(&policy(), &mut state, now));
+        state.mark_healthy();
+        assert_eq!(delay_of(plan_restart(&policy(), &mut state, now)), 10);
+    }
+
+    #[test]
+    fn restarts_are_limited_per_window() {
+        let policy = RestartPolicy { max_restarts_per_window: 2, ..policy() };
+        let mut state = RestartState::default();
+        let start = Instant::now();
+
+        for offset in [0, 60] {
+            delay_of(plan_restart(&policy, &mut state, start + Duration::from_secs(offset)));
+            state.mark_healthy();
+        }
+        assert!(matches!(
+            plan_restart(&policy, &mut state, start + Duration::from_secs(120)),
+            RestartDecision::GiveUp { .. }
+        ));
+
+        // Once the first restart is out of the window there is room again
+        state.mark_healthy();
+        let later = start + Duration::from_secs(61 * 60);
+        assert_eq!(delay_of(plan_restart(&policy, &mut state, later)), 10);
+        assert_eq!(state.recent_restarts.len(), 2);
+    }
+}
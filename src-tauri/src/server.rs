@@ -0,0 +1,738 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use chrono::Local;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, System, UpdateKind};
+use tauri::Emitter;
+use tauri_plugin_shell::ShellExt;
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use crate::metrics;
+use crate::restart::{self, RestartDecision, RestartPolicy, RestartState};
+
+// Global state for tracking supervised Minecraft server processes
+pub static SERVER_PROCESSES: LazyLock<Mutex<HashMap<String, ServerProcess>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Number of console lines kept per server so the UI can rebuild its console after a reload
+const CONSOLE_HISTORY_LINES: usize = 1000;
+
+// Default time the server gets to save and exit after `stop` before escalating
+const DEFAULT_STOP_GRACE_SECS: u64 = 60;
+// Time given to SIGTERM before falling back to SIGKILL
+const TERMINATE_GRACE_SECS: u64 = 15;
+// Time to wait for the OS to tear the process down after SIGKILL
+const KILL_GRACE_SECS: u64 = 5;
+const STOP_POLL_INTERVAL_MS: u64 = 250;
+
+// Everything needed to (re)launch a server
+#[derive(Debug, Clone)]
+pub struct ServerLaunch {
+    pub server_path: String,
+    pub java_path: String,
+    pub java_args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+// Recent console output plus a running line count, so callers can wait for new lines
+#[derive(Default)]
+pub struct ConsoleBuffer {
+    pub lines: VecDeque<String>,
+    pub total: u64,
+}
+
+impl ConsoleBuffer {
+    fn push(&mut self, line: String) {
+        if self.lines.len() >= CONSOLE_HISTORY_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+        self.total += 1;
+    }
+
+    // Lines received after the given position that are still buffered
+    fn since(&self, position: u64) -> impl Iterator<Item = &String> {
+        let first_buffered = self.total - self.lines.len() as u64;
+        let skip = position.saturating_sub(first_buffered) as usize;
+        self.lines.iter().skip(skip)
+    }
+}
+
+pub struct ServerProcess {
+    pub server_id: String,
+    pub launch: ServerLaunch,
+    pub pid: Option<u32>,
+    pub is_running: bool,
+    // Startup finished ("Done" was printed)
+    pub is_online: bool,
+    pub started_at: String,
+    pub started_at_unix: u64,
+    pub exit_code: Option<i32>,
+    pub console: ConsoleBuffer,
+    pub restart_policy: RestartPolicy,
+    pub restart_state: RestartState,
+    // Set when the stop was asked for, so the exit is not treated as a crash
+    pub stop_requested: bool,
+    pub restart_pending: bool,
+    child: Option<CommandChild>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServerStatus {
+    pub server_id: String,
+    pub is_running: bool,
+    pub is_online: bool,
+    pub stop_requested: bool,
+    pub pid: Option<u32>,
+    pub server_path: Option<String>,
+    pub started_at: Option<String>,
+    pub exit_code: Option<i32>,
+    pub restart_pending: bool,
+    pub consecutive_crashes: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServerStartResult {
+    pub success: bool,
+    pub server_id: String,
+    pub pid: Option<u32>,
+    pub message: String,
+}
+
+// Stage of the stop ladder that ended the process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopStage {
+    AlreadyStopped,
+    StopCommand,
+    Terminate,
+    Kill,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServerStopResult {
+    pub server_id: String,
+    pub stopped: bool,
+    pub stage: StopStage,
+    pub elapsed_ms: u64,
+    pub message: String,
+}
+
+impl ServerProcess {
+    fn status(&self) -> ServerStatus {
+        ServerStatus {
+            server_id: self.server_id.clone(),
+            is_running: self.is_running,
+            is_online: self.is_online,
+            stop_requested: self.stop_requested,
+            pid: self.pid,
+            server_path: Some(self.launch.server_path.clone()),
+            started_at: Some(self.started_at.clone()),
+            exit_code: self.exit_code,
+            restart_pending: self.restart_pending,
+            consecutive_crashes: self.restart_state.consecutive_crashes,
+        }
+    }
+}
+
+/// Check whether a server is currently running under the supervisor
+pub fn is_server_running(server_id: &str) -> bool {
+    SERVER_PROCESSES.lock()
+        .map(|processes| processes.get(server_id).is_some_and(|p| p.is_running))
+        .unwrap_or(false)
+}
+
+/// Check whether a server is running, also when it was started outside the supervisor
+/// (e.g. from its start script): a Java process working in the server folder counts
+pub fn is_server_dir_running(server_id: &str, server_path: &Path) -> bool {
+    if is_server_running(server_id) {
+        return true;
+    }
+    let Ok(server_dir) = server_path.canonicalize() else { return false };
+
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::new().with_cwd(UpdateKind::OnlyIfNotSet),
+    );
+    sys.processes().values().any(|process| {
+        process.name().to_string_lossy().to_lowercase().contains("java")
+            && process.cwd().and_then(|cwd| cwd.canonicalize().ok()).is_some_and(|cwd| cwd == server_dir)
+    })
+}
+
+/// Get the PID of a running server
+pub fn get_server_pid(server_id: &str) -> Option<u32> {
+    let processes = SERVER_PROCESSES.lock().ok()?;
+    processes.get(server_id).filter(|p| p.is_running).and_then(|p| p.pid)
+}
+
+/// Mark a server as intentionally stopping so its exit does not trigger a restart
+fn mark_stop_requested(server_id: &str) {
+    if let Ok(mut processes) = SERVER_PROCESSES.lock() {
+        if let Some(proc) = processes.get_mut(server_id) {
+            proc.stop_requested = true;
+            proc.restart_pending = false;
+        }
+    }
+}
+
+/// Current console position of a server, to be passed to `wait_for_console_line`
+pub fn console_position(server_id: &str) -> Option<u64> {
+    let processes = SERVER_PROCESSES.lock().ok()?;
+    processes.get(server_id).map(|p| p.console.total)
+}
+
+/// Block until a console line containing `needle` shows up after `position` (or the timeout elapses)
+pub fn wait_for_console_line(server_id: &str, position: u64, needle: &str, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let found = SERVER_PROCESSES.lock()
+            .map(|processes| processes.get(server_id)
+                .is_some_and(|p| p.console.since(position).any(|line| line.contains(needle))))
+            .unwrap_or(false);
+        if found {
+            return true;
+        }
+        if Instant::now() >= deadline || !is_server_running(server_id) {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(STOP_POLL_INTERVAL_MS));
+    }
+}
+
+/// Write a line to the server's stdin
+pub fn write_server_stdin(server_id: &str, line: &str) -> Result<(), String> {
+    let mut processes = SERVER_PROCESSES.lock().map_err(|e| e.to_string())?;
+    let proc = processes.get_mut(server_id)
+        .filter(|p| p.is_running)
+        .ok_or("Server is not running")?;
+    let child = proc.child.as_mut().ok_or("Server stdin is not available")?;
+
+    let mut data = line.trim_end_matches(['\r', '\n']).to_string();
+    data.push('\n');
+    child.write(data.as_bytes())
+        .map_err(|e| format!("Failed to write to server stdin: {}", e))
+}
+
+/// Spawn the Java process for a server and start streaming its output.
+///
+/// Passing a `restart_policy` resets the crash history; `None` keeps the previous one (used for auto-restarts).
+pub fn spawn_server(
+    app: &tauri::AppHandle,
+    server_id: &str,
+    launch: ServerLaunch,
+    restart_policy: Option<RestartPolicy>,
+) -> Result<u32, String> {
+    let server_dir = PathBuf::from(&launch.server_path);
+    if !server_dir.exists() {
+        return Err(format!("Server path does not exist: {}", launch.server_path));
+    }
+
+    log::info!("Starting server {}: \"{}\" {}", server_id, launch.java_path, launch.java_args.join(" "));
+
+    let (mut rx, child) = app.shell()
+        .command(&launch.java_path)
+        .args(&launch.java_args)
+        .envs(&launch.env)
+        .current_dir(&server_dir)
+        .spawn()
+        .map_err(|e| format!("Failed to spawn server: {}", e))?;
+
+    let pid = child.pid();
+    metrics::track(server_id, pid);
+
+    // Store process info
+    {
+        let mut processes = SERVER_PROCESSES.lock().map_err(|e| e.to_string())?;
+        let previous = processes.remove(server_id);
+        let (console, policy, state) = match (previous, restart_policy) {
+            (Some(prev), None) => (prev.console, prev.restart_policy, prev.restart_state),
+            (Some(prev), Some(policy)) => (prev.console, policy, RestartState::default()),
+            (None, policy) => (ConsoleBuffer::default(), policy.unwrap_or_default(), RestartState::default()),
+        };
+        let now = Local::now();
+        processes.insert(server_id.to_string(), ServerProcess {
+            server_id: server_id.to_string(),
+            launch,
+            pid: Some(pid),
+            is_running: true,
+            is_online: false,
+            started_at: now.to_rfc3339(),
+            started_at_unix: now.timestamp().max(0) as u64,
+            exit_code: None,
+            console,
+            restart_policy: policy,
+            restart_state: state,
+            stop_requested: false,
+            restart_pending: false,
+            child: Some(child),
+        });
+    }
+
+    let _ = app.emit("server-started", serde_json::json!({
+        "server_id": server_id,
+        "pid": pid
+    }));
+
+    // Spawn task to monitor output
+    let app_clone = app.clone();
+    let server_id_clone = server_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(line) => {
+                    let line_str = String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']).to_string();
+                    record_console_line(&server_id_clone, &line_str);
+                    let _ = app_clone.emit("server-output", serde_json::json!({
+                        "server_id": server_id_clone,
+                        "type": "stdout",
+                        "message": line_str
+                    }));
+
+                    // "Done (12.345s)! For help, type "help"" marks the end of startup
+                    if line_str.contains("Done (") && line_str.contains('!') {
+                        if let Ok(mut processes) = SERVER_PROCESSES.lock() {
+                            if let Some(proc) = processes.get_mut(&server_id_clone) {
+                                proc.is_online = true;
+                                proc.restart_state.mark_healthy();
+                            }
+                        }
+                        let _ = app_clone.emit("server-online", serde_json::json!({
+                            "server_id": server_id_clone
+                        }));
+                    }
+                }
+                CommandEvent::Stderr(line) => {
+                    let line_str = String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']).to_string();
+                    record_console_line(&server_id_clone, &line_str);
+                    let _ = app_clone.emit("server-output", serde_json::json!({
+                        "server_id": server_id_clone,
+                        "type": "stderr",
+                        "message": line_str
+                    }));
+                }
+                CommandEvent::Terminated(payload) => {
+                    log::info!("[Server {}] Process terminated with code: {:?}", server_id_clone, payload.code);
+
+                    let _ = app_clone.emit("server-stopped", serde_json::json!({
+                        "server_id": server_id_clone,
+                        "code": payload.code,
+                        "signal": payload.signal
+                    }));
+                    handle_server_exit(&app_clone, &server_id_clone, pid, payload.code);
+                    break;
+                }
+                CommandEvent::Error(err) => {
+                    log::error!("[Server {}] {}", server_id_clone, err);
+                }
+                _ => {}
+            }
+        }
+    });
+
+    Ok(pid)
+}
+
+// Update state after the process exited and apply the restart policy if it crashed
+fn handle_server_exit(app: &tauri::AppHandle, server_id: &str, pid: u32, code: Option<i32>) {
+    let (decision, crash_report) = {
+        let Ok(mut processes) = SERVER_PROCESSES.lock() else { return };
+        let Some(proc) = processes.get_mut(server_id) else { return };
+        if proc.pid != Some(pid) {
+            return;
+        }
+
+        proc.is_running = false;
+        proc.is_online = false;
+        proc.exit_code = code;
+        proc.child = None;
+
+        // A clean exit or a requested stop is not a crash
+        if proc.stop_requested || code == Some(0) {
+            return;
+        }
+
+        let crash_report = crate::logs::find_crash_report_since(&proc.launch.server_path, proc.started_at_unix);
+        let decision = restart::plan_restart(&proc.restart_policy, &mut proc.restart_state, Instant::now());
+        proc.restart_pending = matches!(decision, RestartDecision::Restart { .. });
+        (decision, crash_report)
+    };
+
+    log::warn!("[Server {}] Crashed with code {:?}", server_id, code);
+    let _ = app.emit("server-crashed", serde_json::json!({
+        "server_id": server_id,
+        "code": code,
+        "crash_report": crash_report
+    }));
+
+    match decision {
+        RestartDecision::Disabled => {}
+        RestartDecision::GiveUp { reason } => {
+            log::error!("[Server {}] Giving up on automatic restarts: {}", server_id, reason);
+            let _ = app.emit("server-restart-gave-up", serde_json::json!({
+                "server_id": server_id,
+                "reason": reason,
+                "crash_report": crash_report
+            }));
+        }
+        RestartDecision::Restart { attempt, delay } => {
+            log::info!("[Server {}] Restarting in {:?} (attempt {})", server_id, delay, attempt);
+            let _ = app.emit("server-restart-scheduled", serde_json::json!({
+                "server_id": server_id,
+                "attempt": attempt,
+                "delay_secs": delay.as_secs(),
+                "crash_report": crash_report
+            }));
+
+            let app_clone = app.clone();
+            let server_id = server_id.to_string();
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(delay).await;
+
+                // The restart may have been cancelled by a stop or a manual start in the meantime
+                let launch = {
+                    let Ok(mut processes) = SERVER_PROCESSES.lock() else { return };
+                    match processes.get_mut(&server_id) {
+                        Some(proc) if proc.restart_pending && !proc.is_running => {
+                            proc.restart_pending = false;
+                            proc.launch.clone()
+                        }
+                        _ => return,
+                    }
+                };
+
+                match spawn_server(&app_clone, &server_id, launch, None) {
+                    Ok(pid) => {
+                        let _ = app_clone.emit("server-restarted", serde_json::json!({
+                            "server_id": server_id,
+                            "attempt": attempt,
+                            "pid": pid
+                        }));
+                    }
+                    Err(e) => {
+                        log::error!("[Server {}] Automatic restart failed: {}", server_id, e);
+                        let _ = app_clone.emit("server-restart-gave-up", serde_json::json!({
+                            "server_id": server_id,
+                            "reason": e
+                        }));
+                    }
+                }
+            });
+        }
+    }
+}
+
+fn record_console_line(server_id: &str, line: &str) {
+    if let Ok(mut processes) = SERVER_PROCESSES.lock() {
+        if let Some(proc) = processes.get_mut(server_id) {
+            proc.console.push(line.to_string());
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn server_start(
+    app: tauri::AppHandle,
+    server_id: String,
+    server_path: String,
+    java_path: String,
+    java_args: Vec<String>,
+    env: Option<BTreeMap<String, String>>,
+    restart_policy: Option<RestartPolicy>,
+) -> Result<ServerStartResult, String> {
+    // Check if already running
+    if is_server_running(&server_id) {
+        return Ok(ServerStartResult {
+            success: false,
+            pid: get_server_pid(&server_id),
+            server_id,
+            message: "Server is already running".to_string(),
+        });
+    }
+
+    let launch = ServerLaunch {
+        server_path,
+        java_path,
+        java_args,
+        env: env.unwrap_or_default(),
+    };
+    let pid = spawn_server(&app, &server_id, launch, Some(restart_policy.unwrap_or_default()))?;
+
+    Ok(ServerStartResult {
+        success: true,
+        server_id,
+        pid: Some(pid),
+        message: "Server started".to_string(),
+    })
+}
+
+#[tauri::command]
+pub fn server_send_input(server_id: String, command: String) -> Result<(), String> {
+    write_server_stdin(&server_id, &command)
+}
+
+/// Update the restart policy of a server (applies to the running process and future auto-restarts)
+#[tauri::command]
+pub fn server_set_restart_policy(server_id: String, policy: RestartPolicy) -> Result<bool, String> {
+    let mut processes = SERVER_PROCESSES.lock().map_err(|e| e.to_string())?;
+    match processes.get_mut(&server_id) {
+        Some(proc) => {
+            if !policy.enabled {
+                proc.restart_pending = false;
+            }
+            proc.restart_policy = policy;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+#[tauri::command]
+pub fn server_kill(server_id: String) -> Result<bool, String> {
+    let mut processes = SERVER_PROCESSES.lock().map_err(|e| e.to_string())?;
+
+    if let Some(proc) = processes.get_mut(&server_id) {
+        proc.restart_pending = false;
+        if !proc.is_running {
+            return Ok(false);
+        }
+
+        proc.stop_requested = true;
+        if let Some(child) = proc.child.take() {
+            child.kill().map_err(|e| format!("Failed to kill server: {}", e))?;
+        }
+
+        log::info!("Killed server: {}", server_id);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+#[tauri::command]
+pub fn server_get_status(server_id: String) -> Result<ServerStatus, String> {
+    let processes = SERVER_PROCESSES.lock().map_err(|e| e.to_string())?;
+
+    if let Some(proc) = processes.get(&server_id) {
+        Ok(proc.status())
+    } else {
+        Ok(ServerStatus {
+            server_id,
+            is_running: false,
+            is_online: false,
+            stop_requested: false,
+            pid: None,
+            server_path: None,
+            started_at: None,
+            exit_code: None,
+            restart_pending: false,
+            consecutive_crashes: 0,
+        })
+    }
+}
+
+/// List all servers currently running under the supervisor (used to reattach after a reload)
+#[tauri::command]
+pub fn server_list_running() -> Result<Vec<ServerStatus>, String> {
+    let processes = SERVER_PROCESSES.lock().map_err(|e| e.to_string())?;
+    Ok(processes.values()
+        .filter(|p| p.is_running)
+        .map(|p| p.status())
+        .collect())
+}
+
+/// Get the buffered console output of a server
+#[tauri::command]
+pub fn server_get_console(server_id: String) -> Result<Vec<String>, String> {
+    let processes = SERVER_PROCESSES.lock().map_err(|e| e.to_string())?;
+    Ok(processes.get(&server_id)
+        .map(|p| p.console.lines.iter().cloned().collect())
+        .unwrap_or_default())
+}
+
+/// Check whether a PID still refers to a live (non-zombie) process
+pub fn is_pid_alive(pid: u32) -> bool {
+    let target_pid = Pid::from_u32(pid);
+    let mut sys = System::new();
+    sys.refresh_processes(ProcessesToUpdate::Some(&[target_pid]), true);
+    match sys.process(target_pid) {
+        Some(proc) => !matches!(proc.status(), ProcessStatus::Zombie | ProcessStatus::Dead),
+        None => false,
+    }
+}
+
+/// Ask a process to terminate with SIGTERM, returning whether the signal was sent.
+/// Windows has no equivalent for a server: taskkill without /F only closes windows, which a
+/// console java.exe does not have, and Ctrl-Break only reaches processes on our own console.
+/// There nothing is sent and false is returned, so callers go straight to `kill_pid`.
+pub fn terminate_pid(pid: u32) -> bool {
+    #[cfg(windows)]
+    {
+        let _ = pid;
+        false
+    }
+    #[cfg(not(windows))]
+    {
+        std::process::Command::new("kill")
+            .args(["-TERM", &pid.to_string()])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+}
+
+/// Forcefully kill a process tree (SIGKILL on Unix, taskkill /F /T on Windows)
+pub fn kill_pid(pid: u32) -> bool {
+    #[cfg(windows)]
+    {
+        // Use /T to kill the entire process tree (important for Java which spawns child processes)
+        match std::process::Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &pid.to_string()])
+            .output()
+        {
+            Ok(output) => {
+                log::info!("Killed process tree for PID {}: {:?}", pid, output.status.success());
+                output.status.success()
+            }
+            Err(e) => {
+                log::error!("Failed to kill process {}: {}", pid, e);
+                false
+            }
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        std::process::Command::new("kill")
+            .args(["-9", &pid.to_string()])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+}
+
+fn is_stopped(server_id: &str, pid: u32, supervised: bool) -> bool {
+    if supervised && !is_server_running(server_id) {
+        return true;
+    }
+    !is_pid_alive(pid)
+}
+
+// Poll until the process is gone or the timeout elapses
+async fn wait_for_exit(server_id: &str, pid: u32, supervised: bool, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if is_stopped(server_id, pid, supervised) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(STOP_POLL_INTERVAL_MS)).await;
+    }
+}
+
+/// Stop a server gracefully, escalating from `stop` to SIGTERM to SIGKILL.
+/// On Windows the SIGTERM stage is skipped, see `terminate_pid`.
+///
+/// `pid` is only needed for servers that were not launched through the supervisor.
+/// When stdin is not available the `stop` command is sent over RCON if credentials are given.
+#[tauri::command]
+pub async fn server_stop(
+    app: tauri::AppHandle,
+    server_id: String,
+    pid: Option<u32>,
+    grace_period_secs: Option<u64>,
+    rcon_host: Option<String>,
+    rcon_port: Option<u16>,
+    rcon_password: Option<String>,
+) -> Result<ServerStopResult, String> {
+    let started = Instant::now();
+    mark_stop_requested(&server_id);
+    let supervised_pid = get_server_pid(&server_id);
+    let supervised = supervised_pid.is_some();
+
+    let result = |stopped: bool, stage: StopStage, message: &str| ServerStopResult {
+        server_id: server_id.clone(),
+        stopped,
+        stage,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        message: message.to_string(),
+    };
+
+    let pid = match supervised_pid.or(pid) {
+        Some(pid) if !is_stopped(&server_id, pid, supervised) => pid,
+        _ => return Ok(result(true, StopStage::AlreadyStopped, "Server is not running")),
+    };
+
+    let emit_stage = |stage: StopStage| {
+        let _ = app.emit("server-stopping", serde_json::json!({
+            "server_id": server_id,
+            "stage": stage
+        }));
+    };
+
+    // Stage 1: ask the server to save and exit on its own
+    let mut stop_sent = supervised && write_server_stdin(&server_id, "stop").is_ok();
+    if !stop_sent {
+        if let (Some(host), Some(port), Some(password)) = (rcon_host, rcon_port, rcon_password) {
+            match crate::rcon::rcon_stop_server(host, port, password).await {
+                Ok(_) => stop_sent = true,
+                Err(e) => log::warn!("[Server {}] RCON stop failed: {}", server_id, e),
+            }
+        }
+    }
+
+    if stop_sent {
+        emit_stage(StopStage::StopCommand);
+        let grace = Duration::from_secs(grace_period_secs.unwrap_or(DEFAULT_STOP_GRACE_SECS));
+        if wait_for_exit(&server_id, pid, supervised, grace).await {
+            log::info!("[Server {}] Stopped after stop command", server_id);
+            return Ok(result(true, StopStage::StopCommand, "Server stopped gracefully"));
+        }
+        log::warn!("[Server {}] Did not stop within {:?}, sending SIGTERM", server_id, grace);
+    } else {
+        log::warn!("[Server {}] No stdin or RCON available, sending SIGTERM", server_id);
+    }
+
+    // Stage 2: SIGTERM lets the JVM run its shutdown hooks (which save the world on most servers).
+    // Skipped on Windows, which cannot send it, instead of waiting out the grace period for nothing.
+    if terminate_pid(pid) {
+        emit_stage(StopStage::Terminate);
+        if wait_for_exit(&server_id, pid, supervised, Duration::from_secs(TERMINATE_GRACE_SECS)).await {
+            log::info!("[Server {}] Stopped after SIGTERM", server_id);
+            return Ok(result(true, StopStage::Terminate, "Server terminated"));
+        }
+        log::warn!("[Server {}] Did not respond to SIGTERM, killing", server_id);
+    } else {
+        log::warn!("[Server {}] Cannot send SIGTERM on this platform, killing", server_id);
+    }
+
+    // Stage 3: SIGKILL as a last resort
+    emit_stage(StopStage::Kill);
+    kill_pid(pid);
+    if wait_for_exit(&server_id, pid, supervised, Duration::from_secs(KILL_GRACE_SECS)).await {
+        return Ok(result(true, StopStage::Kill, "Server killed"));
+    }
+
+    log::error!("[Server {}] Failed to stop process {}", server_id, pid);
+    Ok(result(false, StopStage::Failed, "Failed to stop server process"))
+}
+
+/// Ask all running servers to stop - called on app exit
+pub fn stop_all_servers() {
+    if let Ok(mut processes) = SERVER_PROCESSES.lock() {
+        for (server_id, proc) in processes.iter_mut() {
+            proc.restart_pending = false;
+            if proc.is_running {
+                proc.stop_requested = true;
+                if let Some(child) = proc.child.as_mut() {
+                    log::info!("Stopping server {} (PID: {:?})", server_id, proc.pid);
+                    let _ = child.write(b"stop\n");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::backup::{self, BackupConsistency};
+use crate::backup_archive::{self, BackupCompression, ProgressTracker};
+use crate::backup_filter::{self, BackupFilter};
+use crate::backup_verify::{to_hex, EntryManifest, ManifestEntry};
+use crate::java;
+use crate::java_requirement;
+use crate::jobs::{self, Job, JobProgress};
+use crate::rcon::RconCredentials;
+use crate::server;
+
+// A bundle is a zip holding a whole server so it can be moved to another machine:
+//   voidlink-bundle.json   BundleManifest, added last
+//   server/<path>          the server directory, including server.json
+const MANIFEST_NAME: &str = "voidlink-bundle.json";
+const SERVER_PREFIX: &str = "server/";
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+// Only what the server recreates by itself; unlike backups, libraries/ is kept since
+// Forge and NeoForge servers do not start without it
+const BUNDLE_EXCLUDE_PATTERNS: &[&str] = &[
+    "*.log",
+    "*.log.gz",
+    "crash-reports/",
+    "logs/",
+    ".cache/",
+    ".voidlink-restore-*/",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub exported_at: String,
+    pub app_version: String,
+    pub server_name: String,
+    pub mc_version: String,
+    pub loader: String, // "vanilla", "paper", "fabric", "forge", ...
+    pub java_major: u32,
+    pub jar_file: String,
+    pub file_count: u64,
+    pub total_size_bytes: u64,
+    pub files: Vec<ManifestEntry>, // paths relative to the server directory
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedServer {
+    pub id: String,
+    pub folder: String, // folder under VoidLink/servers, used as the server's route
+    pub name: String,
+    pub path: String,
+    pub java_path: String,
+    pub java_downloaded: bool,
+}
+
+// Metadata the frontend keeps in server.json
+pub(crate) fn read_server_meta(server_dir: &Path) -> Result<serde_json::Value, String> {
+    let content = fs::read_to_string(server_dir.join("server.json"))
+        .map_err(|e| format!("Failed to read server.json: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid server.json: {}", e))
+}
+
+fn meta_str<'a>(meta: &'a serde_json::Value, pointer: &str) -> Option<&'a str> {
+    meta.pointer(pointer).and_then(|v| v.as_str()).filter(|s| !s.is_empty())
+}
+
+// Same folder naming as servers created from the UI: <safe-name>-<8 hex chars>
+fn safe_name(name: &str) -> String {
+    let mut safe = String::new();
+    for c in name.to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            safe.push(c);
+        } else if !safe.ends_with('-') {
+            safe.push('-');
+        }
+    }
+    let safe = safe.trim_matches('-');
+    if safe.is_empty() { "untitled".to_string() } else { safe.to_string() }
+}
+
+fn short_id() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let duration = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("{:08x}", (duration.as_secs() as u32) ^ duration.subsec_nanos())
+}
+
+fn servers_dir() -> Result<PathBuf, String> {
+    let docs = dirs::document_dir().ok_or("Could not find Documents directory")?;
+    Ok(docs.join("VoidLink").join("servers"))
+}
+
+/// Export a whole server into a single bundle as a cancellable job
+#[tauri::command]
+pub async fn server_export_cmd(
+    app: tauri::AppHandle,
+    server_path: String,
+    destination: String,
+    exclude_patterns: Option<Vec<String>>,
+) -> Result<BundleManifest, String> {
+    let handle = tauri::async_runtime::spawn_blocking(move || {
+        jobs::run(&app, "export", &server_path, |job| {
+            export_server(&app, job, &server_path, Path::new(&destination), exclude_patterns)
+        })
+    });
+
+    handle.await.map_err(|e| e.to_string())?
+}
+
+fn export_server(
+    app: &tauri::AppHandle,
+    job: &Job,
+    server_path: &str,
+    destination: &Path,
+    exclude_patterns: Option<Vec<String>>,
+) -> Result<BundleManifest, String> {
+    let server_dir = PathBuf::from(server_path);
+    let meta = read_server_meta(&server_dir)?;
+
+    let patterns = exclude_patterns
+        .unwrap_or_else(|| BUNDLE_EXCLUDE_PATTERNS.iter().map(|p| p.to_string()).collect());
+    let filter = BackupFilter::new(&server_dir, &patterns)?;
+
+    // Freeze saving of a running world like a hot backup, so region files are not copied mid-write
+    let server_id = server_dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let rcon = RconCredentials::from_server_dir(&server_dir);
+    let mut saving_disabled = false;
+    if server::is_server_dir_running(&server_id, &server_dir) {
+        match &rcon {
+            Some(credentials) => {
+                let (consistency, disabled) = backup::begin_hot_backup(&server_id, &server_dir, credentials);
+                if consistency != BackupConsistency::HotConsistent {
+                    log::warn!("Could not flush {} before exporting, the world may be inconsistent", server_id);
+                }
+                saving_disabled = disabled;
+            }
+            None => log::warn!("{} is running without RCON, exporting without flushing the world", server_id),
+        }
+    }
+
+    let result = archive_server(job, &server_dir, destination, &filter);
+
+    // Always re-enable saving, even if archiving failed
+    if saving_disabled {
+        if let Some(credentials) = &rcon {
+            backup::end_hot_backup(&server_id, credentials);
+        }
+    }
+    let (total_size_bytes, written) = result?;
+
+    let mut files = written.entries;
+    for file in &mut files {
+        if let Some(relative) = file.path.strip_prefix(SERVER_PREFIX) {
+            file.path = relative.to_string();
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let requirement = java_requirement::detect_requirement(&server_dir);
+    let manifest = BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        exported_at: chrono::Local::now().to_rfc3339(),
+        app_version: app.package_info().version.to_string(),
+        server_name: meta_str(&meta, "/name").unwrap_or("Server").to_string(),
+        mc_version: requirement.mc_version
+            .or_else(|| meta_str(&meta, "/version").map(str::to_string))
+            .unwrap_or_default(),
+        loader: requirement.loader,
+        java_major: requirement.recommended_major,
+        jar_file: meta_str(&meta, "/jarFile").unwrap_or_default().to_string(),
+        file_count: files.len() as u64,
+        total_size_bytes,
+        files,
+    };
+
+    if let Err(e) = append_manifest(destination, &manifest) {
+        let _ = fs::remove_file(destination);
+        return Err(e);
+    }
+
+    log::info!(
+        "Exported {} ({} files, {} bytes) to {:?}",
+        manifest.server_name, manifest.file_count, manifest.total_size_bytes, destination
+    );
+    Ok(manifest)
+}
+
+// Zip every top-level file and folder the filter lets through below SERVER_PREFIX
+fn archive_server(
+    job: &Job,
+    server_dir: &Path,
+    destination: &Path,
+    filter: &BackupFilter,
+) -> Result<(u64, EntryManifest), String> {
+    let top_level: Vec<String> = fs::read_dir(server_dir)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+
+    let mut entries = backup_filter::collect_entries(server_dir, &top_level, filter, |_| {});
+    for entry in &mut entries {
+        entry.relative_path = format!("{}{}", SERVER_PREFIX, entry.relative_path);
+    }
+
+    let progress = ProgressTracker::new(entries.iter().map(|e| e.size).sum(), job.cancel_flag(), |progress| {
+        job.progress(JobProgress {
+            done: progress.bytes_done,
+            total: progress.bytes_total,
+            message: progress.current_file,
+            eta_secs: progress.eta_secs,
+        });
+    });
+    backup_archive::write_zip(destination, &entries, BackupCompression::default(), &progress)
+}
+
+fn append_manifest(destination: &Path, manifest: &BundleManifest) -> Result<(), String> {
+    let file = OpenOptions::new().read(true).write(true).open(destination).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new_append(file).map_err(|e| e.to_string())?;
+    zip.start_file(MANIFEST_NAME, FileOptions::default()).map_err(|e| e.to_string())?;
+    let content = serde_json::to_vec_pretty(manifest).map_err(|e| e.to_string())?;
+    zip.write_all(&content).map_err(|e| e.to_string())?;
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn read_manifest(archive: &mut ZipArchive<File>) -> Result<BundleManifest, String> {
+    let mut file = archive.by_name(MANIFEST_NAME)
+        .map_err(|_| "Not a VoidLink server bundle (manifest missing)".to_string())?;
+    let mut content = String::new();
+    file.read_to_string(&mut content).map_err(|e| e.to_string())?;
+    let manifest: BundleManifest = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid bundle manifest: {}", e))?;
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(format!(
+            "Bundle format {} is newer than this version of VoidLink supports",
+            manifest.format_version
+        ));
+    }
+    Ok(manifest)
+}
+
+fn open_bundle(bundle_path: &str) -> Result<ZipArchive<File>, String> {
+    let file = File::open(bundle_path).map_err(|e| format!("Failed to open bundle: {}", e))?;
+    ZipArchive::new(file).map_err(|e| format!("Failed to read bundle: {}", e))
+}
+
+/// Read the manifest of a bundle without importing it
+#[tauri::command]
+pub fn server_bundle_inspect_cmd(bundle_path: String) -> Result<BundleManifest, String> {
+    read_manifest(&mut open_bundle(&bundle_path)?)
+}
+
+/// Recreate a server from a bundle as a cancellable job, downloading its Java if no matching
+/// installation is found
+#[tauri::command]
+pub async fn server_import_cmd(
+    app: tauri::AppHandle,
+    bundle_path: String,
+    name: Option<String>,
+) -> Result<ImportedServer, String> {
+    let handle = tauri::async_runtime::spawn_blocking(move || {
+        jobs::run(&app, "import", &bundle_path, |job| import_server(&app, job, &bundle_path, name))
+    });
+
+    handle.await.map_err(|e| e.to_string())?
+}
+
+fn import_server(
+    app: &tauri::AppHandle,
+    job: &Job,
+    bundle_path: &str,
+    name: Option<String>,
+) -> Result<ImportedServer, String> {
+    let mut archive = open_bundle(bundle_path)?;
+    let manifest = read_manifest(&mut archive)?;
+    let name = name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| manifest.server_name.clone());
+
+    let id = short_id();
+    let folder = format!("{}-{}", safe_name(&name), id);
+    let server_dir = servers_dir()?.join(&folder);
+    fs::create_dir_all(&server_dir).map_err(|e| e.to_string())?;
+
+    // Leave nothing behind if the import fails or is cancelled
+    match import_into(app, job, &mut archive, &manifest, &server_dir, &id, &folder, &name) {
+        Ok(imported) => {
+            log::info!("Imported {} from {} into {:?}", name, bundle_path, server_dir);
+            Ok(imported)
+        }
+        Err(e) => {
+            let _ = fs::remove_dir_all(&server_dir);
+            Err(e)
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn import_into(
+    app: &tauri::AppHandle,
+    job: &Job,
+    archive: &mut ZipArchive<File>,
+    manifest: &BundleManifest,
+    server_dir: &Path,
+    id: &str,
+    folder: &str,
+    name: &str,
+) -> Result<ImportedServer, String> {
+    extract_server(job, archive, manifest, server_dir)?;
+    job.check_cancelled()?;
+
+    let (java_path, java_downloaded) = ensure_java(app, job, server_dir)?;
+
+    // Point the metadata at its new home and the Java of this machine
+    let mut meta = read_server_meta(server_dir)?;
+    let path = format!("VoidLink/servers/{}", folder);
+    meta["id"] = serde_json::json!(id);
+    meta["name"] = serde_json::json!(name);
+    meta["path"] = serde_json::json!(path);
+    if !meta["javaSettings"].is_object() {
+        meta["javaSettings"] = serde_json::json!({ "memory": 4, "flags": "" }); // GB, like the create form
+    }
+    meta["javaSettings"]["path"] = serde_json::json!(java_path);
+    let content = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
+    fs::write(server_dir.join("server.json"), content).map_err(|e| e.to_string())?;
+
+    Ok(ImportedServer {
+        id: id.to_string(),
+        folder: folder.to_string(),
+        name: name.to_string(),
+        path,
+        java_path,
+        java_downloaded,
+    })
+}
+
+// Extract the server files, checking each against the size and hash in the manifest
+fn extract_server(
+    job: &Job,
+    archive: &mut ZipArchive<File>,
+    manifest: &BundleManifest,
+    server_dir: &Path,
+) -> Result<(), String> {
+    let expected: HashMap<&str, &ManifestEntry> =
+        manifest.files.iter().map(|f| (f.path.as_str(), f)).collect();
+    let mut done = 0u64;
+    let mut extracted = 0usize;
+
+    for i in 0..archive.len() {
+        job.check_cancelled()?;
+        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+        let name = file.name().to_string();
+        let Some(relative) = name.strip_prefix(SERVER_PREFIX).map(|r| r.trim_end_matches('/')) else { continue };
+        if relative.is_empty() {
+            continue;
+        }
+        let enclosed = file.enclosed_name()
+            .and_then(|p| p.strip_prefix(SERVER_PREFIX).ok())
+            .map(Path::to_path_buf)
+            .ok_or_else(|| format!("Unsafe path in bundle: {}", file.name()))?;
+        let out_path = server_dir.join(enclosed);
+
+        if file.is_dir() {
+            fs::create_dir_all(&out_path).map_err(|e| e.to_string())?;
+            continue;
+        }
+        let entry = expected.get(relative)
+            .ok_or_else(|| format!("File not listed in the bundle manifest: {}", relative))?;
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let mut outfile = File::create(&out_path).map_err(|e| e.to_string())?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut size = 0u64;
+        loop {
+            let read = file.read(&mut buffer).map_err(|e| format!("Failed to extract {}: {}", relative, e))?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            outfile.write_all(&buffer[..read]).map_err(|e| e.to_string())?;
+            size += read as u64;
+            done += read as u64;
+            job.progress(JobProgress {
+                done,
+                total: manifest.total_size_bytes,
+                message: relative.to_string(),
+                eta_secs: None,
+            });
+        }
+        if size != entry.size || to_hex(&hasher.finalize()) != entry.sha256 {
+            return Err(format!("Corrupt file in bundle: {}", relative));
+        }
+        extracted += 1;
+    }
+
+    if extracted != manifest.files.len() {
+        return Err(format!(
+            "Bundle is incomplete: {} of {} files present",
+            extracted, manifest.files.len()
+        ));
+    }
+    if !server_dir.join("server.json").exists() {
+        return Err("Bundle does not contain server.json".to_string());
+    }
+    Ok(())
+}
+
+// Find an installed Java the server accepts, downloading the recommended one if there is none.
+// Returns the path to the java executable and whether it was downloaded.
+fn ensure_java(app: &tauri::AppHandle, job: &Job, server_dir: &Path) -> Result<(String, bool), String> {
+    let requirement = java_requirement::detect_requirement(server_dir);
+    if let Some(installation) = java_requirement::best_installation(&java::detect_installations(app), &requirement) {
+        return Ok((installation.path, false));
+    }
+
+    let major = requirement.recommended_major;
+    log::info!("No Java {} found, downloading it", major);
+    let install_dir = app.path().app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("java")
+        .join(major.to_string());
+    let path = java::download_and_install_java(app, major, None, install_dir.to_string_lossy().to_string(), job)?;
+    Ok((path, true))
+}
@@ -0,0 +1,505 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
+use tauri::Emitter;
+
+use crate::java_requirement;
+use crate::metrics_store;
+use crate::rcon::{BlockingSession, RconCredentials};
+
+// In-game performance read over RCON. Every server software reports it differently, so each
+// registered server gets a list of command dialects to try (Spark first when it is installed),
+// and the first one that answers is used until it stops working. A poll uses one RCON
+// connection for all of its commands.
+//
+// Vanilla RCON joins the messages of a command without a separator, so the parsers search
+// for markers in the text instead of going line by line.
+
+const PERF_POLL_INTERVAL_SECS: u64 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PerfSource {
+    Spark,
+    Paper,
+    Forge,
+    NeoForge,
+    Vanilla,
+    Debug, // vanilla before `tick query`, measured between two polls
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerPerformance {
+    pub timestamp: i64, // unix millis
+    pub tps: Option<f32>,
+    pub mspt: Option<f32>, // milliseconds per tick
+    pub entities: Option<u32>,
+    pub source: PerfSource, // where tps and mspt came from
+}
+
+struct PerfTarget {
+    credentials: RconCredentials,
+    server_dir: PathBuf,
+    dialects: Vec<PerfSource>,
+    working: Option<PerfSource>,
+    // When the tick profiler was started for the next `debug stop`
+    debug_since: Option<SystemTime>,
+    latest: Option<ServerPerformance>,
+}
+
+static TARGETS: LazyLock<Mutex<HashMap<String, PerfTarget>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub fn start(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(PERF_POLL_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let app = app.clone();
+            // RCON calls block on the async runtime, so they must not run on it
+            let _ = tauri::async_runtime::spawn_blocking(move || poll_all(&app)).await;
+        }
+    });
+}
+
+// Dialects worth trying for a server, most detailed first
+fn detect_dialects(server_dir: &Path) -> Vec<PerfSource> {
+    let mut dialects = Vec::new();
+    if has_spark(server_dir) {
+        dialects.push(PerfSource::Spark);
+    }
+    match java_requirement::detect_requirement(server_dir).loader.as_str() {
+        "paper" | "purpur" | "spigot" | "folia" => dialects.push(PerfSource::Paper),
+        "forge" => dialects.push(PerfSource::Forge),
+        "neoforge" => dialects.push(PerfSource::NeoForge),
+        _ => {}
+    }
+    // `tick query` (1.20.3+) works on every loader, `debug` on older versions
+    dialects.push(PerfSource::Vanilla);
+    dialects.push(PerfSource::Debug);
+    dialects
+}
+
+fn has_spark(server_dir: &Path) -> bool {
+    ["plugins", "mods"].iter().any(|dir| {
+        fs::read_dir(server_dir.join(dir)).into_iter().flatten().flatten().any(|entry| {
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            name.starts_with("spark") && name.ends_with(".jar")
+        })
+    })
+}
+
+fn poll_all(app: &tauri::AppHandle) {
+    let targets: Vec<_> = match TARGETS.lock() {
+        Ok(targets) => targets.iter()
+            .map(|(id, target)| {
+                // The dialect that worked last time goes first
+                let mut dialects = target.dialects.clone();
+                if let Some(working) = target.working {
+                    dialects.retain(|d| *d != working);
+                    dialects.insert(0, working);
+                }
+                (id.clone(), target.credentials.clone(), target.server_dir.clone(), dialects, target.debug_since)
+            })
+            .collect(),
+        Err(_) => return,
+    };
+
+    for (server_id, credentials, server_dir, dialects, mut debug_since) in targets {
+        let performance = probe(&credentials, &server_dir, &dialects, &mut debug_since);
+
+        if let Ok(mut targets) = TARGETS.lock() {
+            if let Some(target) = targets.get_mut(&server_id) {
+                target.debug_since = debug_since;
+                if let Some(performance) = &performance {
+                    target.working = Some(performance.source);
+                    target.latest = Some(performance.clone());
+                }
+            }
+        }
+        let Some(performance) = performance else { continue };
+        metrics_store::report_game_stats(&server_id, performance.tps, None);
+        let _ = app.emit("server-performance", serde_json::json!({
+            "server_id": server_id,
+            "performance": performance
+        }));
+    }
+}
+
+fn probe(
+    credentials: &RconCredentials,
+    server_dir: &Path,
+    dialects: &[PerfSource],
+    debug_since: &mut Option<SystemTime>,
+) -> Option<ServerPerformance> {
+    // RCON is down (server stopped or starting), no dialect can answer
+    let mut session = match BlockingSession::connect(credentials) {
+        Ok(session) => session,
+        Err(e) => {
+            log::debug!("Performance probe failed: {}", e);
+            return None;
+        }
+    };
+
+    for &dialect in dialects {
+        match probe_dialect(&mut session, server_dir, dialect, debug_since) {
+            Ok(Some((tps, mspt))) => {
+                let entities = session.cmd("execute if entity @e")
+                    .ok()
+                    .and_then(|response| parse_entity_count(&response));
+                return Some(ServerPerformance {
+                    timestamp: chrono::Local::now().timestamp_millis(),
+                    tps,
+                    mspt,
+                    entities,
+                    source: dialect,
+                });
+            }
+            Ok(None) => continue,
+            // The connection broke, the other dialects would fail the same way
+            Err(e) => {
+                log::debug!("Performance probe failed: {}", e);
+                return None;
+            }
+        }
+    }
+    None
+}
+
+// (tps, mspt) when the server understood the command
+fn probe_dialect(
+    session: &mut BlockingSession,
+    server_dir: &Path,
+    dialect: PerfSource,
+    debug_since: &mut Option<SystemTime>,
+) -> Result<Option<(Option<f32>, Option<f32>)>, String> {
+    let result = match dialect {
+        PerfSource::Spark => parse_spark_tps(&session.cmd("spark tps")?),
+        PerfSource::Paper => {
+            let tps = parse_paper_tps(&session.cmd("tps")?);
+            // `mspt` was added in Paper 1.16.5
+            let mspt = match tps {
+                Some(_) => parse_paper_mspt(&session.cmd("mspt")?),
+                None => None,
+            };
+            tps.map(|tps| (Some(tps), mspt))
+        }
+        PerfSource::Forge => parse_forge_tps(&session.cmd("forge tps")?),
+        PerfSource::NeoForge => parse_forge_tps(&session.cmd("neoforge tps")?),
+        PerfSource::Vanilla => parse_tick_query(&session.cmd("tick query")?),
+        PerfSource::Debug => {
+            // TPS of the ticks since the previous poll, none on the first one
+            let tps = match debug_since.take() {
+                Some(since) => {
+                    let tps = parse_debug_stop(&session.cmd("debug stop")?);
+                    remove_profile_results(server_dir, since);
+                    tps
+                }
+                None => None,
+            };
+            if parse_debug_start(&session.cmd("debug start")?) {
+                *debug_since = Some(SystemTime::now());
+                Some((tps, None))
+            } else {
+                None
+            }
+        }
+    };
+    Ok(result)
+}
+
+// `debug stop` saves a report to debug/ every time, only the ones this poller caused are removed
+fn remove_profile_results(server_dir: &Path, since: SystemTime) {
+    let Ok(entries) = fs::read_dir(server_dir.join("debug")) else { return };
+    for entry in entries.flatten() {
+        let ours = entry.file_name().to_string_lossy().starts_with("profile-results-")
+            && entry.metadata().and_then(|m| m.modified()).is_ok_and(|modified| modified >= since);
+        if ours {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+// === Parsers ===
+
+// Drop Minecraft formatting codes (§a, §l, ...)
+fn strip_formatting(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+// Leading number of a string, e.g. "19.97, 20.0" -> 19.97, "*20.0" -> 20.0
+fn leading_number(text: &str) -> Option<f32> {
+    let text = text.trim_start().trim_start_matches('*');
+    let end = text.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(text.len());
+    text[..end].parse().ok()
+}
+
+// Number right after a marker, e.g. after "Mean TPS: "
+fn number_after(text: &str, marker: &str) -> Option<f32> {
+    let start = text.find(marker)? + marker.len();
+    leading_number(&text[start..])
+}
+
+// Values following a "...:" header, past the prefix Spark puts in front of every line ("[⚡]")
+fn after_header(text: &str) -> Option<&str> {
+    let values = &text[text.find(':')? + 1..];
+    let first = values.find(|c: char| c.is_ascii_digit() || c == '*')?;
+    Some(&values[first..])
+}
+
+/// Paper, Purpur and Spigot `tps`: "TPS from last 1m, 5m, 15m: 20.0, *20.0, 19.98"
+pub fn parse_paper_tps(response: &str) -> Option<f32> {
+    let text = strip_formatting(response);
+    let values = &text[text.find("TPS from last")?..];
+    number_after(values, ": ")
+}
+
+/// Paper `mspt`: "Server tick times (avg/min/max) from last 5s, 10s, 1m: ◴ 3.2/1.1/8.0, ..."
+/// gives the 5 second average
+pub fn parse_paper_mspt(response: &str) -> Option<f32> {
+    let text = strip_formatting(response);
+    let header_end = text.find("1m:")? + "1m:".len();
+    let values = &text[header_end..];
+    let first_digit = values.find(|c: char| c.is_ascii_digit())?;
+    leading_number(&values[first_digit..])
+}
+
+/// Spark `spark tps`: the 1 minute TPS and the median tick duration of the last 10 seconds
+/// ("TPS from last 5s, 10s, 1m, 5m, 15m: *20.0, *20.0, *20.0, ... Tick durations
+/// (min/med/95%ile/max ms) from last 10s, 1m: 1.2/2.3/5.0/10.1; ...")
+pub fn parse_spark_tps(response: &str) -> Option<(Option<f32>, Option<f32>)> {
+    let text = strip_formatting(response);
+    let tps_start = text.find("TPS from last")?;
+    let tps_values = &text[tps_start..];
+    let tps_values = after_header(tps_values)?;
+    let tps: Vec<f32> = tps_values.split(',').map_while(leading_number).collect();
+    let tps = tps.get(2).or(tps.first()).copied();
+
+    let mspt = text.find("Tick durations").and_then(|start| {
+        let durations = &text[start..];
+        after_header(durations)?.split('/').nth(1).and_then(leading_number)
+    });
+
+    tps.map(|tps| (Some(tps), mspt))
+}
+
+/// Forge `forge tps` ("Overall: Mean tick time: 1.234 ms. Mean TPS: 20.000") and NeoForge
+/// `neoforge tps` ("Overall: 20.000 TPS (1.234 ms/tick)")
+pub fn parse_forge_tps(response: &str) -> Option<(Option<f32>, Option<f32>)> {
+    let text = strip_formatting(response);
+    let overall = &text[text.find("Overall")?..];
+    let overall = &overall[overall.find(':')? + 1..];
+
+    if overall.contains("Mean TPS") {
+        let mspt = number_after(overall, "Mean tick time: ");
+        let tps = number_after(overall, "Mean TPS: ")?;
+        return Some((Some(tps), mspt));
+    }
+    let tps = leading_number(overall)?;
+    let mspt = number_after(overall, "(");
+    Some((Some(tps), mspt))
+}
+
+/// Vanilla `tick query` (1.20.3+): "Target tick rate: 20.0 per second. Average time per tick:
+/// 2.3ms (Target: 50.0ms)". TPS is the target rate unless ticks take longer than that allows.
+pub fn parse_tick_query(response: &str) -> Option<(Option<f32>, Option<f32>)> {
+    let text = strip_formatting(response);
+    let mspt = number_after(&text, "Average time per tick: ")?;
+    let target = number_after(&text, "Target tick rate: ").unwrap_or(20.0);
+    let tps = if mspt > 0.0 { target.min(1000.0 / mspt) } else { target };
+    Some((Some(tps), Some(mspt)))
+}
+
+/// Vanilla `debug stop`: "Stopped debug profiling after 15.02 seconds and 300 ticks (19.97
+/// ticks per second)", from 1.17 on "... 15.01 second(s) and 300 tick(s) (19.99 tick(s) per second)"
+pub fn parse_debug_stop(response: &str) -> Option<f32> {
+    let text = strip_formatting(response);
+    let stopped = &text[text.find("Stopped")?..];
+    stopped.match_indices('(').find_map(|(i, _)| leading_number(&stopped[i + 1..]))
+}
+
+/// Vanilla `debug start`: "Started debug profiling" ("Started tick profiling" from 1.17), or
+/// "The tick profiler is already started"
+pub fn parse_debug_start(response: &str) -> bool {
+    let text = strip_formatting(response);
+    text.contains("Started") || text.contains("already started")
+}
+
+/// `execute if entity @e`: "Test passed, count: 123", or "Test failed" when there are none
+pub fn parse_entity_count(response: &str) -> Option<u32> {
+    let text = strip_formatting(response);
+    if text.contains("Test failed") {
+        return Some(0);
+    }
+    number_after(&text, "count: ").map(|count| count as u32)
+}
+
+// === Commands ===
+
+/// Poll a server's in-game performance over RCON while it is registered
+#[tauri::command]
+pub fn server_perf_register(server_id: String, server_path: String, rcon: RconCredentials) -> Result<(), String> {
+    let dialects = detect_dialects(Path::new(&server_path));
+    log::info!("Polling performance of {} with {:?}", server_id, dialects);
+    let mut targets = TARGETS.lock().map_err(|e| e.to_string())?;
+    targets.insert(server_id, PerfTarget {
+        credentials: rcon,
+        server_dir: PathBuf::from(server_path),
+        dialects,
+        working: None,
+        debug_since: None,
+        latest: None,
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn server_perf_unregister(server_id: String) -> Result<(), String> {
+    let mut targets = TARGETS.lock().map_err(|e| e.to_string())?;
+    targets.remove(&server_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn server_perf_latest(server_id: String) -> Result<Option<ServerPerformance>, String> {
+    let targets = TARGETS.lock().map_err(|e| e.to_string())?;
+    Ok(targets.get(&server_id).and_then(|t| t.latest.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Responses captured over RCON, formatting codes included
+
+    const PAPER_TPS: &str = "§6TPS from last 1m, 5m, 15m: §a*20.0§r§6, §a*20.0§r§6, §a19.98";
+    const PAPER_TPS_LAGGING: &str = "§6TPS from last 1m, 5m, 15m: §e17.52§r§6, §a19.41§r§6, §a19.87";
+    const PAPER_MSPT: &str = "§6Server tick times §e(§7avg§e/§7min§e/§7max§e)§6 from last 5s§7,§6 10s§7,§6 1m§e:\n\
+        §6◴ §a3.2§7/§a1.1§7/§a8.0§e, §a3.4§7/§a1.1§7/§a11.9§e, §a3.6§7/§a0.9§7/§a42.7";
+
+    const FORGE_TPS: &str = "Dim minecraft:overworld (minecraft:overworld): Mean tick time: 2.173 ms. Mean TPS: 20.000\n\
+        Dim minecraft:the_nether (minecraft:the_nether): Mean tick time: 0.062 ms. Mean TPS: 20.000\n\
+        Dim minecraft:the_end (minecraft:the_end): Mean tick time: 0.041 ms. Mean TPS: 20.000\n\
+        Overall: Mean tick time: 58.312 ms. Mean TPS: 17.149";
+    const NEOFORGE_TPS: &str = "minecraft:overworld: 20.000 TPS (1.876 ms/tick)\n\
+        minecraft:the_nether: 20.000 TPS (0.054 ms/tick)\n\
+        minecraft:the_end: 20.000 TPS (0.038 ms/tick)\n\
+        Overall: 20.000 TPS (2.125 ms/tick)";
+
+    const SPARK_TPS: &str = "§8[§e⚡§8] §7TPS from last 5s, 10s, 1m, 5m, 15m:\n\
+        §8[§e⚡§8] §a*20.0§8, §a*20.0§8, §a19.96§8, §a*20.0§8, §a*20.0\n\
+        §8[§e⚡§8] \n\
+        §8[§e⚡§8] §7Tick durations (min/med/95%ile/max ms) from last 10s, 1m:\n\
+        §8[§e⚡§8] §a1.1§7/§a2.4§7/§a5.8§7/§a11.3§8;  §a0.9§7/§a2.3§7/§a6.2§7/§a48.0\n\
+        §8[§e⚡§8] \n\
+        §8[§e⚡§8] §7CPU usage from last 10s, 1m, 15m:\n\
+        §8[§e⚡§8] §a12%§8, §a10%§8, §a9%§8  §7(system)";
+
+    const TICK_QUERY: &str = "The game is running normally\n\
+        Target tick rate: 20.0 per second.\n\
+        Average time per tick: 2.3ms (Target: 50.0ms)\
+        Percentiles: P50: 2.1ms P95: 3.9ms P99: 5.6ms, sample: 100";
+    const TICK_QUERY_OVERLOADED: &str = "The game is running normally\n\
+        Target tick rate: 20.0 per second.\n\
+        Average time per tick: 62.5ms (Target: 50.0ms)\
+        Percentiles: P50: 60.1ms P95: 71.0ms P99: 80.2ms, sample: 100";
+
+    const DEBUG_STOP_LEGACY: &str = "Stopped debug profiling after 15.02 seconds and 300 ticks (19.97 ticks per second)";
+    const DEBUG_STOP: &str = "Stopped tick profiling after 15.01 second(s) and 286 tick(s) (19.05 tick(s) per second)";
+
+    const UNKNOWN_COMMAND: &str = "Unknown or incomplete command, see below for error<--[HERE]";
+
+    fn approx(value: Option<f32>, expected: f32) -> bool {
+        value.is_some_and(|v| (v - expected).abs() < 1e-3)
+    }
+
+    #[test]
+    fn paper() {
+        assert!(approx(parse_paper_tps(PAPER_TPS), 20.0));
+        assert!(approx(parse_paper_tps(PAPER_TPS_LAGGING), 17.52));
+        assert!(approx(parse_paper_mspt(PAPER_MSPT), 3.2));
+        assert_eq!(parse_paper_tps(UNKNOWN_COMMAND), None);
+    }
+
+    #[test]
+    fn forge_uses_the_overall_line() {
+        let (tps, mspt) = parse_forge_tps(FORGE_TPS).unwrap();
+        assert!(approx(tps, 17.149));
+        assert!(approx(mspt, 58.312));
+
+        let (tps, mspt) = parse_forge_tps(NEOFORGE_TPS).unwrap();
+        assert!(approx(tps, 20.0));
+        assert!(approx(mspt, 2.125));
+
+        assert_eq!(parse_forge_tps(UNKNOWN_COMMAND), None);
+    }
+
+    #[test]
+    fn spark_reads_past_the_line_prefix() {
+        let (tps, mspt) = parse_spark_tps(SPARK_TPS).unwrap();
+        assert!(approx(tps, 19.96)); // 1 minute
+        assert!(approx(mspt, 2.4)); // median of the last 10 seconds
+        assert_eq!(parse_spark_tps(UNKNOWN_COMMAND), None);
+    }
+
+    #[test]
+    fn tick_query() {
+        let (tps, mspt) = parse_tick_query(TICK_QUERY).unwrap();
+        assert!(approx(tps, 20.0));
+        assert!(approx(mspt, 2.3));
+
+        // Ticks slower than the target rate allows
+        let (tps, mspt) = parse_tick_query(TICK_QUERY_OVERLOADED).unwrap();
+        assert!(approx(tps, 16.0));
+        assert!(approx(mspt, 62.5));
+
+        assert_eq!(parse_tick_query(UNKNOWN_COMMAND), None);
+    }
+
+    #[test]
+    fn debug_profiling() {
+        assert!(approx(parse_debug_stop(DEBUG_STOP_LEGACY), 19.97));
+        assert!(approx(parse_debug_stop(DEBUG_STOP), 19.05));
+        assert_eq!(parse_debug_stop("The tick profiler hasn't started"), None);
+
+        assert!(parse_debug_start("Started debug profiling"));
+        assert!(parse_debug_start("Started tick profiling"));
+        assert!(parse_debug_start("The tick profiler is already started"));
+        assert!(!parse_debug_start(UNKNOWN_COMMAND));
+    }
+
+    #[test]
+    fn entity_count() {
+        assert_eq!(parse_entity_count("Test passed, count: 137"), Some(137));
+        assert_eq!(parse_entity_count("Test failed"), Some(0));
+        assert_eq!(parse_entity_count(UNKNOWN_COMMAND), None);
+    }
+
+    #[test]
+    fn profile_results_of_other_runs_are_kept() {
+        let dir = std::env::temp_dir().join(format!("voidlink-perf-{}", std::process::id()));
+        let debug = dir.join("debug");
+        fs::create_dir_all(&debug).unwrap();
+        fs::write(debug.join("profile-results-2024-01-01_10.00.00.txt"), "old").unwrap();
+        let since = SystemTime::now() + Duration::from_secs(3600);
+        remove_profile_results(&dir, since);
+        assert!(debug.join("profile-results-2024-01-01_10.00.00.txt").exists());
+
+        fs::write(debug.join("notes.txt"), "user file").unwrap();
+        remove_profile_results(&dir, SystemTime::UNIX_EPOCH);
+        assert!(!debug.join("profile-results-2024-01-01_10.00.00.txt").exists());
+        assert!(debug.join("notes.txt").exists());
+        fs::remove_dir_all(&dir).ok();
+    }
+}